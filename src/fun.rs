@@ -29,7 +29,7 @@ impl Default for DynamicPanel {
 
 impl ui::Panel for DynamicPanel {
     fn render(&mut self) -> ui::Render {
-        let ds = self.timer.delta_s() as f32;
+        let ds = self.timer.delta_s();
 
         self.a += ds * 2.;
         self.b += ds * 360.;
@@ -112,6 +112,9 @@ impl Default for FpsPanel {
 }
 
 impl ui::Panel for FpsPanel {
+    fn focusable(&self) -> bool {
+        false
+    }
     fn render(&mut self) -> ui::Render {
         let delta_s = self.timer.delta_s();
 
@@ -147,25 +150,13 @@ impl CounterPanel {
 
 impl ui::Panel for CounterPanel {
     fn tick(&mut self, key_events: &ui::InputStream) {
-        if key_events.contains(&InputEvent {
-            code: KeyCode::Up,
-            modifiers: KeyModifiers::NONE,
-        }) {
+        if key_events.contains(&InputEvent::new(KeyCode::Up, KeyModifiers::NONE)) {
             self.count += 1;
-        } else if key_events.contains(&InputEvent {
-            code: KeyCode::Down,
-            modifiers: KeyModifiers::NONE,
-        }) {
+        } else if key_events.contains(&InputEvent::new(KeyCode::Down, KeyModifiers::NONE)) {
             self.count -= 1;
-        } else if key_events.contains(&InputEvent {
-            code: KeyCode::Up,
-            modifiers: KeyModifiers::SHIFT,
-        }) {
+        } else if key_events.contains(&InputEvent::new(KeyCode::Up, KeyModifiers::SHIFT)) {
             self.count += 10;
-        } else if key_events.contains(&InputEvent {
-            code: KeyCode::Down,
-            modifiers: KeyModifiers::SHIFT,
-        }) {
+        } else if key_events.contains(&InputEvent::new(KeyCode::Down, KeyModifiers::SHIFT)) {
             self.count -= 10;
         }
     }
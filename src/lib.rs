@@ -1,23 +1,18 @@
 extern crate approx;
 
 use ansi_term::*;
-use itertools::Itertools;
 // use ansi_term_old::Color;
 // use crossterm::event::{KeyCode, KeyboardEnhancementFlags};
 use crate::math::*;
 // use crossterm::{cursor, terminal, QueueableCommand};
-use crossterm::terminal;
 #[allow(unused_imports)]
 use fun::*;
 use render_3d::*;
 // use std::io::{stdout, Write};
 use rasmus_lib::flags::TerminalFlags;
-use std::num::NonZeroUsize;
 // use std::panic::PanicInfo;
 
 // pub use rasmus_lib::flags::TERMINAL_FLAGS;
-pub use rasmus_lib::input::INPUT;
-pub use rasmus_lib::macros::*;
 pub use rasmus_lib::*;
 
 pub mod app;
@@ -26,7 +21,10 @@ pub mod rasmus_lib;
 pub mod render_3d;
 pub mod test_data;
 
+#[deprecated(note = "use the accent style of `ui::Theme::current()`")]
 pub const ACCENT_COLOR: Color = Color::Green;
+/// The theme config loaded by `run`, if it exists, see `ui::theme`.
+pub const THEME_CONFIG_PATH: &str = "theme.conf";
 // Mostly for debugging when you wan't to temporarily not add one to displayed indices
 pub const HUMAN_NUMBER_DIFFERENCE: usize = 1;
 
@@ -63,12 +61,36 @@ impl Drop for CleanUp {
 
 pub fn run() {
     let mut gui = ui::Gui::new();
+    // Lets the camera move smoothly while keys are held.
+    gui.enable_keyboard_enhancement();
 
     let fps_panel = FpsPanel::new();
-    gui.add_panel(uvec2(1, 1), fps_panel);
+    let fps_panel_ref = gui.add_panel(uvec2(1, 1), fps_panel);
+
+    // Diagnostics are shown in the log panel, as printing them would end up
+    // over the gui.
+    let log_buffer = ui::LogBuffer::new();
+    ui::BufferLogger::new(log_buffer.clone(), log::LevelFilter::Debug)
+        .install()
+        .expect("a logger was set already");
+    let log_panel_ref = gui.add_panel(uvec2(20, 1), ui::LogPanel::new(log_buffer.clone()));
+    gui.register_command("log.clear", "Clear the log", move |_panels| {
+        log_buffer.clear();
+    });
 
-    let debug_panel = ui::TextPanel::new("".to_owned());
-    let debug_panel_ref = gui.add_panel(uvec2(20, 1), debug_panel);
+    match ui::Theme::load(THEME_CONFIG_PATH) {
+        Ok(theme) => gui.set_theme(theme),
+        // Not having a theme config is fine.
+        Err(ui::ThemeError::Io(_)) => {}
+        Err(error) => log::warn!("{}: {}", THEME_CONFIG_PATH, error),
+    }
+    let mut theme_index = 0;
+    gui.register_command("theme.cycle", "Switch theme", move |_panels| {
+        theme_index = (theme_index + 1) % ui::BUILTIN_THEMES.len();
+        let name = ui::BUILTIN_THEMES[theme_index];
+        ui::Theme::set_current(ui::Theme::builtin(name).unwrap());
+        log::info!("switched to the {} theme", name);
+    });
 
     let mut scene_panel = render_3d::ScenePanel::new(render_3d::Scene::new());
 
@@ -83,6 +105,12 @@ pub fn run() {
 
     scene_panel.debug_object = Some(cube_ref);
 
+    let SceneObject::Object(suzanne) = scene_panel.scene.get_object(cube_ref).unwrap();
+    log::info!(
+        "loaded Suzanne, {} triangles",
+        suzanne.mesh.triangles_iter().count()
+    );
+
     {
         let scene = &mut scene_panel.scene;
 
@@ -118,62 +146,93 @@ pub fn run() {
         // panic!();
     }
 
-    let (width, height) = terminal::size().unwrap();
-
-    scene_panel.renderer.set_size(
-        NonZeroUsize::new(width as usize / 2).unwrap(),
-        NonZeroUsize::new(height as usize - 5).unwrap(),
-    );
-
-    let scene_panel_ref = gui.add_panel(uvec2(1, 6), scene_panel);
+    scene_panel.camera_controller = Some(CameraController::orbit(vec3(0., 0., 0.), 2.));
 
-    gui.add_ticker(move |panels, _inputs| {
-        let normals;
-        let triangles;
-        {
-            let scene_panel = panels
-                .get_mut(&scene_panel_ref)
-                .expect("couldn't get scene_panel")
-                .as_panel_mut::<render_3d::ScenePanel>()
-                .expect("couldn't convert scene_panel");
-
-            let scene = &mut scene_panel.scene;
+    // The renderer is resized to fill its part of the layout by the gui.
+    scene_panel.cell_size = gui
+        .capabilities()
+        .cell_size_or(capabilities::CellSize::FALLBACK);
 
-            let SceneObject::Object(debug_object) =
-                scene.get_object(cube_ref).expect("object dissapeared!");
+    let scene_panel_ref = gui.add_entity(
+        ui::PanelEntity::new(uvec2(1, 6), scene_panel)
+            .with_border(ui::Border::Rounded)
+            .with_title("Suzanne"),
+    );
 
-            triangles = debug_object
-                .mesh
-                .triangles_iter()
-                .map(|tri| tri.transform(&debug_object.transform))
-                .take(2)
-                .collect::<Vec<_>>();
+    gui.register_command("camera.reset", "Reset camera", move |panels| {
+        let scene_panel = panels
+            .get_mut(&scene_panel_ref)
+            .and_then(|panel| panel.as_panel_mut::<render_3d::ScenePanel>())
+            .expect("couldn't get scene_panel");
+        scene_panel.camera_controller = Some(CameraController::orbit(vec3(0., 0., 0.), 2.));
+    });
 
-            normals = triangles.iter().map(|tri| tri.normal()).collect::<Vec<_>>();
+    let inspector_ref = gui.add_entity(
+        ui::PanelEntity::new(uvec2(1, 6), render_3d::SceneInspector::new())
+            .with_border(ui::Border::Rounded)
+            .with_title("Inspector"),
+    );
+    // The inspector shows a snapshot of the scene, as it can't borrow the
+    // scene panel, and the changes made in it are applied here.
+    gui.add_ticker(move |panels, _| {
+        let edits = panels
+            .get_mut(&inspector_ref)
+            .and_then(|panel| panel.as_panel_mut::<render_3d::SceneInspector>())
+            .map(render_3d::SceneInspector::take_edits)
+            .unwrap_or_default();
+        let Some(scene_panel) = panels
+            .get_mut(&scene_panel_ref)
+            .and_then(|panel| panel.as_panel_mut::<render_3d::ScenePanel>())
+        else {
+            return;
+        };
+
+        for edit in edits {
+            // The camera controller places the camera every tick, and the
+            // animation rotates the debug object, which would undo the edit.
+            let moves_camera = edit.target == render_3d::EditTarget::Camera
+                && matches!(
+                    edit.property,
+                    render_3d::EditProperty::Position | render_3d::EditProperty::Rotation
+                );
+            if moves_camera && scene_panel.camera_controller.take().is_some() {
+                log::info!("camera controls are off until the camera is reset");
+            }
+            let rotates_object = edit.target != render_3d::EditTarget::Camera
+                && edit.property == render_3d::EditProperty::Rotation;
+            if rotates_object && scene_panel.debug_object.take().is_some() {
+                log::info!("stopped animating the rotation of the object");
+            }
+
+            edit.apply(&mut scene_panel.scene);
         }
+
+        let snapshot = render_3d::SceneSnapshot::new(&scene_panel.scene);
+        if let Some(inspector) = panels
+            .get_mut(&inspector_ref)
+            .and_then(|panel| panel.as_panel_mut::<render_3d::SceneInspector>())
         {
-            let debug_panel = panels
-                .get_mut(&debug_panel_ref)
-                .expect("couldn't get debug_panel")
-                .as_panel_mut::<ui::TextPanel>()
-                .expect("couldn't convert debug_panel");
-
-            let debug_text = normals
-                .into_iter()
-                .enumerate()
-                .map(|(i, normal)| format!("normal {}: {: >6.3}", i, normal))
-                .chain(
-                    triangles
-                        .into_iter()
-                        .enumerate()
-                        .map(|(i, tri)| format!("face {}: {: >6.3}", i, tri)),
-                )
-                .join("\n");
-
-            debug_panel.set_contents(debug_text);
+            inspector.update(snapshot);
         }
     });
 
+    gui.set_layout(ui::Layout::vertical(vec![
+        (
+            ui::Constraint::Fixed(5),
+            ui::Layout::horizontal(vec![
+                (ui::Constraint::Fixed(19), ui::Layout::panel(fps_panel_ref)),
+                (ui::Constraint::Flex(1), ui::Layout::panel(log_panel_ref)),
+            ]),
+        ),
+        (
+            ui::Constraint::Flex(1),
+            ui::Layout::horizontal(vec![
+                (ui::Constraint::Flex(1), ui::Layout::panel(scene_panel_ref)),
+                (ui::Constraint::Fixed(42), ui::Layout::panel(inspector_ref)),
+            ]),
+        ),
+    ]));
+
     // let alligator_panel = ui::TextPanel::new(test_data::ALLIGATOR_ART.to_owned());
 
     // let text = "\
@@ -198,9 +257,7 @@ pub fn run() {
     // gui.add_panel(uvec2(1, 4), alligator_panel);
     // gui.add_panel(uvec2(33, 20), counter_panel);
 
-    loop {
-        gui.tick();
-    }
+    gui.run();
 }
 
 // dyn testing stuff
@@ -7,12 +7,8 @@ fn main() {
 
     let _clean_up = CleanUp;
 
-    // Barely works for any terminals :(
-    // execute!(
-    //     io::stdout(),
-    //     event::PushKeyboardEnhancementFlags(event::KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
-    // )
-    // .expect("couldn't push keyboard enhancement flags");
+    // Keyboard enhancement is only enabled by the gui, for terminals that
+    // support it. See `Gui::enable_keyboard_enhancement`.
 
     flags::TerminalFlags::set_raw_mode(true);
 
@@ -80,7 +76,7 @@ fn main() {
     // ));
 }
 
-pub fn panic_handler(info: &std::panic::PanicInfo) {
+pub fn panic_handler(info: &std::panic::PanicHookInfo) {
     clean_up();
 
     print!("{}", info);
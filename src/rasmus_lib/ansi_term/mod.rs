@@ -4,6 +4,7 @@ pub use private::cursor;
 pub use private::flush_commands;
 pub use private::format;
 pub use private::line;
+pub use private::parse;
 pub use private::screen;
 
 pub use format::Color;
@@ -64,6 +64,13 @@ pub fn move_left(columns: u16) {
     }
 }
 
+pub fn get_move_right(columns: u16) -> String {
+    if columns > 0 {
+        return get_sequence(&format!("{}C", columns));
+    }
+    "".to_owned()
+}
+
 /// Move the cursor the specified amount of lines to the right.
 ///
 /// **Note:** this will only queue the command. You need to call `flush_commands`
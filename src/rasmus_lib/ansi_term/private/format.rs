@@ -1,7 +1,7 @@
 use super::get_sequence;
 use super::queue_sequence;
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum Color {
     Black,
     Red,
@@ -11,12 +11,48 @@ pub enum Color {
     Magenta,
     Cyan,
     White,
+    #[default]
     Default,
     ColorId(u8),
     RGB(u8, u8, u8),
 }
 
-#[derive(Clone, Copy)]
+impl Color {
+    /// Convert rgb colors to the closest color in the 256 color palette, for
+    /// terminals without truecolor support. Other colors are left as is.
+    ///
+    /// Source: https://en.wikipedia.org/wiki/ANSI_escape_code#8-bit
+    pub fn to_palette(self) -> Self {
+        let Color::RGB(r, g, b) = self else {
+            return self;
+        };
+
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+
+        // Use the grayscale ramp (232-255) for colors without saturation.
+        if max - min < 10 {
+            let average = (r as u16 + g as u16 + b as u16) / 3;
+            return match average {
+                0..=3 => Color::ColorId(16),
+                247..=255 => Color::ColorId(231),
+                _ => Color::ColorId(232 + ((average - 4) * 24 / 243) as u8),
+            };
+        }
+
+        fn to_cube(channel: u8) -> u8 {
+            match channel {
+                0..=47 => 0,
+                48..=114 => 1,
+                _ => (channel - 35) / 40,
+            }
+        }
+
+        Color::ColorId(16 + 36 * to_cube(r) + 6 * to_cube(g) + to_cube(b))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Format {
     Reset,
     Bold,
@@ -109,6 +145,27 @@ pub fn get_format(format: Format) -> String {
     get_sequence(format_str)
 }
 
+/// Get a single escape sequence applying all `formats`, in order.
+///
+/// This is shorter than concatenating the sequences returned by `get_format`.
+/// Returns an empty string if `formats` is empty.
+pub fn get_formats(formats: &[Format]) -> String {
+    if formats.is_empty() {
+        return String::new();
+    }
+
+    let params = formats
+        .iter()
+        .map(|format| match get_format_string(*format) {
+            FormatString::Static(str) => str.trim_end_matches('m').to_owned(),
+            FormatString::Dynamic(string) => string.trim_end_matches('m').to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(";");
+
+    get_sequence(&format!("{}m", params))
+}
+
 pub fn format_str(string: &str, formats: &[Format]) -> String {
     let mut result = String::new();
 
@@ -1,5 +1,10 @@
+use super::get_sequence;
 use super::queue_sequence;
 
+pub fn get_clear_to_end() -> String {
+    get_sequence("0K")
+}
+
 /// Clear from the current cursor position to the end of the line.
 ///
 /// **Note:** this will only queue the command. You need to call `flush_commands`
@@ -4,6 +4,7 @@ use std::io::Write;
 pub mod cursor;
 pub mod format;
 pub mod line;
+pub mod parse;
 pub mod screen;
 
 // pub use cursor;
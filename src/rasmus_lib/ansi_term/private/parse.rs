@@ -0,0 +1,155 @@
+//! Splitting of strings containing ansi escape sequences.
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// Printable text, possibly containing control characters like `\n`.
+    Text(&'a str),
+    /// Select graphic rendition (`CSI ... m`), with its numeric parameters.
+    ///
+    /// An empty parameter list is equivalent to a reset.
+    Sgr(Vec<u16>),
+    /// Any other control sequence (`CSI ...`), like cursor movement.
+    Csi { params: &'a str, final_byte: char },
+    /// Any other escape sequence (OSC, APC, DCS or a simple `ESC x`) in its
+    /// entirety, including the leading escape.
+    Other(&'a str),
+}
+
+/// Returns an iterator splitting `string` into text and escape sequences.
+///
+/// # Examples
+/// ```
+/// use terminal_renderer::ansi_term::parse::{tokenize, Token};
+///
+/// let tokens = tokenize("a\x1b[1;31mb\x1b[2Kc").collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     tokens,
+///     vec![
+///         Token::Text("a"),
+///         Token::Sgr(vec![1, 31]),
+///         Token::Text("b"),
+///         Token::Csi { params: "2", final_byte: 'K' },
+///         Token::Text("c"),
+///     ]
+/// );
+/// ```
+pub fn tokenize(string: &str) -> Tokens<'_> {
+    Tokens { rest: string }
+}
+
+pub struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        if !self.rest.starts_with(ESC) {
+            let end = self.rest.find(ESC).unwrap_or(self.rest.len());
+            let (text, rest) = self.rest.split_at(end);
+            self.rest = rest;
+            return Some(Token::Text(text));
+        }
+
+        let length = escape_length(self.rest);
+        let (sequence, rest) = self.rest.split_at(length);
+        self.rest = rest;
+
+        Some(classify(sequence))
+    }
+}
+
+/// Returns the length in bytes of the escape sequence at the start of
+/// `string`. Unterminated sequences span the rest of the string.
+fn escape_length(string: &str) -> usize {
+    let mut chars = string.char_indices().skip(1);
+
+    let Some((_, introducer)) = chars.next() else {
+        return string.len();
+    };
+
+    match introducer {
+        // CSI: parameters and intermediates, ended by a byte in `@..=~`.
+        '[' => {
+            for (index, char) in chars {
+                if ('@'..='~').contains(&char) {
+                    return index + char.len_utf8();
+                }
+            }
+            string.len()
+        }
+        // OSC, DCS, APC, PM and SOS: ended by ST (`ESC \`), or BEL for OSC.
+        ']' | 'P' | '_' | '^' | 'X' => {
+            let mut last_was_escape = false;
+            for (index, char) in chars {
+                if char == BEL && introducer == ']' {
+                    return index + 1;
+                }
+                if last_was_escape && char == '\\' {
+                    return index + 1;
+                }
+                last_was_escape = char == ESC;
+            }
+            string.len()
+        }
+        char => 1 + char.len_utf8(),
+    }
+}
+
+fn classify(sequence: &str) -> Token<'_> {
+    let Some(body) = sequence.strip_prefix("\x1b[") else {
+        return Token::Other(sequence);
+    };
+
+    let Some(final_byte) = body.chars().last() else {
+        return Token::Other(sequence);
+    };
+    let params = &body[..body.len() - final_byte.len_utf8()];
+
+    if final_byte == 'm'
+        && params
+            .chars()
+            .all(|char| char.is_ascii_digit() || char == ';')
+    {
+        let params = params
+            .split(';')
+            .filter(|param| !param.is_empty())
+            .map(|param| param.parse().unwrap_or(0))
+            .collect();
+
+        return Token::Sgr(params);
+    }
+
+    Token::Csi { params, final_byte }
+}
+
+mod tests {
+    #[test]
+    fn tokenize_other_sequences_test() {
+        use super::*;
+
+        let tokens =
+            tokenize("\x1b7x\x1b_Ga=T;AAAA\x1b\\y\x1b]1337;File=:AA\x07\x1b[m").collect::<Vec<_>>();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Other("\x1b7"),
+                Token::Text("x"),
+                Token::Other("\x1b_Ga=T;AAAA\x1b\\"),
+                Token::Text("y"),
+                Token::Other("\x1b]1337;File=:AA\x07"),
+                Token::Sgr(vec![]),
+            ]
+        );
+    }
+}
@@ -71,11 +71,38 @@ pub fn clear() {
     queue_sequence("2J");
 }
 
+pub fn get_begin_synchronized_update() -> String {
+    get_sequence("?2026h")
+}
+
+/// Make the terminal hold off displaying any output until
+/// `end_synchronized_update` is called, so frames are shown all at once.
+///
+/// Only supported by some terminals, see
+/// `TerminalCapabilities::synchronized_output`.
+/// Source: https://gist.github.com/christianparpart/d8a62cc1ab659194337d73e399004036
+///
+/// **Note:** this will only queue the command. You need to call `flush_commands`
+/// for it to activate.
+pub fn begin_synchronized_update() {
+    queue_sequence("?2026h");
+}
+
+pub fn get_end_synchronized_update() -> String {
+    get_sequence("?2026l")
+}
+
+/// Display all output since `begin_synchronized_update` was called.
+///
+/// **Note:** this will only queue the command. You need to call `flush_commands`
+/// for it to activate.
+pub fn end_synchronized_update() {
+    queue_sequence("?2026l");
+}
+
 pub fn get_fill_with_char(char: char) -> String {
     let (width, height) = crossterm::terminal::size().expect("couldn't get terminal size");
-    std::iter::repeat(char)
-        .take(width as usize * height as usize)
-        .collect::<String>()
+    std::iter::repeat_n(char, width as usize * height as usize).collect::<String>()
 }
 
 /// Fills the entire screen with `char`.
@@ -87,8 +114,17 @@ pub fn fill_with_char(char: char) {
     let (width, height) = crossterm::terminal::size().expect("couldn't get terminal size");
     print!(
         "{}",
-        std::iter::repeat(char)
-            .take((width * height) as usize)
-            .collect::<String>()
+        std::iter::repeat_n(char, (width * height) as usize).collect::<String>()
     );
 }
+
+/// Put `text` on the system clipboard with OSC 52, which also works over ssh.
+/// Terminals that don't support it, or only allow reading the clipboard,
+/// ignore it.
+/// Source: https://invisible-island.net/xterm/ctlseqs/ctlseqs.html#h3-Operating-System-Commands
+pub fn get_set_clipboard(text: &str) -> String {
+    format!(
+        "\x1b]52;c;{}\x07",
+        crate::encoding::base64::encode(text.as_bytes())
+    )
+}
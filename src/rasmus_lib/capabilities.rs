@@ -0,0 +1,363 @@
+//! Detection of optional terminal features.
+//!
+//! Terminals are asked about their features by printing query escape
+//! sequences and parsing the answers. The primary device attributes query is
+//! sent last, since every terminal answers it, which lets the probe stop
+//! waiting as soon as possible.
+
+use crate::ansi_term::parse::{tokenize, Token};
+use crate::flags::TerminalFlags;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Default time to wait for the terminal to answer the queries.
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Query synchronized output support (DECRQM for mode 2026).
+///
+/// Source: https://gist.github.com/christianparpart/d8a62cc1ab659194337d73e399004036
+const QUERY_SYNCHRONIZED_OUTPUT: &str = "\x1b[?2026$p";
+/// Query the kitty keyboard protocol flags.
+///
+/// Source: https://sw.kovidgoyal.net/kitty/keyboard-protocol/#detection-of-support-for-this-protocol
+const QUERY_KITTY_KEYBOARD: &str = "\x1b[?u";
+/// Query kitty graphics support with a tiny image that is never displayed.
+///
+/// Source: https://sw.kovidgoyal.net/kitty/graphics-protocol/#querying-support-and-available-transmission-mediums
+const QUERY_KITTY_GRAPHICS: &str = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\";
+/// Query the size of a cell in pixels (XTWINOPS 16), answered with
+/// `CSI 6 ; height ; width t`.
+const QUERY_CELL_SIZE: &str = "\x1b[16t";
+/// Query the terminal name and version (XTVERSION).
+const QUERY_VERSION: &str = "\x1b[>0q";
+/// Query the secondary device attributes (DA2).
+const QUERY_SECONDARY_ATTRIBUTES: &str = "\x1b[>c";
+/// Query the primary device attributes (DA1).
+const QUERY_PRIMARY_ATTRIBUTES: &str = "\x1b[c";
+
+/// Optional features supported by the terminal.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TerminalCapabilities {
+    /// Frames can be wrapped in begin/end synchronized update sequences
+    /// (DEC private mode 2026), avoiding tearing.
+    pub synchronized_output: bool,
+    /// 24-bit colors are displayed correctly.
+    pub truecolor: bool,
+    /// The kitty keyboard protocol, reporting key releases and repeats.
+    pub kitty_keyboard: bool,
+    /// The kitty graphics protocol.
+    pub kitty_graphics: bool,
+    /// iTerm2's inline image protocol.
+    pub iterm2_images: bool,
+    /// Size of a cell in pixels, if the terminal reports it.
+    pub cell_size: Option<CellSize>,
+    /// Name and version reported by the terminal, like `kitty(0.26.5)`.
+    pub terminal_version: Option<String>,
+    /// Whether the terminal answered the primary device attributes query.
+    /// If `false`, the probe timed out and most fields are guesses based on
+    /// environment variables.
+    pub responded: bool,
+}
+
+impl TerminalCapabilities {
+    /// Capabilities of a terminal without any optional features.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Detect the capabilities of the terminal connected to stdin and stdout.
+    ///
+    /// Raw mode is enabled while waiting for the answers, for at most
+    /// `timeout`. Nothing else may read from stdin during this time, so the
+    /// probe must run before the input thread is started.
+    ///
+    /// If stdin or stdout isn't a terminal, only environment variables are
+    /// used. The same goes for platforms other than unix, where the answers
+    /// can't be read without crossterm parsing them as key events.
+    pub fn probe(timeout: Duration) -> Self {
+        if !is_terminal() {
+            return Self::from_responses("");
+        }
+
+        let was_raw = TerminalFlags::is_raw_mode();
+        TerminalFlags::set_raw_mode(true);
+
+        let mut stdout = io::stdout();
+        let written = write!(
+            stdout,
+            "{}{}{}{}{}{}{}",
+            QUERY_SYNCHRONIZED_OUTPUT,
+            QUERY_KITTY_KEYBOARD,
+            QUERY_KITTY_GRAPHICS,
+            QUERY_CELL_SIZE,
+            QUERY_VERSION,
+            QUERY_SECONDARY_ATTRIBUTES,
+            QUERY_PRIMARY_ATTRIBUTES,
+        )
+        .and_then(|_| stdout.flush());
+
+        let responses = match written {
+            Ok(()) => read_until(timeout, |responses| {
+                tokenize(responses).any(|token| is_primary_attributes(&token))
+            }),
+            Err(_) => String::new(),
+        };
+
+        TerminalFlags::set_raw_mode(was_raw);
+
+        let mut capabilities = Self::from_responses(&responses);
+        // The window size is more reliable than the escape sequence, which
+        // some terminals answer with made up values.
+        if let Some(cell_size) = CellSize::from_window_size() {
+            capabilities.cell_size = Some(cell_size);
+        }
+
+        capabilities
+    }
+
+    /// The size of a cell in pixels, or `fallback` if the terminal didn't
+    /// report it.
+    pub fn cell_size_or(&self, fallback: CellSize) -> CellSize {
+        self.cell_size.unwrap_or(fallback)
+    }
+
+    /// Build the capabilities from the answers to the probe queries, combined
+    /// with hints from environment variables.
+    pub fn from_responses(responses: &str) -> Self {
+        let mut capabilities = Self::none();
+
+        for token in tokenize(responses) {
+            match token {
+                Token::Csi {
+                    params,
+                    final_byte: 'y',
+                } => {
+                    // DECRPM: `CSI ? 2026 ; status $ y`, where status 1 or 2
+                    // means that the mode is recognized and can be changed.
+                    if let Some(status) = params
+                        .strip_prefix("?2026;")
+                        .and_then(|rest| rest.strip_suffix('$'))
+                    {
+                        capabilities.synchronized_output = matches!(status, "1" | "2");
+                    }
+                }
+                Token::Csi {
+                    params,
+                    final_byte: 't',
+                } => {
+                    if let Some(size) = params.strip_prefix("6;") {
+                        capabilities.cell_size =
+                            size.split_once(';').and_then(|(height, width)| {
+                                CellSize::new(width.parse().ok()?, height.parse().ok()?)
+                            });
+                    }
+                }
+                Token::Csi {
+                    params,
+                    final_byte: 'u',
+                } if params.starts_with('?') => capabilities.kitty_keyboard = true,
+                Token::Csi {
+                    params,
+                    final_byte: 'c',
+                } if params.starts_with('?') => capabilities.responded = true,
+                Token::Other(sequence) => {
+                    if let Some(body) = sequence.strip_prefix("\x1b_G") {
+                        if body.starts_with("i=31;OK") {
+                            capabilities.kitty_graphics = true;
+                        }
+                    } else if let Some(body) = sequence.strip_prefix("\x1bP>|") {
+                        let version = body.trim_end_matches("\x1b\\");
+                        capabilities.terminal_version = Some(version.to_owned());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        capabilities.apply_environment_hints(
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM_PROGRAM").ok().as_deref(),
+        );
+
+        capabilities
+    }
+
+    fn apply_environment_hints(&mut self, color_term: Option<&str>, term_program: Option<&str>) {
+        if matches!(color_term, Some("truecolor" | "24bit")) {
+            self.truecolor = true;
+        }
+
+        let name = self
+            .terminal_version
+            .as_deref()
+            .or(term_program)
+            .unwrap_or("")
+            .to_lowercase();
+
+        if name.starts_with("iterm") || name.starts_with("wezterm") {
+            self.iterm2_images = true;
+        }
+
+        const TRUECOLOR_TERMINALS: [&str; 6] =
+            ["kitty", "wezterm", "iterm", "foot", "alacritty", "contour"];
+        if TRUECOLOR_TERMINALS
+            .iter()
+            .any(|terminal| name.starts_with(terminal))
+        {
+            self.truecolor = true;
+        }
+    }
+}
+
+/// The size of a terminal cell in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CellSize {
+    pub width: u16,
+    pub height: u16,
+}
+
+impl CellSize {
+    /// A typical cell size, used when the terminal doesn't report it. Cells of
+    /// most fonts are about twice as tall as they are wide.
+    pub const FALLBACK: CellSize = CellSize {
+        width: 10,
+        height: 20,
+    };
+
+    /// Returns `None` if either side is zero, which terminals report when
+    /// they don't know the size.
+    pub fn new(width: u16, height: u16) -> Option<Self> {
+        (width > 0 && height > 0).then_some(Self { width, height })
+    }
+
+    /// Read the cell size from the pixel size of the terminal window
+    /// (`TIOCGWINSZ`), if the terminal fills it in. Always `None` on
+    /// platforms other than unix.
+    #[cfg(unix)]
+    pub fn from_window_size() -> Option<Self> {
+        [1, 0].into_iter().find_map(|fd| {
+            let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+            let result = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) };
+
+            if result != 0 || size.ws_col == 0 || size.ws_row == 0 {
+                return None;
+            }
+            Self::new(size.ws_xpixel / size.ws_col, size.ws_ypixel / size.ws_row)
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_window_size() -> Option<Self> {
+        None
+    }
+
+    /// Width divided by height.
+    pub fn aspect_ratio(self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+}
+
+impl Default for CellSize {
+    fn default() -> Self {
+        Self::FALLBACK
+    }
+}
+
+fn is_primary_attributes(token: &Token) -> bool {
+    matches!(token, Token::Csi { params, final_byte: 'c' } if params.starts_with('?'))
+}
+
+/// Whether both stdin and stdout are terminals that can be probed.
+#[cfg(unix)]
+fn is_terminal() -> bool {
+    unsafe { libc::isatty(0) == 1 && libc::isatty(1) == 1 }
+}
+
+#[cfg(not(unix))]
+fn is_terminal() -> bool {
+    false
+}
+
+/// Read raw bytes from stdin until `is_done` returns `true` for everything
+/// read so far, or until `timeout` has passed.
+#[cfg(unix)]
+fn read_until(timeout: Duration, is_done: impl Fn(&str) -> bool) -> String {
+    let deadline = Instant::now() + timeout;
+    let mut bytes = Vec::new();
+    let mut buffer = [0u8; 1024];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+
+        let mut poll_fd = libc::pollfd {
+            fd: 0,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready = unsafe { libc::poll(&mut poll_fd, 1, remaining.as_millis() as libc::c_int) };
+        if ready <= 0 {
+            break;
+        }
+
+        let count = unsafe { libc::read(0, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if count <= 0 {
+            break;
+        }
+        bytes.extend_from_slice(&buffer[..count as usize]);
+
+        if is_done(&String::from_utf8_lossy(&bytes)) {
+            break;
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(not(unix))]
+fn read_until(_timeout: Duration, _is_done: impl Fn(&str) -> bool) -> String {
+    String::new()
+}
+
+mod tests {
+    #[test]
+    fn parse_kitty_responses_test() {
+        use super::*;
+
+        let responses = "\x1b[?2026;2$y\x1b[?0u\x1b_Gi=31;OK\x1b\\\x1b[6;17;8t\x1bP>|kitty(0.26.5)\x1b\\\x1b[>1;4000;19c\x1b[?62;c";
+        let capabilities = TerminalCapabilities::from_responses(responses);
+
+        assert!(capabilities.responded);
+        assert!(capabilities.synchronized_output);
+        assert!(capabilities.kitty_keyboard);
+        assert!(capabilities.kitty_graphics);
+        assert!(capabilities.truecolor);
+        assert_eq!(capabilities.cell_size, CellSize::new(8, 17));
+        assert_eq!(
+            capabilities.terminal_version.as_deref(),
+            Some("kitty(0.26.5)")
+        );
+    }
+
+    #[test]
+    fn parse_minimal_responses_test() {
+        use super::*;
+
+        let mut capabilities = TerminalCapabilities::from_responses("\x1b[?2026;0$y\x1b[?1;2c");
+        capabilities.truecolor = false;
+
+        assert!(capabilities.responded);
+        assert!(!capabilities.synchronized_output);
+        assert!(!capabilities.kitty_keyboard);
+        assert!(!capabilities.kitty_graphics);
+        assert_eq!(
+            capabilities.cell_size_or(CellSize::FALLBACK),
+            CellSize::FALLBACK
+        );
+
+        capabilities.apply_environment_hints(Some("truecolor"), None);
+        assert!(capabilities.truecolor);
+    }
+}
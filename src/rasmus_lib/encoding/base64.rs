@@ -0,0 +1,50 @@
+//! Standard base64 encoding (RFC 4648), with padding.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Returns the length of the encoded string for `byte_count` input bytes.
+pub const fn encoded_len(byte_count: usize) -> usize {
+    byte_count.div_ceil(3) * 4
+}
+
+/// Encode `bytes` as a base64 string.
+///
+/// # Examples
+/// ```
+/// use terminal_renderer::encoding::base64;
+///
+/// assert_eq!(base64::encode(b"Man"), "TWFu");
+/// assert_eq!(base64::encode(b"Ma"), "TWE=");
+/// assert_eq!(base64::encode(b"M"), "TQ==");
+/// ```
+pub fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(encoded_len(bytes.len()));
+    encode_into(bytes, &mut out);
+    out
+}
+
+/// Encode `bytes` as base64, appending the result to `out`.
+pub fn encode_into(bytes: &[u8], out: &mut String) {
+    for chunk in bytes.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+
+        out.push(ALPHABET[(triple >> 18) as usize & 0x3f] as char);
+        out.push(ALPHABET[(triple >> 12) as usize & 0x3f] as char);
+
+        if chunk.len() > 1 {
+            out.push(ALPHABET[(triple >> 6) as usize & 0x3f] as char);
+        } else {
+            out.push('=');
+        }
+
+        if chunk.len() > 2 {
+            out.push(ALPHABET[triple as usize & 0x3f] as char);
+        } else {
+            out.push('=');
+        }
+    }
+}
@@ -0,0 +1,411 @@
+//! A minimal animated gif encoder, with median cut color quantization.
+//!
+//! Every frame gets its own palette of at most 256 colors, so colors stay
+//! accurate even when they change a lot between frames.
+//!
+//! Source: https://www.w3.org/Graphics/GIF/spec-gif89a.txt
+
+use std::collections::HashMap;
+
+/// Largest code the lzw compression in gif files can use.
+const MAX_CODE: u16 = 4095;
+
+#[derive(Debug, Clone)]
+pub struct GifEncoder {
+    width: u16,
+    height: u16,
+    out: Vec<u8>,
+    frame_count: usize,
+}
+
+impl GifEncoder {
+    /// Start an animation of `width` by `height` pixels that loops forever.
+    pub fn new(width: u16, height: u16) -> Self {
+        let mut out = Vec::new();
+        out.extend_from_slice(b"GIF89a");
+
+        // Logical screen descriptor, without a global color table.
+        out.extend_from_slice(&width.to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+        out.extend_from_slice(&[0, 0, 0]);
+
+        // Application extension, making viewers loop the animation.
+        out.extend_from_slice(&[0x21, 0xff, 11]);
+        out.extend_from_slice(b"NETSCAPE2.0");
+        out.extend_from_slice(&[3, 1, 0, 0, 0]);
+
+        Self {
+            width,
+            height,
+            out,
+            frame_count: 0,
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Add a frame shown for `delay_centis` hundredths of a second.
+    ///
+    /// `rgb` contains the rows from top to bottom, using three bytes per
+    /// pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `rgb` doesn't match the size of the animation.
+    pub fn add_frame(&mut self, rgb: &[u8], delay_centis: u16) {
+        assert_eq!(
+            rgb.len(),
+            self.width as usize * self.height as usize * 3,
+            "frame data doesn't match gif dimensions"
+        );
+
+        let (palette, indices) = quantize(rgb, 256);
+
+        // The color table size has to be a power of two, of at least two.
+        let table_bits = (usize::BITS - (palette.len().max(2) - 1).leading_zeros()) as u8;
+
+        // Graphic control extension, setting the delay.
+        self.out.extend_from_slice(&[0x21, 0xf9, 4, 0x04]);
+        self.out.extend_from_slice(&delay_centis.to_le_bytes());
+        self.out.extend_from_slice(&[0, 0]);
+
+        // Image descriptor, with a local color table.
+        self.out.push(0x2c);
+        self.out.extend_from_slice(&[0, 0, 0, 0]);
+        self.out.extend_from_slice(&self.width.to_le_bytes());
+        self.out.extend_from_slice(&self.height.to_le_bytes());
+        self.out.push(0x80 | (table_bits - 1));
+
+        for index in 0..1 << table_bits {
+            let color = palette.get(index).copied().unwrap_or_default();
+            self.out.extend_from_slice(&color);
+        }
+
+        let min_code_size = table_bits.max(2);
+        self.out.push(min_code_size);
+        for block in lzw_compress(&indices, min_code_size).chunks(255) {
+            self.out.push(block.len() as u8);
+            self.out.extend_from_slice(block);
+        }
+        self.out.push(0);
+
+        self.frame_count += 1;
+    }
+
+    /// Finish the animation, returning the contents of the gif file.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.out.push(0x3b);
+        self.out
+    }
+}
+
+/// Reduce the colors of `rgb` to at most `max_colors` using median cut.
+///
+/// Returns the palette and the palette index of every pixel. Images that
+/// already use few enough colors keep their exact colors.
+pub fn quantize(rgb: &[u8], max_colors: usize) -> (Vec<[u8; 3]>, Vec<u8>) {
+    assert!(
+        (1..=256).contains(&max_colors),
+        "palettes hold between 1 and 256 colors"
+    );
+
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for pixel in rgb.chunks_exact(3) {
+        *counts.entry([pixel[0], pixel[1], pixel[2]]).or_default() += 1;
+    }
+
+    let mut colors: Vec<([u8; 3], usize)> = counts.into_iter().collect();
+    // Keeps the output deterministic, as hash map order isn't.
+    colors.sort_unstable();
+
+    let palette: Vec<[u8; 3]> = if colors.len() <= max_colors {
+        colors.iter().map(|(color, _)| *color).collect()
+    } else {
+        median_cut(colors, max_colors)
+    };
+
+    let mut lookup: HashMap<[u8; 3], u8> = HashMap::new();
+    let indices = rgb
+        .chunks_exact(3)
+        .map(|pixel| {
+            let color = [pixel[0], pixel[1], pixel[2]];
+            *lookup
+                .entry(color)
+                .or_insert_with(|| nearest_color(&palette, color))
+        })
+        .collect();
+
+    (palette, indices)
+}
+
+fn median_cut(colors: Vec<([u8; 3], usize)>, max_colors: usize) -> Vec<[u8; 3]> {
+    fn widest_channel(colors: &[([u8; 3], usize)]) -> (usize, u8) {
+        (0..3)
+            .map(|channel| {
+                let min = colors.iter().map(|(c, _)| c[channel]).min().unwrap_or(0);
+                let max = colors.iter().map(|(c, _)| c[channel]).max().unwrap_or(0);
+                (channel, max - min)
+            })
+            .max_by_key(|(_, range)| *range)
+            .unwrap()
+    }
+
+    let mut boxes = vec![colors];
+
+    while boxes.len() < max_colors {
+        // Split the box spanning the widest range of a single channel.
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| (index, widest_channel(colors)))
+            .max_by_key(|(_, (_, range))| *range)
+            .map(|(index, (channel, _))| (index, channel))
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|(color, _)| color[channel]);
+
+        // Split at the median pixel, keeping at least one color on each side.
+        let total: usize = colors.iter().map(|(_, count)| count).sum();
+        let mut seen = 0;
+        let mut split = colors.len() - 1;
+        for (i, (_, count)) in colors.iter().enumerate() {
+            seen += count;
+            if seen * 2 >= total {
+                split = i + 1;
+                break;
+            }
+        }
+        let split = split.clamp(1, colors.len() - 1);
+
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|colors| {
+            let total: usize = colors.iter().map(|(_, count)| count).sum();
+            let mut sum = [0usize; 3];
+            for (color, count) in colors {
+                for channel in 0..3 {
+                    sum[channel] += color[channel] as usize * count;
+                }
+            }
+            sum.map(|channel| ((channel + total / 2) / total) as u8)
+        })
+        .collect()
+}
+
+fn nearest_color(palette: &[[u8; 3]], color: [u8; 3]) -> u8 {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, entry)| {
+            (0..3)
+                .map(|channel| (entry[channel] as i32 - color[channel] as i32).pow(2))
+                .sum::<i32>()
+        })
+        .map(|(index, _)| index as u8)
+        .unwrap_or(0)
+}
+
+/// Compress palette indices with the variable code size lzw used by gif.
+fn lzw_compress(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear_code: u16 = 1 << min_code_size;
+    let end_code = clear_code + 1;
+
+    let mut writer = BitWriter::default();
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = min_code_size + 1;
+    let mut next_code = end_code + 1;
+
+    writer.write(clear_code, code_size);
+
+    let mut indices = indices.iter();
+    let Some(first) = indices.next() else {
+        writer.write(end_code, code_size);
+        return writer.finish();
+    };
+    let mut prefix = *first as u16;
+
+    for index in indices {
+        if let Some(code) = table.get(&(prefix, *index)) {
+            prefix = *code;
+            continue;
+        }
+
+        writer.write(prefix, code_size);
+
+        if next_code > MAX_CODE {
+            writer.write(clear_code, code_size);
+            table.clear();
+            code_size = min_code_size + 1;
+            next_code = end_code + 1;
+        } else {
+            table.insert((prefix, *index), next_code);
+            if next_code == 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+            next_code += 1;
+        }
+
+        prefix = *index as u16;
+    }
+
+    writer.write(prefix, code_size);
+    writer.write(end_code, code_size);
+    writer.finish()
+}
+
+/// Packs codes least significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+
+        while self.bits >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
+
+mod tests {
+    /// A straightforward gif lzw decoder, to check the encoder against.
+    #[allow(dead_code)]
+    fn lzw_decompress(data: &[u8], min_code_size: u8) -> Vec<u8> {
+        let clear_code: u16 = 1 << min_code_size;
+        let end_code = clear_code + 1;
+
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut code_size = min_code_size + 1;
+        let mut previous: Option<u16> = None;
+        let mut out = Vec::new();
+
+        let mut bit_position = 0;
+        loop {
+            let mut code = 0u16;
+            for bit in 0..code_size as usize {
+                let byte = data[(bit_position + bit) / 8];
+                code |= (((byte >> ((bit_position + bit) % 8)) & 1) as u16) << bit;
+            }
+            bit_position += code_size as usize;
+
+            if code == clear_code {
+                table = (0..clear_code).map(|index| vec![index as u8]).collect();
+                table.push(Vec::new());
+                table.push(Vec::new());
+                code_size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end_code {
+                return out;
+            }
+
+            let entry = match previous {
+                None => table[code as usize].clone(),
+                Some(previous) => {
+                    let mut previous_entry = table[previous as usize].clone();
+                    let entry = if (code as usize) < table.len() {
+                        table[code as usize].clone()
+                    } else {
+                        let mut entry = previous_entry.clone();
+                        entry.push(previous_entry[0]);
+                        entry
+                    };
+                    previous_entry.push(entry[0]);
+                    table.push(previous_entry);
+                    if table.len() == 1 << code_size && code_size < 12 {
+                        code_size += 1;
+                    }
+                    entry
+                }
+            };
+
+            out.extend_from_slice(&entry);
+            previous = Some(code);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trip_test() {
+        use super::*;
+
+        // Long enough to fill the code table a few times.
+        let indices: Vec<u8> = (0..40_000u32)
+            .map(|i| ((i * 7919) % 13 + (i / 97) % 3) as u8)
+            .collect();
+
+        let compressed = lzw_compress(&indices, 4);
+        assert_eq!(lzw_decompress(&compressed, 4), indices);
+
+        let uniform = vec![1u8; 10_000];
+        assert_eq!(lzw_decompress(&lzw_compress(&uniform, 2), 2), uniform);
+    }
+
+    #[test]
+    fn quantize_test() {
+        use super::*;
+
+        // Exact palette when there are few colors.
+        let rgb = [255, 0, 0, 0, 0, 255, 255, 0, 0];
+        let (palette, indices) = quantize(&rgb, 256);
+        assert_eq!(palette, vec![[0, 0, 255], [255, 0, 0]]);
+        assert_eq!(indices, vec![1, 0, 1]);
+
+        // A gradient of 512 colors reduced to 16.
+        let rgb: Vec<u8> = (0..512u32)
+            .flat_map(|i| [(i / 2) as u8, 0, (255 - i / 2) as u8])
+            .collect();
+        let (palette, indices) = quantize(&rgb, 16);
+        assert_eq!(palette.len(), 16);
+        for (pixel, index) in rgb.chunks(3).zip(indices) {
+            let color = palette[index as usize];
+            assert!((color[0] as i32 - pixel[0] as i32).abs() <= 16);
+        }
+    }
+
+    #[test]
+    fn gif_structure_test() {
+        use super::*;
+
+        let mut encoder = GifEncoder::new(2, 1);
+        encoder.add_frame(&[255, 0, 0, 0, 255, 0], 10);
+        encoder.add_frame(&[0, 0, 255, 0, 0, 255], 10);
+        assert_eq!(encoder.frame_count(), 2);
+
+        let gif = encoder.finish();
+        assert_eq!(&gif[..6], b"GIF89a");
+        assert_eq!(&gif[6..10], &[2, 0, 1, 0]);
+        assert_eq!(gif.last(), Some(&0x3b));
+    }
+}
@@ -0,0 +1,63 @@
+//! Small, dependency free encoders used for exporting and transmitting images.
+
+pub mod base64;
+pub mod gif;
+pub mod png;
+
+/// Calculates the CRC-32 checksum (as used by png and gzip) of `bytes`.
+///
+/// Source: https://www.w3.org/TR/png/#D-CRCAppendix
+pub fn crc32(bytes: &[u8]) -> u32 {
+    crc32_update(0xffff_ffff, bytes) ^ 0xffff_ffff
+}
+
+/// Continues a CRC-32 calculation, where `crc` is the previous *un-inverted*
+/// value. Start with `0xffffffff` and invert the final result.
+pub fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    crc
+}
+
+/// Calculates the Adler-32 checksum used by the zlib format.
+///
+/// Source: https://en.wikipedia.org/wiki/Adler-32
+pub fn adler32(bytes: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    for chunk in bytes.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+
+    (b << 16) | a
+}
+
+mod tests {
+    #[test]
+    fn crc32_test() {
+        use super::crc32;
+
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn adler32_test() {
+        use super::adler32;
+
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+}
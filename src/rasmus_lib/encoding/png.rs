@@ -0,0 +1,154 @@
+//! A minimal png encoder.
+//!
+//! Image data is stored in uncompressed deflate blocks, which keeps the
+//! encoder tiny at the cost of larger files. Every png decoder supports this.
+//!
+//! Source: https://www.w3.org/TR/png/
+
+use super::{adler32, crc32_update};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Largest amount of data a single stored deflate block can hold.
+const MAX_STORED_BLOCK: usize = 65535;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorType {
+    /// One byte per pixel.
+    Grayscale,
+    /// Three bytes per pixel.
+    Rgb,
+    /// Four bytes per pixel.
+    Rgba,
+}
+
+impl ColorType {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            ColorType::Grayscale => 1,
+            ColorType::Rgb => 3,
+            ColorType::Rgba => 4,
+        }
+    }
+
+    fn png_id(self) -> u8 {
+        match self {
+            ColorType::Grayscale => 0,
+            ColorType::Rgb => 2,
+            ColorType::Rgba => 6,
+        }
+    }
+}
+
+/// Encode 8-bit image data as a png file.
+///
+/// `data` contains the rows from top to bottom, each pixel using
+/// `color_type.bytes_per_pixel()` bytes.
+///
+/// # Panics
+///
+/// Panics if the length of `data` doesn't match `width`, `height` and
+/// `color_type`.
+pub fn encode(width: u32, height: u32, color_type: ColorType, data: &[u8]) -> Vec<u8> {
+    let row_len = width as usize * color_type.bytes_per_pixel();
+    assert_eq!(
+        data.len(),
+        row_len * height as usize,
+        "image data doesn't match png dimensions"
+    );
+
+    let mut out = Vec::with_capacity(data.len() + height as usize + 128);
+    out.extend_from_slice(&SIGNATURE);
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.push(8); // bit depth
+    header.push(color_type.png_id());
+    header.push(0); // compression method
+    header.push(0); // filter method
+    header.push(0); // no interlacing
+    write_chunk(&mut out, b"IHDR", &header);
+
+    // Every row is prefixed by its filter type, which is always none here.
+    let mut filtered = Vec::with_capacity(data.len() + height as usize);
+    if row_len > 0 {
+        for row in data.chunks(row_len) {
+            filtered.push(0);
+            filtered.extend_from_slice(row);
+        }
+    }
+
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&filtered));
+    write_chunk(&mut out, b"IEND", &[]);
+
+    out
+}
+
+/// Wrap `data` in a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let block_count = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut out = Vec::with_capacity(data.len() + block_count * 5 + 6);
+
+    // CMF: deflate with a 32K window, FLG: no dictionary, fastest level.
+    // (0x78 << 8 | 0x01) is divisible by 31 as required.
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+
+        out.push(is_final as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+
+    let crc = crc32_update(crc32_update(0xffff_ffff, kind), data) ^ 0xffff_ffff;
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+mod tests {
+    #[test]
+    fn png_structure_test() {
+        use super::*;
+
+        let data = [255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+        let png = encode(2, 2, ColorType::Rgb, &data);
+
+        assert_eq!(&png[..8], &SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..20], &2u32.to_be_bytes());
+        assert_eq!(&png[20..24], &2u32.to_be_bytes());
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+        // IEND chunk crc is always the same
+        assert_eq!(&png[png.len() - 4..], &[0xae, 0x42, 0x60, 0x82]);
+    }
+
+    #[test]
+    fn zlib_stored_test() {
+        use super::*;
+
+        let data = vec![7u8; MAX_STORED_BLOCK + 10];
+        let stream = zlib_stored(&data);
+
+        // header + two blocks of headers + data + checksum
+        assert_eq!(stream.len(), 2 + 5 * 2 + data.len() + 4);
+        assert_eq!(stream[2], 0);
+        assert_eq!(stream[2 + 5 + MAX_STORED_BLOCK], 1);
+    }
+}
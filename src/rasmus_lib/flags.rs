@@ -1,5 +1,9 @@
 // use ansi_term::*;
 use super::ansi_term;
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
 use crossterm::terminal;
 use static_init::dynamic;
 
@@ -9,6 +13,9 @@ static mut TERMINAL_FLAGS: TerminalFlags = TerminalFlags::new();
 pub struct TerminalFlags {
     raw_mode: bool,
     alternate_buffer: bool,
+    mouse_capture: bool,
+    bracketed_paste: bool,
+    keyboard_enhancement: bool,
 }
 
 impl TerminalFlags {
@@ -16,9 +23,16 @@ impl TerminalFlags {
         Self {
             raw_mode: false,
             alternate_buffer: false,
+            mouse_capture: false,
+            bracketed_paste: false,
+            keyboard_enhancement: false,
         }
     }
 
+    pub fn is_raw_mode() -> bool {
+        TERMINAL_FLAGS.read().raw_mode
+    }
+
     pub fn set_raw_mode(enabled: bool) {
         let mut flags = TERMINAL_FLAGS.write();
         if flags.raw_mode == enabled {
@@ -50,7 +64,86 @@ impl TerminalFlags {
         }
     }
 
+    /// Make the terminal report mouse presses, releases, drags, movement and
+    /// scrolling, instead of using the mouse for selecting text.
+    pub fn set_mouse_capture(enabled: bool) {
+        let mut flags = TERMINAL_FLAGS.write();
+
+        if flags.mouse_capture == enabled {
+            return;
+        }
+
+        flags.mouse_capture = enabled;
+
+        let mut stdout = std::io::stdout();
+        if enabled {
+            crossterm::execute!(stdout, EnableMouseCapture).expect("Couldn't enable mouse capture");
+        } else {
+            crossterm::execute!(stdout, DisableMouseCapture)
+                .expect("Couldn't disable mouse capture");
+        }
+    }
+
+    /// Make the terminal report pasted text as a single paste event, instead
+    /// of typing it out key by key.
+    pub fn set_bracketed_paste(enabled: bool) {
+        let mut flags = TERMINAL_FLAGS.write();
+
+        if flags.bracketed_paste == enabled {
+            return;
+        }
+
+        flags.bracketed_paste = enabled;
+
+        let mut stdout = std::io::stdout();
+        if enabled {
+            crossterm::execute!(stdout, EnableBracketedPaste)
+                .expect("Couldn't enable bracketed paste");
+        } else {
+            crossterm::execute!(stdout, DisableBracketedPaste)
+                .expect("Couldn't disable bracketed paste");
+        }
+    }
+
+    pub fn is_keyboard_enhancement() -> bool {
+        TERMINAL_FLAGS.read().keyboard_enhancement
+    }
+
+    /// Make the terminal report key repeats and releases, using the kitty
+    /// keyboard protocol. Terminals that don't support it ignore this, so
+    /// check `TerminalCapabilities::kitty_keyboard` first.
+    ///
+    /// Characters typed with shift are reported as the unshifted key with the
+    /// shift modifier while this is enabled, like `shift+/` instead of `?`,
+    /// which `InputEvent` turns back into the typed character.
+    pub fn set_keyboard_enhancement(enabled: bool) {
+        let mut flags = TERMINAL_FLAGS.write();
+
+        if flags.keyboard_enhancement == enabled {
+            return;
+        }
+
+        flags.keyboard_enhancement = enabled;
+
+        let mut stdout = std::io::stdout();
+        if enabled {
+            // Plain keys like letters only report releases when every key is
+            // sent as an escape code.
+            let enhancements = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES;
+            crossterm::execute!(stdout, PushKeyboardEnhancementFlags(enhancements))
+                .expect("Couldn't push keyboard enhancement flags");
+        } else {
+            crossterm::execute!(stdout, PopKeyboardEnhancementFlags)
+                .expect("Couldn't pop keyboard enhancement flags");
+        }
+    }
+
     pub fn clean_up() {
+        Self::set_keyboard_enhancement(false);
+        Self::set_mouse_capture(false);
+        Self::set_bracketed_paste(false);
         Self::set_raw_mode(false);
         Self::set_alternative_buffer(false);
 
@@ -0,0 +1,552 @@
+//! Mapping of key chords and sequences to named actions.
+//!
+//! Panels ask the input stream whether an action like `"scene.pause"` was
+//! triggered, instead of matching key codes themselves, which lets users
+//! rebind keys with a config file:
+//!
+//! ```text
+//! # Comments start with '#'.
+//! quit = ctrl+q
+//! scene.pause = p
+//! # Sequences are separated by spaces.
+//! camera.toggle_mode = g m
+//! # Repeating an action adds another binding, an empty value unbinds it.
+//! help = f1
+//! help = ?
+//! focus.previous =
+//! ```
+//!
+//! Bindings are global, but actions are only seen by the focused panel and
+//! then the `Gui` and its tickers, never by the other panels. So the bare letters bound to
+//! `camera.*` and `scene.pause` only move the camera while a `ScenePanel` has
+//! focus, and a panel that consumes a key, like a text input typing it,
+//! hides the actions it triggers from the gui as well.
+
+use super::{InputEvent, KeyCode, KeyModifiers};
+use std::error;
+use std::fmt::{self, Display};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+pub const QUIT: &str = "quit";
+pub const HELP: &str = "help";
+pub const COMMAND_PALETTE: &str = "command_palette";
+pub const FOCUS_NEXT: &str = "focus.next";
+pub const FOCUS_PREVIOUS: &str = "focus.previous";
+
+// Actions shared by the widgets in `ui`.
+pub const ACTIVATE: &str = "ui.activate";
+pub const UP: &str = "ui.up";
+pub const DOWN: &str = "ui.down";
+pub const LEFT: &str = "ui.left";
+pub const RIGHT: &str = "ui.right";
+pub const PAGE_UP: &str = "ui.page_up";
+pub const PAGE_DOWN: &str = "ui.page_down";
+pub const HOME: &str = "ui.home";
+pub const END: &str = "ui.end";
+
+/// The bindings used by `Keymap::default`, in the config file format.
+pub const DEFAULT_KEYMAP: &str = "\
+quit = ctrl+c
+help = ?
+command_palette = ctrl+p
+focus.next = tab
+focus.previous = shift+tab
+scene.pause = space
+camera.toggle_mode = m
+camera.rotate_left = left
+camera.rotate_right = right
+camera.rotate_up = up
+camera.rotate_down = down
+camera.forward = w
+camera.back = s
+camera.left = a
+camera.right = d
+camera.rise = e
+camera.sink = q
+camera.zoom_in = +
+camera.zoom_in = =
+camera.zoom_out = -
+ui.activate = enter
+ui.activate = space
+ui.up = up
+ui.down = down
+ui.left = left
+ui.right = right
+ui.page_up = pageup
+ui.page_down = pagedown
+ui.home = home
+ui.end = end
+";
+
+/// A key together with the modifiers held while pressing it.
+///
+/// Chords are normalized, so that they compare equal regardless of how the
+/// terminal reports them: shift is implied by characters and by `BackTab`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers = modifiers;
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+
+        Self { code, modifiers }
+    }
+}
+
+impl From<InputEvent> for KeyChord {
+    fn from(event: InputEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+/// Error returned when a key chord can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeyError {
+    pub key: String,
+}
+
+impl Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid key \"{}\"", self.key)
+    }
+}
+
+impl error::Error for ParseKeyError {}
+
+impl FromStr for KeyChord {
+    type Err = ParseKeyError;
+
+    /// Parse chords like `ctrl+c`, `shift+tab`, `alt+left` or `+`.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let error = || ParseKeyError {
+            key: string.to_owned(),
+        };
+
+        // The key itself may be a plus, like in `ctrl++`.
+        let (modifier_names, key) = if string == "+" {
+            ("", "+")
+        } else if let Some(modifier_names) = string.strip_suffix("++") {
+            (modifier_names, "+")
+        } else {
+            string.rsplit_once('+').unwrap_or(("", string))
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+            modifiers |= match name.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(error()),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(char), None) => KeyCode::Char(char),
+            _ => match key.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(number @ 1..=24) => KeyCode::F(number),
+                    _ => return Err(error()),
+                },
+            },
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}", name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(char) => write!(f, "{}", char),
+            KeyCode::F(number) => write!(f, "f{}", number),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "shift+tab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Insert => write!(f, "insert"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// A sequence of chords triggering an action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub keys: Vec<KeyChord>,
+    pub action: String,
+}
+
+impl Binding {
+    /// The keys separated by spaces, like in the config file.
+    pub fn keys_string(&self) -> String {
+        self.keys
+            .iter()
+            .map(|chord| chord.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[derive(Debug)]
+pub enum KeymapError {
+    Io(io::Error),
+    MissingEquals { line: usize },
+    MissingAction { line: usize },
+    InvalidKey { line: usize, key: String },
+}
+
+impl Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::Io(error) => write!(f, "couldn't read keymap: {}", error),
+            KeymapError::MissingEquals { line } => {
+                write!(f, "expected \"action = keys\" at line {}", line)
+            }
+            KeymapError::MissingAction { line } => {
+                write!(f, "missing action name at line {}", line)
+            }
+            KeymapError::InvalidKey { line, key } => {
+                write!(f, "invalid key \"{}\" at line {}", key, line)
+            }
+        }
+    }
+}
+
+impl error::Error for KeymapError {}
+
+impl From<io::Error> for KeymapError {
+    fn from(error: io::Error) -> Self {
+        KeymapError::Io(error)
+    }
+}
+
+/// Bindings from key sequences to actions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    /// A keymap without any bindings.
+    pub fn empty() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Parse a keymap from a config file, see the module documentation for
+    /// the format.
+    pub fn parse(config: &str) -> Result<Self, KeymapError> {
+        let mut keymap = Self::empty();
+        keymap.apply_config(config)?;
+        Ok(keymap)
+    }
+
+    /// The default keymap, with the bindings in the config file at `path`
+    /// replacing the defaults of the actions it mentions.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KeymapError> {
+        let config = std::fs::read_to_string(path)?;
+
+        let mut keymap = Self::default();
+        keymap.apply_config(&config)?;
+        Ok(keymap)
+    }
+
+    /// Apply the bindings in `config`. Every action it mentions loses the
+    /// bindings it had before.
+    ///
+    /// Nothing is changed if the config is invalid.
+    pub fn apply_config(&mut self, config: &str) -> Result<(), KeymapError> {
+        let mut bindings = Vec::new();
+        let mut actions = Vec::new();
+
+        for (index, line) in config.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (action, keys) = line
+                .split_once('=')
+                .ok_or(KeymapError::MissingEquals { line: line_number })?;
+            let action = action.trim();
+            if action.is_empty() {
+                return Err(KeymapError::MissingAction { line: line_number });
+            }
+
+            actions.push(action.to_owned());
+
+            if keys.trim().is_empty() {
+                continue;
+            }
+            let keys = parse_sequence(keys).map_err(|error| KeymapError::InvalidKey {
+                line: line_number,
+                key: error.key,
+            })?;
+            bindings.push(Binding {
+                keys,
+                action: action.to_owned(),
+            });
+        }
+
+        self.bindings
+            .retain(|binding| !actions.contains(&binding.action));
+        self.bindings.extend(bindings);
+
+        Ok(())
+    }
+
+    /// Bind a space separated sequence of chords, like `"g g"` or
+    /// `"ctrl+x ctrl+s"`, to `action`, in addition to its other bindings.
+    pub fn bind(&mut self, keys: &str, action: impl Into<String>) -> Result<(), ParseKeyError> {
+        let keys = parse_sequence(keys)?;
+        self.bind_chords(keys, action);
+        Ok(())
+    }
+
+    pub fn bind_chords(&mut self, keys: Vec<KeyChord>, action: impl Into<String>) {
+        let action = action.into();
+        self.bindings
+            .retain(|binding| binding.keys != keys || binding.action != action);
+        self.bindings.push(Binding { keys, action });
+    }
+
+    /// Remove every binding of `action`.
+    pub fn unbind(&mut self, action: &str) {
+        self.bindings.retain(|binding| binding.action != action);
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    pub fn bindings_for<'a>(&'a self, action: &'a str) -> impl Iterator<Item = &'a Binding> + 'a {
+        self.bindings
+            .iter()
+            .filter(move |binding| binding.action == action)
+    }
+
+    /// Every bound action with its key sequences, in the order the actions
+    /// were first bound. Used for listing the bindings to the user.
+    pub fn actions(&self) -> Vec<(&str, Vec<String>)> {
+        let mut actions: Vec<(&str, Vec<String>)> = Vec::new();
+        for binding in &self.bindings {
+            let keys = binding.keys_string();
+            match actions
+                .iter_mut()
+                .find(|(action, _)| *action == binding.action)
+            {
+                Some((_, all_keys)) => all_keys.push(keys),
+                None => actions.push((&binding.action, vec![keys])),
+            }
+        }
+        actions
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::parse(DEFAULT_KEYMAP).expect("default keymap is invalid")
+    }
+}
+
+fn parse_sequence(keys: &str) -> Result<Vec<KeyChord>, ParseKeyError> {
+    keys.split_whitespace().map(KeyChord::from_str).collect()
+}
+
+/// Keys pressed so far of a sequence that hasn't been completed yet.
+#[derive(Debug, Clone, Default)]
+pub struct KeymapState {
+    pending: Vec<KeyChord>,
+}
+
+impl KeymapState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the last keys are the start of a longer sequence.
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn pending(&self) -> &[KeyChord] {
+        &self.pending
+    }
+
+    /// Feed the next key press, returning the actions of the bindings it
+    /// completes, if any. A key can be bound to several actions, like the
+    /// arrow keys moving both the camera and the selection of a list.
+    ///
+    /// If the pending keys can't be completed anymore, the oldest ones are
+    /// dropped until they form the start of a binding again, so a key that
+    /// breaks a sequence can still start another one.
+    pub fn feed<'k>(&mut self, keymap: &'k Keymap, event: InputEvent) -> Vec<&'k str> {
+        self.pending.push(event.into());
+
+        while !self.pending.is_empty() {
+            let mut actions: Vec<&str> = Vec::new();
+            for binding in &keymap.bindings {
+                if binding.keys == self.pending && !actions.contains(&binding.action.as_str()) {
+                    actions.push(&binding.action);
+                }
+            }
+            if !actions.is_empty() {
+                self.pending.clear();
+                return actions;
+            }
+
+            if keymap
+                .bindings
+                .iter()
+                .any(|binding| binding.keys.starts_with(&self.pending))
+            {
+                return Vec::new();
+            }
+
+            self.pending.remove(0);
+        }
+
+        Vec::new()
+    }
+
+    /// Forget the pending keys.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+}
+
+mod tests {
+    #[test]
+    fn parse_chord_test() {
+        use super::*;
+
+        let chord = |string: &str| string.parse::<KeyChord>().unwrap();
+
+        assert_eq!(
+            chord("ctrl+c"),
+            KeyChord::new(KeyCode::Char('c'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            chord("shift+tab"),
+            KeyChord::new(KeyCode::BackTab, KeyModifiers::SHIFT)
+        );
+        assert_eq!(
+            chord("ctrl++"),
+            KeyChord::new(KeyCode::Char('+'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            chord("F5"),
+            KeyChord::new(KeyCode::F(5), KeyModifiers::NONE)
+        );
+        assert_eq!(chord("alt+space").to_string(), "alt+space");
+        assert!("hyper+x".parse::<KeyChord>().is_err());
+
+        // Terminals report shift with capital letters and back tab.
+        assert_eq!(
+            KeyChord::from(InputEvent::new(KeyCode::Char('?'), KeyModifiers::SHIFT)),
+            chord("?")
+        );
+    }
+
+    #[test]
+    fn apply_config_test() {
+        use super::*;
+
+        let mut keymap = Keymap::default();
+        keymap
+            .apply_config("# rebind\nquit = ctrl+q\nquit = q q\nfocus.previous =\n")
+            .unwrap();
+
+        let quit: Vec<String> = keymap
+            .bindings_for(QUIT)
+            .map(|binding| binding.keys_string())
+            .collect();
+        assert_eq!(quit, vec!["ctrl+q", "q q"]);
+        assert_eq!(keymap.bindings_for(FOCUS_PREVIOUS).count(), 0);
+        assert_eq!(keymap.bindings_for(HELP).count(), 1);
+
+        assert!(matches!(
+            keymap.apply_config("quit = ctrl+c\nhelp ?"),
+            Err(KeymapError::MissingEquals { line: 2 })
+        ));
+        assert!(matches!(
+            Keymap::parse("help = hyper+h"),
+            Err(KeymapError::InvalidKey { line: 1, .. })
+        ));
+        // Failed configs leave the keymap untouched.
+        assert_eq!(keymap.bindings_for(QUIT).count(), 2);
+    }
+
+    #[test]
+    fn key_sequence_test() {
+        use super::*;
+
+        let keymap =
+            Keymap::parse("top = g g\nmark = m\nselect = m\nquit = ctrl+x ctrl+c").unwrap();
+        let mut state = KeymapState::new();
+        let key = |char| InputEvent::new(KeyCode::Char(char), KeyModifiers::NONE);
+        let ctrl = |char| InputEvent::new(KeyCode::Char(char), KeyModifiers::CONTROL);
+
+        assert_eq!(state.feed(&keymap, key('g')), Vec::<&str>::new());
+        assert!(state.is_pending());
+        assert_eq!(state.feed(&keymap, key('g')), vec!["top"]);
+
+        // A key breaking the sequence can still trigger its own binding.
+        assert_eq!(state.feed(&keymap, key('g')), Vec::<&str>::new());
+        assert_eq!(state.feed(&keymap, key('m')), vec!["mark", "select"]);
+
+        assert_eq!(state.feed(&keymap, ctrl('x')), Vec::<&str>::new());
+        assert_eq!(state.feed(&keymap, ctrl('c')), vec!["quit"]);
+        assert!(!state.is_pending());
+    }
+}
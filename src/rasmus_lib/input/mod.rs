@@ -1,34 +1,35 @@
 use crossterm::event;
 use crossterm::event::{Event, KeyEvent};
+use crossterm::QueueableCommand;
 // use crossterm::event::{KeyCode, KeyModifiers};
 // use lazy_static::lazy_static;
 // use std::cell::RefCell;
-use static_init::dynamic;
 use std::io::Read;
 use std::io::Write;
 use std::io::{self, stdout};
+use std::str;
 use std::str::FromStr;
-use std::sync::mpsc::{self, Sender};
 use std::sync::Mutex;
 use std::time::Duration;
-use std::{str, thread};
 
-use self::string_editor::StringEditor;
+use self::string_editor::{LinePrinter, StringEditor};
+use crate::ansi_term_old;
 use crate::ansi_term_old::Command;
 use crate::linear_ui::{Buffer, Clearable, Render};
-use crate::utils::PeekableReceiver;
+use crate::math::*;
 use crate::utils::StrUtils;
-use crate::{ansi_term_old, print_crlf};
 
+pub mod keymap;
+pub mod source;
 pub mod string_editor;
 
+pub use source::*;
+
 pub use crossterm::event::KeyCode;
+pub use crossterm::event::KeyEventKind;
 pub use crossterm::event::KeyModifiers;
-
-#[dynamic]
-pub static INPUT: Input = Input::new();
-// lazy_static! {
-// }
+pub use crossterm::event::MouseButton;
+pub use crossterm::event::MouseEventKind;
 
 pub enum InputError {
     IoError(io::Error),
@@ -39,75 +40,251 @@ pub enum InputError {
 pub struct InputEvent {
     pub code: KeyCode,
     pub modifiers: KeyModifiers,
+    /// Terminals only report repeats and releases with keyboard enhancement
+    /// enabled, see `TerminalFlags::set_keyboard_enhancement`. Otherwise
+    /// every event is a press, including the ones caused by holding a key.
+    pub kind: KeyEventKind,
 }
 
 impl InputEvent {
+    /// A key press.
     pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
-        Self { code, modifiers }
+        Self::new_with_kind(code, modifiers, KeyEventKind::Press)
+    }
+
+    pub fn new_with_kind(code: KeyCode, modifiers: KeyModifiers, kind: KeyEventKind) -> Self {
+        Self {
+            code,
+            modifiers,
+            kind,
+        }
+    }
+
+    pub fn is_release(&self) -> bool {
+        self.kind == KeyEventKind::Release
+    }
+
+    /// Whether both events are for the same key and modifiers, where a
+    /// repeat matches a press but a release only matches another release.
+    pub fn matches(&self, other: &InputEvent) -> bool {
+        self.code == other.code
+            && self.modifiers == other.modifiers
+            && self.is_release() == other.is_release()
     }
 }
 
+/// With keyboard enhancement enabled, terminals report characters typed with
+/// shift as the unshifted key with the shift modifier, like `/` with shift
+/// for `?`. These are turned back into the typed character, see
+/// `shifted_char`, so they match the same bindings either way.
 impl From<KeyEvent> for InputEvent {
     fn from(event: KeyEvent) -> Self {
+        let code = match event.code {
+            KeyCode::Char(char) if event.modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(shifted_char(char))
+            }
+            code => code,
+        };
+
         Self {
-            code: event.code,
+            code,
             modifiers: event.modifiers,
+            kind: event.kind,
         }
     }
 }
 
-pub struct Input {
-    receiver: Mutex<PeekableReceiver<InputEvent>>,
+/// Symbols typed with shift on a US keyboard layout, after the key they are
+/// typed with.
+const SHIFTED_SYMBOLS: [(char, char); 21] = [
+    ('`', '~'),
+    ('1', '!'),
+    ('2', '@'),
+    ('3', '#'),
+    ('4', '$'),
+    ('5', '%'),
+    ('6', '^'),
+    ('7', '&'),
+    ('8', '*'),
+    ('9', '('),
+    ('0', ')'),
+    ('-', '_'),
+    ('=', '+'),
+    ('[', '{'),
+    (']', '}'),
+    ('\\', '|'),
+    (';', ':'),
+    ('\'', '"'),
+    (',', '<'),
+    ('.', '>'),
+    ('/', '?'),
+];
+
+/// The character typed by pressing the key of `char` with shift, assuming a
+/// US keyboard layout for symbols. Characters that are already shifted are
+/// returned as is.
+///
+/// ```
+/// use terminal_renderer::input::shifted_char;
+///
+/// assert_eq!(shifted_char('a'), 'A');
+/// assert_eq!(shifted_char('/'), '?');
+/// assert_eq!(shifted_char('?'), '?');
+/// ```
+pub fn shifted_char(char: char) -> char {
+    if let Some((_, shifted)) = SHIFTED_SYMBOLS.iter().find(|(key, _)| *key == char) {
+        return *shifted;
+    }
+
+    let mut upper = char.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(upper), None) => upper,
+        _ => char,
+    }
 }
 
-impl Input {
-    fn handle_event(event: InputEvent, sender: &Sender<InputEvent>) {
+/// The character of the key `char` is typed with, the reverse of
+/// `shifted_char`.
+pub fn unshifted_char(char: char) -> char {
+    if let Some((key, _)) = SHIFTED_SYMBOLS.iter().find(|(_, shifted)| *shifted == char) {
+        return *key;
+    }
+
+    let mut lower = char.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(lower), None) => lower,
+        _ => char,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MouseEvent {
+    pub kind: MouseEventKind,
+    /// Zero based cell position. Relative to the screen when read from the
+    /// terminal, and to the top left corner of a panel's contents when passed
+    /// to the panel, in which case it can be negative or past its size while
+    /// dragging.
+    pub position: IVec2,
+    pub modifiers: KeyModifiers,
+}
+
+impl MouseEvent {
+    pub fn new(kind: MouseEventKind, position: IVec2, modifiers: KeyModifiers) -> Self {
+        Self {
+            kind,
+            position,
+            modifiers,
+        }
+    }
+
+    /// The same event, with its position relative to `origin`.
+    pub fn relative_to(self, origin: IVec2) -> Self {
+        Self {
+            position: self.position - origin,
+            ..self
+        }
+    }
+}
+
+impl From<event::MouseEvent> for MouseEvent {
+    fn from(event: event::MouseEvent) -> Self {
+        Self {
+            kind: event.kind,
+            position: ivec2(event.column as i32, event.row as i32),
+            modifiers: event.modifiers,
+        }
+    }
+}
+
+/// Any event read from the terminal.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TerminalEvent {
+    Key(InputEvent),
+    /// Only reported while mouse capture is enabled, see
+    /// `TerminalFlags::set_mouse_capture`.
+    Mouse(MouseEvent),
+    /// Text pasted into the terminal. Only reported while bracketed paste is
+    /// enabled, see `TerminalFlags::set_bracketed_paste`, otherwise pasted
+    /// text arrives as key presses.
+    Paste(String),
+    /// The terminal was resized to `columns` by `rows` cells.
+    Resize {
+        columns: u16,
+        rows: u16,
+    },
+}
+
+impl TerminalEvent {
+    /// Convert an event read with crossterm. Presses and releases of the
+    /// modifier keys themselves are left out, as they're already part of the
+    /// modifiers of the keys they modify.
+    pub fn from_crossterm(event: Event) -> Option<Self> {
         match event {
-            InputEvent {
-                code: KeyCode::Char('c'),
-                modifiers: KeyModifiers::CONTROL,
-            } => {
-                crate::clean_up();
-                // TerminalFlags::clean_up();
-                // flush_commands();
-                // crossterm::terminal::disable_raw_mode().expect("Help oh god why");
-
-                print_crlf!("Terminated!");
-
-                std::process::exit(130);
-            }
-            event => {
-                // print_crlf!("Sent code {:?}", event.code);
-                sender.send(event).expect("Sending failed :(");
-            }
+            Event::Key(KeyEvent {
+                code: KeyCode::Modifier(_),
+                ..
+            }) => None,
+            Event::Key(event) => Some(Self::Key(event.into())),
+            Event::Mouse(event) => Some(Self::Mouse(event.into())),
+            Event::Paste(text) => Some(Self::Paste(text)),
+            Event::Resize(columns, rows) => Some(Self::Resize { columns, rows }),
+            Event::FocusGained | Event::FocusLost => None,
         }
     }
+}
 
-    pub fn new() -> Self {
-        let (sender, receiver) = mpsc::channel();
+impl From<InputEvent> for TerminalEvent {
+    fn from(event: InputEvent) -> Self {
+        Self::Key(event)
+    }
+}
 
-        thread::spawn(move || loop {
-            // if !event::poll(Duration::from_millis(500)).unwrap() {
-            //     continue;
-            // }
+/// Blocking and non-blocking access to terminal events, used by the linear
+/// ui. Reads the terminal by default, see `Input::with_source`.
+///
+/// Only one `Input` or `Gui` should read the terminal at a time, as they
+/// would split its events between them.
+pub struct Input {
+    source: Mutex<PeekableSource>,
+    /// The lines read by `read_string`, which can be browsed with up and
+    /// down in later prompts.
+    history: Mutex<Vec<String>>,
+}
 
-            if let Event::Key(event) = event::read().unwrap() {
-                // print_crlf!("from thread: {:?}", event.code);
-                Self::handle_event(event.into(), &sender);
-            }
-        });
+struct PeekableSource {
+    source: Box<dyn InputSource + Send>,
+    peeked: Option<TerminalEvent>,
+}
 
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self::with_source(CrosstermInputSource::new())
+    }
+
+    /// Read events from `source` instead of the terminal.
+    pub fn with_source(source: impl InputSource + Send + 'static) -> Self {
         Self {
-            receiver: Mutex::new(PeekableReceiver::new(receiver)),
+            source: Mutex::new(PeekableSource {
+                source: Box::new(source),
+                peeked: None,
+            }),
+            history: Mutex::new(Vec::new()),
         }
     }
 
     pub fn is_empty(&self) -> bool {
-        self.receiver
-            .lock()
-            .expect("couldn't lock mutex")
-            .is_empty()
-            .expect("input channel disconnected")
+        let mut source = self.source.lock().expect("couldn't lock mutex");
+        if source.peeked.is_none() {
+            source.peeked = source.source.poll_event();
+        }
+
+        source.peeked.is_none()
     }
 
     /// Returns an iterator over input events.
@@ -128,46 +305,64 @@ impl Input {
     /// Attempts to waits for the next input event, returning `None` if the input
     /// channel has hanged up.
     ///
+    /// Events other than key presses and repeats are skipped, see
+    /// `get_terminal_event`.
+    ///
     /// # Panics
     ///
     /// Panics if the input sender thread has panicked, causing the mutex lock
     /// to fail.
     pub fn get_event(&self) -> Option<InputEvent> {
-        let event = self
-            .receiver
-            .lock()
-            .expect("couldn't lock input mutex")
-            .recv();
-
-        event.ok()
+        loop {
+            match self.get_terminal_event()? {
+                TerminalEvent::Key(event) if !event.is_release() => return Some(event),
+                _ => {}
+            }
+        }
     }
 
     /// Returns the next available input event if available, returning `None` if
     /// all inputs have been processed or the input channel has disconnected
     /// (maybe it should panic if that's the case?).
     ///
+    /// Events other than key presses and repeats are skipped, see
+    /// `try_get_terminal_event`.
+    ///
     /// # Panics
     ///
     /// Panics if the input sender thread has panicked, causing the mutex lock
     /// to fail.
     pub fn try_get_event(&self) -> Option<InputEvent> {
-        let event = self
-            .receiver
-            .lock()
-            .expect("couldn't lock input mutex")
-            .try_recv();
+        loop {
+            match self.try_get_terminal_event()? {
+                TerminalEvent::Key(event) if !event.is_release() => return Some(event),
+                _ => {}
+            }
+        }
+    }
 
-        event.ok()
+    /// Like `get_event`, but also returns events other than key presses.
+    pub fn get_terminal_event(&self) -> Option<TerminalEvent> {
+        let mut source = self.source.lock().expect("couldn't lock input mutex");
+
+        source.peeked.take().or_else(|| source.source.wait_event())
+    }
+
+    /// Like `try_get_event`, but also returns events other than key presses.
+    pub fn try_get_terminal_event(&self) -> Option<TerminalEvent> {
+        let mut source = self.source.lock().expect("couldn't lock input mutex");
+
+        source.peeked.take().or_else(|| source.source.poll_event())
+    }
+
+    /// Returns an iterator over all currently available terminal events.
+    pub fn terminal_events(&self) -> TerminalEventIter<'_> {
+        TerminalEventIter { input: self }
     }
 
     pub fn read_char(&self) -> char {
         loop {
-            let event = self
-                .receiver
-                .lock()
-                .expect("Couldn't unlock mutex")
-                .recv()
-                .expect("Input failed");
+            let event = self.get_event().expect("Input failed");
             match event {
                 InputEvent {
                     code: KeyCode::Char(char),
@@ -191,12 +386,7 @@ impl Input {
 
     pub fn loop_input<F: FnMut(InputEvent) -> bool>(&self, mut f: F) {
         loop {
-            let event = self
-                .receiver
-                .lock()
-                .expect("Couldn't unlock mutex")
-                .recv()
-                .expect("Input failed");
+            let event = self.get_event().expect("Input failed");
 
             if !f(event) {
                 break;
@@ -214,86 +404,18 @@ impl Input {
     ///
     /// Panics if stdin couldn't be read or stdin.flush fails.
     pub fn read_string(&self) -> String {
-        let mut editor = StringEditor::new();
-
-        // let mut stdout = io::stdout();
-
-        // let mut error = None;
-        self.loop_input(|key| {
-            let input: Option<String> = match key {
-                InputEvent {
-                    code: KeyCode::Char(char),
-                    ..
-                } => Some(char.to_string()),
-                InputEvent {
-                    code: KeyCode::Enter,
-                    ..
-                } => Some("\r\n".to_string()),
-                InputEvent {
-                    code: KeyCode::Tab, ..
-                } => Some('\t'.to_string()),
-                InputEvent {
-                    code: KeyCode::Backspace,
-                    modifiers: KeyModifiers::NONE,
-                    ..
-                } => {
-                    editor.delete();
-                    None
-                }
-                InputEvent {
-                    code: KeyCode::Backspace,
-                    modifiers: KeyModifiers::ALT,
-                    ..
-                } => {
-                    editor.delete_word();
-                    None
-                }
-                InputEvent {
-                    code: KeyCode::Left,
-                    modifiers: KeyModifiers::NONE,
-                    ..
-                } => {
-                    editor.move_left();
-                    None
-                }
-                InputEvent {
-                    code: KeyCode::Left,
-                    modifiers: KeyModifiers::CONTROL,
-                    ..
-                } => {
-                    editor.move_left_word();
-                    None
-                }
-                InputEvent {
-                    code: KeyCode::Right,
-                    modifiers: KeyModifiers::NONE,
-                    ..
-                } => {
-                    editor.move_right();
-                    None
-                }
-                InputEvent {
-                    code: KeyCode::Right,
-                    modifiers: KeyModifiers::CONTROL,
-                    ..
-                } => {
-                    editor.move_right_word();
-                    None
-                }
-                _ => None,
-            };
-            if let Some(input) = input {
-                editor.write(&input);
-                if input == "\r\n" {
-                    return false;
-                }
-            }
-            true
-        });
+        let history = self.history.lock().expect("couldn't lock mutex").clone();
+        let mut editor = StringEditor::new().with_history(history);
+        let mut printer = LinePrinter::new();
 
+        show_prompt_cursor(true);
+        self.loop_input(|event| edit_prompt(&mut editor, &mut printer, &event));
+        show_prompt_cursor(false);
         print!("\r\n");
 
-        editor.close() + "\r\n"
+        let line = editor.submit();
+        *self.history.lock().expect("couldn't lock mutex") = editor.history().to_vec();
+        line + "\r\n"
     }
 
     fn read_input<T: FromStr>(&self) -> InputOut<T> {
@@ -477,6 +599,18 @@ impl<'a> Iterator for InputBlockingIter<'a> {
     }
 }
 
+pub struct TerminalEventIter<'a> {
+    input: &'a Input,
+}
+
+impl<'a> Iterator for TerminalEventIter<'a> {
+    type Item = TerminalEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.input.try_get_terminal_event()
+    }
+}
+
 pub struct InputOut<T: FromStr> {
     pub result: Result<T, InputError>,
     // input_lines: u32,
@@ -596,124 +730,47 @@ pub fn wait_for_key(key: KeyCode) -> crossterm::Result<()> {
 #[allow(deprecated)]
 pub fn read_string() -> InputResult<String> {
     let mut editor = StringEditor::new();
+    let mut printer = LinePrinter::new();
 
-    // let mut stdout = io::stdout();
-
-    // let mut error = None;
-    if let Err(err) = loop_input(|key| {
-        let input: Option<String> = match key {
-            KeyEvent {
-                code: KeyCode::Char(char),
-                ..
-            } => Some(char.to_string()),
-            KeyEvent {
-                code: KeyCode::Enter,
-                ..
-            } => Some("\r\n".to_string()),
-            KeyEvent {
-                code: KeyCode::Tab, ..
-            } => Some('\t'.to_string()),
-            KeyEvent {
-                code: KeyCode::Backspace,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => {
-                editor.delete();
-                None
-            }
-            KeyEvent {
-                code: KeyCode::Backspace,
-                modifiers: KeyModifiers::ALT,
-                ..
-            } => {
-                editor.delete_word();
-                None
-            }
-            KeyEvent {
-                code: KeyCode::Left,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => {
-                editor.move_left();
-                None
-            }
-            KeyEvent {
-                code: KeyCode::Left,
-                modifiers: KeyModifiers::CONTROL,
-                ..
-            } => {
-                editor.move_left_word();
-                None
-            }
-            KeyEvent {
-                code: KeyCode::Right,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => {
-                editor.move_right();
-                None
-            }
-            KeyEvent {
-                code: KeyCode::Right,
-                modifiers: KeyModifiers::CONTROL,
-                ..
-            } => {
-                editor.move_right_word();
-                None
-            }
-            _ => None,
-        };
-        if let Some(input) = input {
-            editor.write(&input);
-            if input == "\r\n" {
-                return false;
-            }
-        }
-        true
-    }) {
+    show_prompt_cursor(true);
+    let result = loop_input(|key| edit_prompt(&mut editor, &mut printer, &key.into()));
+    show_prompt_cursor(false);
+    if let Err(err) = result {
         return Err(InputError::IoError(err));
-    };
+    }
+    print!("\r\n");
 
-    // if let Some(error) = error {
-    //     return Err(error);
-    // }
+    Ok(editor.close() + "\r\n")
+}
 
-    // let mut bytes: Vec<u8> = Vec::new();
-    // let mut out = String::new();
-
-    // let mut char_buffer = [0u8; 4];
-    // let mut current_index = 0;
-    // let mut buf = [0; 1];
-    // while io::stdin().read(&mut buf).expect("Failed to read line") == 1 {
-    //     char_buffer[current_index] = buf[0];
-    //     current_index += 1;
-    //     if let Ok(string) = str::from_utf8(&char_buffer[0..current_index]) {
-    //         let char = string.chars().next().expect("invalid utf-8 byte sequence");
-    //         out.push(char);
-    //         print!("{}", char);
-    //         if char == '\r' {
-    //             out.push('\n');
-    //             print!("\n");
-    //         }
-    //         current_index = 0;
-
-    //         io::stdout().flush().expect("Couldn't flush output");
-
-    //         if char == '\r' {
-    //             break;
-    //         }
-    //     }
-    //     // println!("{}\r", buf[0]);
-    //     // print!("{}", buf[0] as char);
-    //     // io::stdout().flush().expect("Couldn't flush output");
-    //     // let char = buf[0] as char;
-    //     bytes.push(buf[0]);
-    // }
-    print!("\r\n");
+/// Apply `event` to the line being read by `editor`, drawing it again.
+/// Returns `false` once enter submits it.
+fn edit_prompt(editor: &mut StringEditor, printer: &mut LinePrinter, event: &InputEvent) -> bool {
+    if event.code == KeyCode::Enter {
+        return false;
+    }
 
-    // // let out_bytes = String::from_utf8(bytes).expect("Invalid utf-8 input");
+    if editor.handle_key(event) {
+        print!("{}", printer.print(editor));
+        stdout().flush().expect("couldn't flush stdout");
+    }
+    true
+}
 
-    Ok(editor.close() + "\r\n")
+/// Show a blinking cursor while a prompt is read.
+fn show_prompt_cursor(show: bool) {
+    let mut stdout = stdout();
+    let result = match show {
+        true => stdout
+            .queue(crossterm::cursor::Show)
+            .and_then(|stdout| stdout.queue(crossterm::cursor::EnableBlinking)),
+        false => stdout
+            .queue(crossterm::cursor::Hide)
+            .and_then(|stdout| stdout.queue(crossterm::cursor::DisableBlinking)),
+    };
+    result
+        .and_then(|stdout| stdout.flush())
+        .expect("couldn't change the cursor");
 }
 
 #[deprecated]
@@ -0,0 +1,463 @@
+//! Where terminal events come from.
+//!
+//! The terminal is read by `CrosstermInputSource`, while
+//! `ScriptedInputSource` replays a fixed list of events, which lets the gui
+//! be driven without a terminal, like in tests.
+
+use super::keymap::KeyChord;
+use super::{
+    InputEvent, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    TerminalEvent,
+};
+use crate::flags::TerminalFlags;
+use crate::math::*;
+use crossterm::event;
+use std::collections::VecDeque;
+use std::error;
+use std::fmt::{self, Display};
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+pub trait InputSource {
+    /// Returns the next event if one is available, without blocking.
+    fn poll_event(&mut self) -> Option<TerminalEvent>;
+
+    /// Waits for the next event, returning `None` if there will never be
+    /// another one.
+    fn wait_event(&mut self) -> Option<TerminalEvent>;
+
+    /// Whether key releases are reported, see `InputStream::is_held`.
+    fn reports_releases(&self) -> bool {
+        false
+    }
+
+    /// Whether every event has been read, and no more will arrive. Sources
+    /// reading the terminal are never finished.
+    fn is_finished(&self) -> bool {
+        false
+    }
+}
+
+/// Reads events from the terminal on a background thread.
+///
+/// The thread is only started when events are first requested, so nothing
+/// reads from stdin before then. See `TerminalCapabilities::probe`.
+pub struct CrosstermInputSource {
+    receiver: Option<Receiver<TerminalEvent>>,
+    exit_on_ctrl_c: Arc<AtomicBool>,
+}
+
+impl CrosstermInputSource {
+    pub fn new() -> Self {
+        Self {
+            receiver: None,
+            exit_on_ctrl_c: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Whether pressing Ctrl+C exits the process immediately, which is the
+    /// default. When disabled, it's delivered like any other key, so it can
+    /// be bound to an action (like `quit` in the `Gui`).
+    pub fn with_exit_on_ctrl_c(self, enabled: bool) -> Self {
+        self.set_exit_on_ctrl_c(enabled);
+        self
+    }
+
+    pub fn set_exit_on_ctrl_c(&self, enabled: bool) {
+        self.exit_on_ctrl_c.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn exit_on_ctrl_c(&self) -> bool {
+        self.exit_on_ctrl_c.load(Ordering::Relaxed)
+    }
+
+    fn receiver(&mut self) -> &Receiver<TerminalEvent> {
+        let exit_on_ctrl_c = &self.exit_on_ctrl_c;
+
+        self.receiver.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel();
+            let exit_on_ctrl_c = exit_on_ctrl_c.clone();
+
+            thread::spawn(move || loop {
+                match TerminalEvent::from_crossterm(event::read().unwrap()) {
+                    Some(TerminalEvent::Key(event)) => {
+                        Self::handle_event(event, &sender, &exit_on_ctrl_c);
+                    }
+                    Some(event) => sender.send(event).expect("Sending failed :("),
+                    None => {}
+                }
+            });
+
+            receiver
+        })
+    }
+
+    fn handle_event(
+        event: InputEvent,
+        sender: &Sender<TerminalEvent>,
+        exit_on_ctrl_c: &AtomicBool,
+    ) {
+        match event {
+            InputEvent {
+                code: KeyCode::Char('c'),
+                modifiers: KeyModifiers::CONTROL,
+                kind: KeyEventKind::Press,
+            } if exit_on_ctrl_c.load(Ordering::Relaxed) => {
+                crate::clean_up();
+
+                crate::print_crlf!("Terminated!");
+
+                std::process::exit(130);
+            }
+            event => {
+                sender.send(event.into()).expect("Sending failed :(");
+            }
+        }
+    }
+}
+
+impl Default for CrosstermInputSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputSource for CrosstermInputSource {
+    fn poll_event(&mut self) -> Option<TerminalEvent> {
+        self.receiver().try_recv().ok()
+    }
+
+    fn wait_event(&mut self) -> Option<TerminalEvent> {
+        self.receiver().recv().ok()
+    }
+
+    fn reports_releases(&self) -> bool {
+        TerminalFlags::is_keyboard_enhancement()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ScriptStep {
+    Event(TerminalEvent),
+    /// No more events are available until the next poll, ending the tick of
+    /// the input manager.
+    EndTick,
+}
+
+/// Replays a fixed list of events, split into ticks.
+///
+/// Scripts can also be written as text, with one command per line:
+///
+/// ```text
+/// # Comments start with '#'.
+/// key ctrl+x
+/// key w release
+/// text hello world
+/// paste first line\nsecond line
+/// click 10 4
+/// mouse scroll_down 10 4
+/// resize 80 24
+/// # Ends the tick, so the events after it are read by the next one.
+/// tick
+/// ```
+///
+/// Keys use the keymap syntax, see `KeyChord`, and are pressed unless
+/// followed by `repeat` or `release`. Mouse positions are zero based, and
+/// mouse buttons default to `left`. Pasted text uses `\n` for newlines.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedInputSource {
+    steps: VecDeque<ScriptStep>,
+    reports_releases: bool,
+}
+
+impl ScriptedInputSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deliver all `events` during the first tick.
+    pub fn from_events(events: impl IntoIterator<Item = TerminalEvent>) -> Self {
+        let mut source = Self::new();
+        for event in events {
+            source.push(event);
+        }
+        source
+    }
+
+    /// Deliver each list of events during its own tick.
+    pub fn from_ticks(ticks: impl IntoIterator<Item = Vec<TerminalEvent>>) -> Self {
+        let mut source = Self::new();
+        for events in ticks {
+            for event in events {
+                source.push(event);
+            }
+            source.end_tick();
+        }
+        source
+    }
+
+    /// Parse a script, see the type documentation for the format.
+    pub fn parse(script: &str) -> Result<Self, ScriptError> {
+        let mut source = Self::new();
+
+        for (index, line) in script.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (command, arguments) = line.split_once(' ').unwrap_or((line, ""));
+            source
+                .parse_command(command, arguments)
+                .map_err(|error| error.at_line(line_number))?;
+        }
+
+        Ok(source)
+    }
+
+    /// Load a script from a file, see `parse`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    fn parse_command(&mut self, command: &str, arguments: &str) -> Result<(), ScriptError> {
+        let mut words = arguments.split_whitespace();
+
+        match command {
+            "key" => {
+                let key = next_word(&mut words)?;
+                let chord: KeyChord = key.parse().map_err(|_| invalid_argument(key))?;
+                let kind = match words.next() {
+                    None | Some("press") => KeyEventKind::Press,
+                    Some("repeat") => KeyEventKind::Repeat,
+                    Some("release") => KeyEventKind::Release,
+                    Some(argument) => return Err(invalid_argument(argument)),
+                };
+                self.push(TerminalEvent::Key(InputEvent::new_with_kind(
+                    chord.code,
+                    chord.modifiers,
+                    kind,
+                )));
+            }
+            "text" => {
+                for char in arguments.chars() {
+                    self.push(InputEvent::new(KeyCode::Char(char), KeyModifiers::NONE).into());
+                }
+            }
+            "paste" => self.push(TerminalEvent::Paste(arguments.replace("\\n", "\n"))),
+            "click" | "mouse" => {
+                let kind = match command {
+                    "click" => None,
+                    _ => Some(next_word(&mut words)?),
+                };
+                let x = parse_number(next_word(&mut words)?)?;
+                let y = parse_number(next_word(&mut words)?)?;
+                let button = match words.next() {
+                    None | Some("left") => MouseButton::Left,
+                    Some("right") => MouseButton::Right,
+                    Some("middle") => MouseButton::Middle,
+                    Some(argument) => return Err(invalid_argument(argument)),
+                };
+
+                let kinds = match kind {
+                    None => vec![MouseEventKind::Down(button), MouseEventKind::Up(button)],
+                    Some("down") => vec![MouseEventKind::Down(button)],
+                    Some("up") => vec![MouseEventKind::Up(button)],
+                    Some("drag") => vec![MouseEventKind::Drag(button)],
+                    Some("move") => vec![MouseEventKind::Moved],
+                    Some("scroll_down") => vec![MouseEventKind::ScrollDown],
+                    Some("scroll_up") => vec![MouseEventKind::ScrollUp],
+                    Some(argument) => return Err(invalid_argument(argument)),
+                };
+                for kind in kinds {
+                    self.push(TerminalEvent::Mouse(MouseEvent::new(
+                        kind,
+                        ivec2(x as i32, y as i32),
+                        KeyModifiers::NONE,
+                    )));
+                }
+            }
+            "resize" => {
+                let columns = parse_number(next_word(&mut words)?)?;
+                let rows = parse_number(next_word(&mut words)?)?;
+                self.push(TerminalEvent::Resize { columns, rows });
+            }
+            "tick" => self.end_tick(),
+            command => {
+                return Err(ScriptError::UnknownCommand {
+                    line: 0,
+                    command: command.to_owned(),
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add an event to the current tick.
+    pub fn push(&mut self, event: TerminalEvent) {
+        if matches!(event, TerminalEvent::Key(event) if event.is_release()) {
+            self.reports_releases = true;
+        }
+        self.steps.push_back(ScriptStep::Event(event));
+    }
+
+    /// End the current tick, so the following events are read by the next
+    /// one.
+    pub fn end_tick(&mut self) {
+        self.steps.push_back(ScriptStep::EndTick);
+    }
+}
+
+impl InputSource for ScriptedInputSource {
+    fn poll_event(&mut self) -> Option<TerminalEvent> {
+        match self.steps.pop_front()? {
+            ScriptStep::Event(event) => Some(event),
+            ScriptStep::EndTick => None,
+        }
+    }
+
+    fn wait_event(&mut self) -> Option<TerminalEvent> {
+        loop {
+            if let ScriptStep::Event(event) = self.steps.pop_front()? {
+                return Some(event);
+            }
+        }
+    }
+
+    fn reports_releases(&self) -> bool {
+        self.reports_releases
+    }
+
+    fn is_finished(&self) -> bool {
+        !self
+            .steps
+            .iter()
+            .any(|step| matches!(step, ScriptStep::Event(_)))
+    }
+}
+
+fn next_word<'a>(words: &mut impl Iterator<Item = &'a str>) -> Result<&'a str, ScriptError> {
+    words.next().ok_or(ScriptError::MissingArgument { line: 0 })
+}
+
+fn invalid_argument(argument: &str) -> ScriptError {
+    ScriptError::InvalidArgument {
+        line: 0,
+        argument: argument.to_owned(),
+    }
+}
+
+fn parse_number(argument: &str) -> Result<u16, ScriptError> {
+    argument.parse().map_err(|_| invalid_argument(argument))
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(io::Error),
+    UnknownCommand { line: usize, command: String },
+    MissingArgument { line: usize },
+    InvalidArgument { line: usize, argument: String },
+}
+
+impl ScriptError {
+    fn at_line(self, line: usize) -> Self {
+        match self {
+            ScriptError::Io(error) => ScriptError::Io(error),
+            ScriptError::UnknownCommand { command, .. } => {
+                ScriptError::UnknownCommand { line, command }
+            }
+            ScriptError::MissingArgument { .. } => ScriptError::MissingArgument { line },
+            ScriptError::InvalidArgument { argument, .. } => {
+                ScriptError::InvalidArgument { line, argument }
+            }
+        }
+    }
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io(error) => write!(f, "couldn't read input script: {}", error),
+            ScriptError::UnknownCommand { line, command } => {
+                write!(f, "unknown command \"{}\" at line {}", command, line)
+            }
+            ScriptError::MissingArgument { line } => {
+                write!(f, "missing argument at line {}", line)
+            }
+            ScriptError::InvalidArgument { line, argument } => {
+                write!(f, "invalid argument \"{}\" at line {}", argument, line)
+            }
+        }
+    }
+}
+
+impl error::Error for ScriptError {}
+
+impl From<io::Error> for ScriptError {
+    fn from(error: io::Error) -> Self {
+        ScriptError::Io(error)
+    }
+}
+
+mod tests {
+    #[test]
+    fn parse_script_test() {
+        use super::*;
+
+        let mut source = ScriptedInputSource::parse(
+            "# comment\nkey ctrl+x\ntext ab\ntick\nclick 3 4 right\nresize 80 24\n",
+        )
+        .unwrap();
+
+        let key = |code, modifiers| TerminalEvent::Key(InputEvent::new(code, modifiers));
+        assert_eq!(
+            source.poll_event(),
+            Some(key(KeyCode::Char('x'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            source.poll_event(),
+            Some(key(KeyCode::Char('a'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            source.poll_event(),
+            Some(key(KeyCode::Char('b'), KeyModifiers::NONE))
+        );
+        // The end of the first tick.
+        assert_eq!(source.poll_event(), None);
+
+        let click =
+            |kind| TerminalEvent::Mouse(MouseEvent::new(kind, ivec2(3, 4), KeyModifiers::NONE));
+        assert_eq!(
+            source.wait_event(),
+            Some(click(MouseEventKind::Down(MouseButton::Right)))
+        );
+        assert_eq!(
+            source.wait_event(),
+            Some(click(MouseEventKind::Up(MouseButton::Right)))
+        );
+        assert_eq!(
+            source.wait_event(),
+            Some(TerminalEvent::Resize {
+                columns: 80,
+                rows: 24
+            })
+        );
+        assert!(source.is_finished());
+        assert!(!source.reports_releases());
+
+        assert!(matches!(
+            ScriptedInputSource::parse("key w\nkey w hold"),
+            Err(ScriptError::InvalidArgument { line: 2, .. })
+        ));
+        assert!(matches!(
+            ScriptedInputSource::parse("jump"),
+            Err(ScriptError::UnknownCommand { line: 1, .. })
+        ));
+    }
+}
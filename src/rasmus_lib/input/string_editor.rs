@@ -1,209 +1,831 @@
-use crossterm::{cursor, QueueableCommand};
-use std::io::{stdout, Write};
+//! Editing a single line of text, separate from how it's shown.
+//!
+//! `StringEditor` only keeps track of the text, cursor and selection, so the
+//! same editing behavior backs the prompts of `Input::read_string`, drawn with
+//! `LinePrinter`, and `ui::TextInput` panels.
 
-use crate::ansi_term_old;
-// use crate::ansi_term::Color;
-use crate::ansi_term_old::Command;
-use crate::ansi_term_old::CursorMovement::*;
-// use crate::ansi_term::Format;
-use crate::utils::StrUtils;
+use std::ops::Range;
 
+use super::{InputEvent, KeyCode, KeyModifiers};
+use crate::ansi_term::*;
+use crate::utils::{str_width, StrUtils};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The most undo steps kept, older ones are forgotten.
+const MAX_UNDO_STEPS: usize = 100;
+/// The most killed texts kept for yanking.
+const MAX_KILL_RING_LENGTH: usize = 16;
+
+/// A way to move the cursor, see `StringEditor::move_cursor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Movement {
+    /// One grapheme to the left.
+    Left,
+    Right,
+    /// To the closest word boundary, see `StrUtils::next_word_boundary_left`.
+    WordLeft,
+    WordRight,
+    Home,
+    End,
+}
+
+/// The kind of the last edit, for grouping consecutive ones into a single
+/// undo step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Insert,
+    Delete,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    string: String,
+    cursor: usize,
+}
+
+/// The text that was last yanked, so `yank_pop` can replace it.
+#[derive(Debug, Clone)]
+struct Yank {
+    range: Range<usize>,
+    /// How many entries from the newest one of the kill ring it is.
+    depth: usize,
+}
+
+/// A line of text being edited.
+///
+/// Positions are byte positions into `string`, which always lie on grapheme
+/// boundaries, so combined characters and emoji are moved over and deleted as
+/// a whole.
+///
+/// Besides the usual editing, it supports:
+/// - a selection, extended by moving with `select`,
+/// - undo and redo, where typing a word or deleting a run of characters is a
+///   single step,
+/// - a kill ring, filled by deleting words or to the ends of the line, which
+///   can be yanked back,
+/// - a history of previously submitted lines.
+///
+/// `handle_key` maps key presses to these like a shell does.
+///
+/// ```
+/// # use terminal_renderer::input::string_editor::{Movement, StringEditor};
+/// let mut editor = StringEditor::from("hello wörld");
+/// editor.move_cursor(Movement::WordLeft);
+/// editor.kill_to_end();
+/// editor.insert("there");
+/// assert_eq!(editor.string(), "hello there");
+///
+/// editor.yank();
+/// assert_eq!(editor.string(), "hello therewörld");
+///
+/// editor.undo();
+/// assert_eq!(editor.string(), "hello there");
+/// ```
+#[derive(Debug, Clone, Default)]
 pub struct StringEditor {
-    pub string: String,
-    cursor_position: usize,
-    string_length: usize,
+    string: String,
+    cursor: usize,
+    /// The end of the selection the cursor isn't at.
+    anchor: Option<usize>,
+    undo_stack: Vec<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    last_edit: Option<EditKind>,
+    kill_ring: Vec<String>,
+    last_yank: Option<Yank>,
+    history: Vec<String>,
+    /// The history entry being shown, if any.
+    history_index: Option<usize>,
+    /// The line being edited before browsing the history.
+    draft: String,
 }
 
 impl StringEditor {
     pub fn new() -> Self {
-        stdout()
-            .queue(cursor::Show)
-            .expect("I hate this")
-            .queue(cursor::EnableBlinking)
-            .expect("I hate this")
-            .flush()
-            .expect("I hate this");
-        Self {
-            string: String::new(),
-            cursor_position: 0,
-            string_length: 0,
-        }
-    }
-
-    pub fn write(&mut self, str: &str) {
-        // if str.contains("\n") {
-        //     panic!("Oh god! StringEditor doesn't support newline charchters");
-        // }
-        let filter_string: String;
-        let filtered = if str.contains('\n') {
-            filter_string = str
-                .chars()
-                .filter(|char| char != &'\n' && char != &'\r')
-                .collect::<String>();
-            &filter_string
-        } else {
-            str
-        };
+        Self::default()
+    }
+
+    /// Previously submitted lines, oldest first, to browse with
+    /// `history_previous`.
+    pub fn with_history(mut self, history: Vec<String>) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn string(&self) -> &str {
+        &self.string
+    }
 
-        let length = filtered.chars().count();
+    pub fn is_empty(&self) -> bool {
+        self.string.is_empty()
+    }
+
+    /// Replace the text, moving the cursor to its end. This can be undone.
+    pub fn set_string(&mut self, string: impl Into<String>) {
+        self.edit(EditKind::Other);
+        self.string = sanitize(&string.into());
+        self.cursor = self.string.len();
+        self.anchor = None;
+    }
+
+    /// The byte position of the cursor.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
 
-        if self.cursor_position >= self.string_length {
-            self.string.push_str(filtered);
-        } else {
-            self.string.insert_str(self.cursor_position, filtered);
+    /// The column of the cursor, counted in graphemes from the start.
+    pub fn cursor_column(&self) -> usize {
+        self.string[..self.cursor].grapheme_len()
+    }
+
+    /// Move the cursor to the byte `position`, rounded down to a grapheme
+    /// boundary. Clears the selection.
+    pub fn set_cursor(&mut self, position: usize) {
+        self.cursor = self
+            .string
+            .grapheme_indices(true)
+            .map(|(index, _)| index)
+            .take_while(|index| *index <= position)
+            .last()
+            .unwrap_or(0);
+        if position >= self.string.len() {
+            self.cursor = self.string.len();
         }
-        print!(
-            "{}",
-            self.string
-                .chars()
-                .skip(self.cursor_position)
-                .collect::<String>()
-        );
+        self.anchor = None;
+        self.end_edit();
+    }
 
-        self.cursor_position += length;
-        self.string_length += length;
+    /// Move the cursor to the grapheme `column`, like when clicking it.
+    pub fn set_cursor_column(&mut self, column: usize) {
+        let position = self
+            .string
+            .grapheme_indices(true)
+            .nth(column)
+            .map_or(self.string.len(), |(position, _)| position);
+        self.set_cursor(position);
+    }
 
-        ansi_term_old::perform_commands(&[Command::Move(Left(
-            (self.string_length - self.cursor_position) as u32,
-        ))]);
+    /// The selected range, if anything is selected.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        if anchor == self.cursor {
+            return None;
+        }
 
-        stdout()
-            .queue(cursor::MoveLeft(1))
-            .expect("Hate")
-            .queue(cursor::MoveRight(1))
-            .expect("Hate")
-            .flush()
-            .expect("Hate");
-        stdout().flush().expect("Help! Flush didn't work");
+        Some(anchor.min(self.cursor)..anchor.max(self.cursor))
     }
 
-    pub fn move_left(&mut self) {
-        self.move_left_chars(1);
+    pub fn selected_text(&self) -> Option<&str> {
+        Some(&self.string[self.selection()?])
     }
-    pub fn move_right(&mut self) {
-        self.move_right_chars(1);
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.string.len();
+        self.end_edit();
     }
 
-    pub fn move_left_word(&mut self) {
-        let boundary = self.string.next_word_boundary_left(self.cursor_position);
-        self.move_left_chars(self.cursor_position - boundary);
+    pub fn clear_selection(&mut self) {
+        self.anchor = None;
     }
-    pub fn move_right_word(&mut self) {
-        let boundary = self.string.next_word_boundary_right(self.cursor_position);
-        self.move_right_chars(boundary - self.cursor_position);
+
+    /// Move the cursor, clearing the selection.
+    pub fn move_cursor(&mut self, movement: Movement) {
+        self.cursor = self.target(movement);
+        self.anchor = None;
+        self.end_edit();
     }
 
-    pub fn delete(&mut self) {
-        if self.string_length == 0 {
+    /// Move the cursor, selecting the text it moves over.
+    pub fn select(&mut self, movement: Movement) {
+        self.anchor.get_or_insert(self.cursor);
+        self.cursor = self.target(movement);
+        self.end_edit();
+    }
+
+    /// Insert `text` at the cursor, replacing the selection. Control
+    /// characters like newlines are left out, since the text is a single
+    /// line.
+    pub fn insert(&mut self, text: &str) {
+        let text = sanitize(text);
+        if text.is_empty() {
             return;
         }
-        if self.cursor_position >= self.string_length {
-            self.string.pop();
 
-            ansi_term_old::perform_commands(&[Command::Move(Left(1)), Command::EraseToLineEnd]);
-            self.cursor_position -= 1;
-        } else {
-            self.cursor_position -= 1;
+        // Words are undone one at a time, along with the spaces after them.
+        let before = &self.string[..self.cursor];
+        let new_word =
+            before.ends_with(char::is_whitespace) && !text.starts_with(char::is_whitespace);
+        if new_word || self.selection().is_some() {
+            self.last_edit = None;
+        }
+        self.edit(EditKind::Insert);
 
-            self.string.remove(self.cursor_position);
-            ansi_term_old::perform_commands(&[Command::Move(Left(1))]);
+        self.delete_selection();
+        self.string.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
 
-            let shifted_string = self
-                .string
-                .chars()
-                .skip(self.cursor_position)
-                .collect::<String>();
+    /// Delete the selection, or the grapheme left of the cursor.
+    pub fn delete_backward(&mut self) {
+        self.delete_towards(Movement::Left, false);
+    }
 
-            print!("{}", shifted_string);
-            ansi_term_old::perform_commands(&[Command::Move(Left(shifted_string.len() as u32))]);
-        }
-        stdout().flush().expect("Help! Flush didn't work");
+    /// Delete the selection, or the grapheme right of the cursor.
+    pub fn delete_forward(&mut self) {
+        self.delete_towards(Movement::Right, false);
+    }
 
-        self.string_length -= 1;
+    /// Kill the selection, or up to the closest word boundary on the left.
+    pub fn delete_word_backward(&mut self) {
+        self.delete_towards(Movement::WordLeft, true);
     }
 
-    pub fn delete_word(&mut self) {
-        if self.string_length == 0 {
-            return;
+    /// Kill the selection, or up to the closest word boundary on the right.
+    pub fn delete_word_forward(&mut self) {
+        self.delete_towards(Movement::WordRight, true);
+    }
+
+    /// Kill the text from the cursor to the end of the line.
+    pub fn kill_to_end(&mut self) {
+        self.anchor = None;
+        self.delete_towards(Movement::End, true);
+    }
+
+    /// Kill the text from the start of the line to the cursor.
+    pub fn kill_to_start(&mut self) {
+        self.anchor = None;
+        self.delete_towards(Movement::Home, true);
+    }
+
+    /// The killed texts, newest last.
+    pub fn kill_ring(&self) -> &[String] {
+        &self.kill_ring
+    }
+
+    /// Insert the last killed text at the cursor. Returns `false` if nothing
+    /// was killed yet.
+    pub fn yank(&mut self) -> bool {
+        let Some(text) = self.kill_ring.last().cloned() else {
+            return false;
+        };
+
+        self.edit(EditKind::Other);
+        self.delete_selection();
+
+        let start = self.cursor;
+        self.string.insert_str(start, &text);
+        self.cursor += text.len();
+        self.last_yank = Some(Yank {
+            range: start..self.cursor,
+            depth: 0,
+        });
+        true
+    }
+
+    /// Right after yanking, replace the yanked text with the kill before it,
+    /// cycling through the kill ring. Returns `false` if the last edit wasn't
+    /// a yank.
+    pub fn yank_pop(&mut self) -> bool {
+        let Some(yank) = self.last_yank.take() else {
+            return false;
+        };
+
+        let depth = (yank.depth + 1) % self.kill_ring.len();
+        let text = self.kill_ring[self.kill_ring.len() - 1 - depth].clone();
+
+        // Part of the same undo step as the yank.
+        let start = yank.range.start;
+        self.string.replace_range(yank.range, &text);
+        self.cursor = start + text.len();
+        self.last_yank = Some(Yank {
+            range: start..self.cursor,
+            depth,
+        });
+        true
+    }
+
+    /// Undo the last edit, returning `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.undo_stack.pop() else {
+            return false;
+        };
+
+        let current = self.restore(snapshot);
+        self.redo_stack.push(current);
+        true
+    }
+
+    /// Redo the last undone edit, returning `false` if there is none.
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.redo_stack.pop() else {
+            return false;
+        };
+
+        let current = self.restore(snapshot);
+        self.undo_stack.push(current);
+        true
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Add `line` to the history, unless it's empty or the same as the last
+    /// entry.
+    pub fn push_history(&mut self, line: &str) {
+        if !line.is_empty() && self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_owned());
         }
+        self.history_index = None;
+    }
 
-        let boundary = self.string.next_word_boundary_left(self.cursor_position);
-        // print!("Found boundary {}", boundary);
-        // stdout().flush().expect("hi");
-        // panic!("hi");
-        let length = self.cursor_position - boundary;
+    /// Show the history entry before the current one. Returns `false` if
+    /// there is none.
+    pub fn history_previous(&mut self) -> bool {
+        let index = match self.history_index {
+            Some(0) => return false,
+            Some(index) => index - 1,
+            None if self.history.is_empty() => return false,
+            None => {
+                self.draft = self.string.clone();
+                self.history.len() - 1
+            }
+        };
 
-        // dbg!(self.cursor_position >= self.string_length)
-        // panic!();
+        self.history_index = Some(index);
+        self.show_history(self.history[index].clone());
+        true
+    }
 
-        if self.cursor_position >= self.string_length {
-            // print!("{}", ansi_term::format(&[Format::Fg(Color::Green)]));
-            for _ in 0..length {
-                self.string.pop();
+    /// Show the history entry after the current one, or the line that was
+    /// being edited after the newest one. Returns `false` if the history
+    /// isn't being browsed.
+    pub fn history_next(&mut self) -> bool {
+        let Some(index) = self.history_index else {
+            return false;
+        };
+
+        let line = match self.history.get(index + 1) {
+            Some(line) => {
+                self.history_index = Some(index + 1);
+                line.clone()
+            }
+            None => {
+                self.history_index = None;
+                std::mem::take(&mut self.draft)
             }
+        };
+        self.show_history(line);
+        true
+    }
 
-            ansi_term_old::perform_commands(&[
-                Command::Move(Left(length as u32)),
-                Command::EraseToLineEnd,
-            ]);
-            // print!("erased to end");
-            self.cursor_position -= length;
-        } else {
-            // print!("{}", ansi_term::format(&[Format::Fg(Color::Red)]));
-            self.string
-                .replace_range(boundary..self.cursor_position, "");
-            ansi_term_old::perform_commands(&[Command::Move(Left(length as u32))]);
-            self.cursor_position -= length;
+    fn show_history(&mut self, line: String) {
+        self.string = line;
+        self.cursor = self.string.len();
+        self.anchor = None;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.end_edit();
+    }
 
-            let shifted_string = self
-                .string
-                .chars()
-                .skip(self.cursor_position)
-                .collect::<String>();
+    /// Take the line, adding it to the history and leaving the editor empty
+    /// for the next one.
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.string);
+        self.push_history(&line);
 
-            print!("{}", shifted_string);
-            ansi_term_old::perform_commands(&[Command::Move(Left(shifted_string.len() as u32))]);
+        self.cursor = 0;
+        self.anchor = None;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.end_edit();
+        line
+    }
+
+    pub fn close(self) -> String {
+        self.string
+    }
+
+    /// Apply the editing shortcut of a key press, returning whether the key
+    /// is one. Enter isn't handled, since what submitting means is up to the
+    /// caller.
+    ///
+    /// | Keys | |
+    /// |---|---|
+    /// | left, right, home, end, ctrl+a, ctrl+e | move, with shift to select |
+    /// | ctrl+left, ctrl+right | move by words |
+    /// | backspace, delete | delete |
+    /// | alt+backspace, ctrl+w, alt+d | kill a word |
+    /// | ctrl+u, ctrl+k | kill to the start or end |
+    /// | ctrl+y, alt+y | yank, and cycle through the kill ring |
+    /// | ctrl+z, ctrl+shift+z | undo and redo |
+    /// | up, down | browse the history |
+    pub fn handle_key(&mut self, event: &InputEvent) -> bool {
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = event.modifiers.contains(KeyModifiers::ALT);
+        let shift = event.modifiers.contains(KeyModifiers::SHIFT);
+
+        let movement = match event.code {
+            KeyCode::Left if ctrl => Some(Movement::WordLeft),
+            KeyCode::Right if ctrl => Some(Movement::WordRight),
+            KeyCode::Left => Some(Movement::Left),
+            KeyCode::Right => Some(Movement::Right),
+            KeyCode::Home => Some(Movement::Home),
+            KeyCode::End => Some(Movement::End),
+            KeyCode::Char('a' | 'A') if ctrl => Some(Movement::Home),
+            KeyCode::Char('e' | 'E') if ctrl => Some(Movement::End),
+            _ => None,
+        };
+        if let Some(movement) = movement {
+            match shift {
+                true => self.select(movement),
+                false => self.move_cursor(movement),
+            }
+            return true;
         }
-        stdout().flush().expect("Help! Flush didn't work");
 
-        self.string_length -= length;
+        match event.code {
+            KeyCode::Char('z' | 'Z') if ctrl && shift => {
+                self.redo();
+            }
+            KeyCode::Char('z') if ctrl => {
+                self.undo();
+            }
+            KeyCode::Char('w') if ctrl => self.delete_word_backward(),
+            KeyCode::Char('u') if ctrl => self.kill_to_start(),
+            KeyCode::Char('k') if ctrl => self.kill_to_end(),
+            KeyCode::Char('y') if ctrl => {
+                self.yank();
+            }
+            KeyCode::Char('y') if alt => {
+                self.yank_pop();
+            }
+            KeyCode::Char('d') if alt => self.delete_word_forward(),
+            // Other shortcuts are left for the caller.
+            KeyCode::Char(_) if ctrl || alt => return false,
+            KeyCode::Char(char) => self.insert(char.encode_utf8(&mut [0; 4])),
+            KeyCode::Backspace if alt || ctrl => self.delete_word_backward(),
+            KeyCode::Backspace => self.delete_backward(),
+            KeyCode::Delete => self.delete_forward(),
+            KeyCode::Up => return self.history_previous(),
+            KeyCode::Down => return self.history_next(),
+            _ => return false,
+        }
+
+        true
     }
 
-    pub fn close(self) -> String {
-        self.string.clone()
+    /// The position `movement` moves the cursor to.
+    fn target(&self, movement: Movement) -> usize {
+        let string = &self.string;
+        match movement {
+            Movement::Left => string[..self.cursor]
+                .grapheme_indices(true)
+                .next_back()
+                .map_or(0, |(position, _)| position),
+            Movement::Right => string[self.cursor..]
+                .graphemes(true)
+                .next()
+                .map_or(self.cursor, |grapheme| self.cursor + grapheme.len()),
+            Movement::WordLeft => string.next_word_boundary_left(self.cursor),
+            Movement::WordRight => string.next_word_boundary_right(self.cursor),
+            Movement::Home => 0,
+            Movement::End => string.len(),
+        }
     }
 
-    fn move_left_chars(&mut self, count: usize) {
-        if count > self.cursor_position {
+    /// Delete the selection, or the text between the cursor and where
+    /// `movement` would move it, putting it in the kill ring if `kill` is
+    /// set.
+    fn delete_towards(&mut self, movement: Movement, kill: bool) {
+        let range = match self.selection() {
+            Some(selection) => selection,
+            None => {
+                let target = self.target(movement);
+                self.cursor.min(target)..self.cursor.max(target)
+            }
+        };
+        if range.is_empty() {
             return;
         }
-        self.cursor_position -= count;
-        ansi_term_old::perform_commands(&[Command::Move(Left(count as u32))]);
+
+        self.edit(match kill {
+            true => EditKind::Other,
+            false => EditKind::Delete,
+        });
+
+        if kill {
+            self.kill_ring.push(self.string[range.clone()].to_owned());
+            if self.kill_ring.len() > MAX_KILL_RING_LENGTH {
+                self.kill_ring.remove(0);
+            }
+        }
+
+        self.cursor = range.start;
+        self.anchor = None;
+        self.string.replace_range(range, "");
     }
-    fn move_right_chars(&mut self, mut count: usize) {
-        if count + self.cursor_position > self.string_length {
-            count = self.string_length - self.cursor_position;
+
+    fn delete_selection(&mut self) {
+        if let Some(selection) = self.selection() {
+            self.cursor = selection.start;
+            self.string.replace_range(selection, "");
         }
+        self.anchor = None;
+    }
+
+    /// Record the state before an edit of `kind`, unless it continues the
+    /// last edit.
+    fn edit(&mut self, kind: EditKind) {
+        self.last_yank = None;
+        self.redo_stack.clear();
 
-        self.cursor_position += count;
-        ansi_term_old::perform_commands(&[Command::Move(Right(count as u32))]);
+        if kind == EditKind::Other || self.last_edit != Some(kind) {
+            self.undo_stack.push(Snapshot {
+                string: self.string.clone(),
+                cursor: self.cursor,
+            });
+            if self.undo_stack.len() > MAX_UNDO_STEPS {
+                self.undo_stack.remove(0);
+            }
+        }
+
+        self.last_edit = Some(kind);
+    }
+
+    /// Make the next edit a new undo step.
+    fn end_edit(&mut self) {
+        self.last_edit = None;
+        self.last_yank = None;
+    }
+
+    /// Replace the state with `snapshot`, returning the current state.
+    fn restore(&mut self, snapshot: Snapshot) -> Snapshot {
+        let current = Snapshot {
+            string: std::mem::replace(&mut self.string, snapshot.string),
+            cursor: self.cursor,
+        };
+        self.cursor = snapshot.cursor;
+        self.anchor = None;
+        self.end_edit();
+        current
     }
 }
 
-impl Default for StringEditor {
-    fn default() -> Self {
-        Self::new()
+impl From<&str> for StringEditor {
+    /// An editor for `string`, with the cursor at its end.
+    fn from(string: &str) -> Self {
+        let string = sanitize(string);
+        Self {
+            cursor: string.len(),
+            string,
+            ..Default::default()
+        }
     }
 }
 
-impl Drop for StringEditor {
-    fn drop(&mut self) {
-        stdout()
-            .queue(cursor::Hide)
-            .expect("I hate this")
-            .queue(cursor::DisableBlinking)
-            .expect("I hate this")
-            .flush()
-            .expect("I hate this");
+fn sanitize(text: &str) -> String {
+    text.chars().filter(|char| !char.is_control()).collect()
+}
+
+/// Draws a `StringEditor` on the current line of the terminal, for prompts.
+///
+/// The line is drawn from where the cursor was when the printer was created,
+/// and redrawn in place by moving the cursor back there.
+#[derive(Debug, Default)]
+pub struct LinePrinter {
+    /// The terminal column of the cursor after the last print, relative to
+    /// the start of the line.
+    cursor_column: usize,
+}
+
+impl LinePrinter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The escape sequences redrawing `editor`, with the selection in reverse
+    /// video, and leaving the cursor at the editor's cursor.
+    pub fn print(&mut self, editor: &StringEditor) -> String {
+        let mut out = cursor::get_move_left(self.cursor_column as u16);
+
+        match editor.selection() {
+            Some(selection) => {
+                out.push_str(&editor.string[..selection.start]);
+                out.push_str(&format::format_str(
+                    &editor.string[selection.clone()],
+                    &[Format::ReverseColors],
+                ));
+                out.push_str(&editor.string[selection.end..]);
+            }
+            None => out.push_str(&editor.string),
+        }
+        out.push_str(&line::get_clear_to_end());
+
+        let columns_after = str_width(&editor.string[editor.cursor..]);
+        out.push_str(&cursor::get_move_left(columns_after as u16));
+
+        self.cursor_column = str_width(&editor.string[..editor.cursor]);
+        out
+    }
+}
+
+mod tests {
+    #[test]
+    fn grapheme_editing_test() {
+        use super::*;
+
+        // "e" followed by a combining acute accent is a single grapheme.
+        let mut editor = StringEditor::from("cafe\u{301} 👍🏽");
+        editor.move_cursor(Movement::Left);
+        editor.move_cursor(Movement::Left);
+        assert_eq!(editor.cursor_column(), 4);
+        editor.delete_backward();
+        assert_eq!(editor.string(), "caf 👍🏽");
+
+        editor.move_cursor(Movement::End);
+        editor.delete_backward();
+        assert_eq!(editor.string(), "caf ");
+
+        // Inserting in the middle of multi-byte text.
+        editor.move_cursor(Movement::Home);
+        editor.move_cursor(Movement::Right);
+        editor.insert("ö\nä");
+        assert_eq!(editor.string(), "cöäaf ");
+        assert_eq!(editor.cursor(), "cöä".len());
+
+        editor.set_cursor_column(1);
+        editor.delete_forward();
+        assert_eq!(editor.string(), "cäaf ");
+    }
+
+    #[test]
+    fn word_movement_test() {
+        use super::*;
+
+        // The combining accent belongs to the word, rather than ending it.
+        let mut editor = StringEditor::from("cafe\u{301} au lait");
+        editor.move_cursor(Movement::Home);
+        editor.move_cursor(Movement::WordRight);
+        assert_eq!(editor.cursor(), "cafe\u{301}".len());
+
+        editor.move_cursor(Movement::End);
+        let stops: Vec<usize> = (0..5)
+            .map(|_| {
+                editor.move_cursor(Movement::WordLeft);
+                editor.cursor()
+            })
+            .collect();
+        assert_eq!(stops, [10, 9, 7, 6, 0]);
+
+        editor.set_cursor_column(4);
+        editor.delete_towards(Movement::WordLeft, false);
+        assert_eq!(editor.string(), " au lait");
+    }
+
+    #[test]
+    fn selection_test() {
+        use super::*;
+
+        let mut editor = StringEditor::from("hello wörld");
+        editor.select(Movement::WordLeft);
+        assert_eq!(editor.selected_text(), Some("wörld"));
+
+        editor.insert("there");
+        assert_eq!(editor.string(), "hello there");
+        assert_eq!(editor.selection(), None);
+
+        editor.select_all();
+        editor.delete_backward();
+        assert!(editor.is_empty());
+    }
+
+    #[test]
+    fn undo_test() {
+        use super::*;
+
+        let mut editor = StringEditor::new();
+        for char in "hello world".chars() {
+            editor.insert(&char.to_string());
+        }
+        editor.delete_backward();
+        editor.delete_backward();
+
+        assert!(editor.undo());
+        assert_eq!(editor.string(), "hello world");
+        assert!(editor.undo());
+        assert_eq!(editor.string(), "hello ");
+        assert!(editor.redo());
+        assert_eq!(editor.string(), "hello world");
+
+        assert!(editor.undo());
+        assert!(editor.undo());
+        assert!(!editor.undo());
+        assert!(editor.is_empty());
+
+        // Editing after undoing forgets what could be redone.
+        editor.insert("x");
+        assert!(!editor.redo());
+    }
+
+    #[test]
+    fn kill_ring_test() {
+        use super::*;
+
+        let mut editor = StringEditor::from("one two three");
+        editor.delete_word_backward();
+        editor.delete_word_backward();
+        editor.delete_word_backward();
+        assert_eq!(editor.string(), "one ");
+        assert_eq!(editor.kill_ring(), ["three", " ", "two"]);
+
+        assert!(editor.yank());
+        assert_eq!(editor.string(), "one two");
+        assert!(editor.yank_pop());
+        assert_eq!(editor.string(), "one  ");
+        assert!(editor.yank_pop());
+        assert_eq!(editor.string(), "one three");
+
+        editor.move_cursor(Movement::Home);
+        editor.kill_to_end();
+        assert!(editor.is_empty());
+        assert!(!editor.yank_pop());
+        assert!(editor.yank());
+        assert_eq!(editor.string(), "one three");
+    }
+
+    #[test]
+    fn history_test() {
+        use super::*;
+
+        let mut editor = StringEditor::new().with_history(vec!["first".to_owned()]);
+        editor.insert("second");
+        assert_eq!(editor.submit(), "second");
+        assert!(editor.is_empty());
+
+        editor.insert("dra");
+        assert!(editor.history_previous());
+        assert_eq!(editor.string(), "second");
+        assert!(editor.history_previous());
+        assert_eq!(editor.string(), "first");
+        assert!(!editor.history_previous());
+
+        assert!(editor.history_next());
+        assert!(editor.history_next());
+        assert_eq!(editor.string(), "dra");
+        assert!(!editor.history_next());
+
+        // Empty and repeated lines aren't added.
+        editor.push_history("");
+        editor.push_history("second");
+        assert_eq!(editor.history(), ["first", "second"]);
+    }
+
+    #[test]
+    fn handle_key_test() {
+        use super::*;
+
+        let mut editor = StringEditor::new();
+        let key = |code, modifiers| InputEvent::new(code, modifiers);
+
+        for char in "hi you".chars() {
+            assert!(editor.handle_key(&key(KeyCode::Char(char), KeyModifiers::NONE)));
+        }
+        assert!(editor.handle_key(&key(KeyCode::Left, KeyModifiers::SHIFT)));
+        assert_eq!(editor.selected_text(), Some("u"));
+        assert!(editor.handle_key(&key(KeyCode::Char('w'), KeyModifiers::CONTROL)));
+        assert_eq!(editor.string(), "hi yo");
+        assert!(editor.handle_key(&key(KeyCode::Char('z'), KeyModifiers::CONTROL)));
+        assert_eq!(editor.string(), "hi you");
+
+        // Keys without an editing shortcut are left alone.
+        assert!(!editor.handle_key(&key(KeyCode::Enter, KeyModifiers::NONE)));
+        assert!(!editor.handle_key(&key(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert!(!editor.handle_key(&key(KeyCode::Up, KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn line_printer_test() {
+        use super::*;
+
+        let mut printer = LinePrinter::new();
+        let mut editor = StringEditor::from("añb");
+        assert_eq!(printer.print(&editor), "añb\x1b[0K");
+
+        editor.move_cursor(Movement::Left);
+        editor.select(Movement::Left);
+        assert_eq!(
+            printer.print(&editor),
+            "\x1b[3Da\x1b[7mñ\x1b[0mb\x1b[0K\x1b[2D"
+        );
+
+        // Wide characters move the cursor by two columns.
+        let mut printer = LinePrinter::new();
+        let mut editor = StringEditor::from("中文");
+        editor.move_cursor(Movement::Left);
+        assert_eq!(printer.print(&editor), "中文\x1b[0K\x1b[2D");
+        editor.move_cursor(Movement::Left);
+        assert_eq!(printer.print(&editor), "\x1b[2D中文\x1b[0K\x1b[4D");
     }
 }
@@ -248,6 +248,12 @@ pub struct UiScene {
     current_id: usize,
 }
 
+impl Default for UiScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl UiScene {
     pub const fn new() -> Self {
         Self {
@@ -1,11 +1,9 @@
 use crossterm::event::KeyCode;
 
-use crate::ansi_term_old;
-use crate::ansi_term_old::Color;
-use crate::ansi_term_old::Format;
+use crate::input::Input;
 use crate::input::InputEvent;
-use crate::input::INPUT;
 use crate::linear_ui::*;
+use crate::ui::Theme;
 use crate::utils::*;
 // use crate::ACCENT_COLOR;
 
@@ -34,11 +32,7 @@ impl WidgetFrame {
         let line_width = line.visual_len();
         let total_width = self.width();
 
-        let extra_width = if line_width > total_width {
-            0
-        } else {
-            total_width - line_width
-        };
+        let extra_width = total_width.saturating_sub(line_width);
 
         let whitespace = " ".repeat(extra_width);
 
@@ -113,10 +107,7 @@ impl<'a, T> Renderable for MenuDisplay<'a, T> {
 
         for (i, (_, option)) in self.iter().enumerate() {
             let formatted: String = if i == self.selected {
-                format!(
-                    "> {}",
-                    ansi_term_old::format_string(option, &[Format::Fg(Color::Green)])
-                )
+                format!("> {}", Theme::current().selection.paint(option))
             } else {
                 format!("  {}", option)
             };
@@ -185,10 +176,12 @@ impl<'a, T> Menu<'a, T> {
         }
     }
 
-    pub fn display(&mut self, scene: &mut UiScene) -> MenuResult<T> {
+    /// Show the menu until an option is picked with enter, reading the keys
+    /// from `input`.
+    pub fn display(&mut self, scene: &mut UiScene, input: &Input) -> MenuResult<'_, T> {
         let menu_rd = scene.append(self.display.render());
 
-        INPUT.loop_input(|event| {
+        input.loop_input(|event| {
             match event {
                 InputEvent {
                     code: KeyCode::Up, ..
@@ -82,9 +82,9 @@ impl ops::Neg for Vec3 {
     type Output = Self;
     fn neg(self) -> Self::Output {
         Vec3 {
-            x: self.x * -1.,
-            y: self.y * -1.,
-            z: self.z * -1.,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
         }
     }
 }
@@ -166,8 +166,8 @@ impl ops::Neg for Vec2 {
     type Output = Self;
     fn neg(self) -> Self::Output {
         Self {
-            x: self.x * -1.,
-            y: self.y * -1.,
+            x: -self.x,
+            y: -self.y,
         }
     }
 }
@@ -1,5 +1,7 @@
 pub mod ansi_term;
 pub mod ansi_term_old;
+pub mod capabilities;
+pub mod encoding;
 pub mod events;
 pub mod flags;
 pub mod input;
@@ -0,0 +1,134 @@
+use super::*;
+use crate::math::*;
+use crate::utils::StrUtils;
+
+/// The line style of a box drawn around a panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Border {
+    /// `┌─┐`
+    #[default]
+    Plain,
+    /// `╭─╮`
+    Rounded,
+    /// `╔═╗`
+    Double,
+    /// `┏━┓`
+    Thick,
+    /// `+-+`, for terminals and fonts without box drawing characters.
+    Ascii,
+}
+
+/// The characters used to draw a border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BorderSymbols {
+    pub top_left: &'static str,
+    pub top_right: &'static str,
+    pub bottom_left: &'static str,
+    pub bottom_right: &'static str,
+    pub horizontal: &'static str,
+    pub vertical: &'static str,
+}
+
+impl Border {
+    pub fn symbols(self) -> BorderSymbols {
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = match self {
+            Border::Plain => ["┌", "┐", "└", "┘", "─", "│"],
+            Border::Rounded => ["╭", "╮", "╰", "╯", "─", "│"],
+            Border::Double => ["╔", "╗", "╚", "╝", "═", "║"],
+            Border::Thick => ["┏", "┓", "┗", "┛", "━", "┃"],
+            Border::Ascii => ["+", "+", "+", "+", "-", "|"],
+        };
+
+        BorderSymbols {
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+            horizontal,
+            vertical,
+        }
+    }
+}
+
+impl CellBuffer {
+    /// Draw `border` along the edges of `rect`, with an optional `title` in
+    /// the top edge. Titles too long to fit are cut off.
+    ///
+    /// Rects smaller than two by two cells are left empty.
+    pub fn draw_border(
+        &mut self,
+        rect: Rect,
+        border: Border,
+        style: CellStyle,
+        title: Option<&str>,
+    ) {
+        let rect = rect.intersection(&Rect::from_size(self.size()));
+        if rect.size.x < 2 || rect.size.y < 2 {
+            return;
+        }
+
+        let symbols = border.symbols();
+        let (left, top) = (rect.position.x, rect.position.y);
+        let (right, bottom) = (rect.right() - 1, rect.bottom() - 1);
+
+        for x in left + 1..right {
+            self.set(uvec2(x, top), symbols.horizontal, style);
+            self.set(uvec2(x, bottom), symbols.horizontal, style);
+        }
+        for y in top + 1..bottom {
+            self.set(uvec2(left, y), symbols.vertical, style);
+            self.set(uvec2(right, y), symbols.vertical, style);
+        }
+        self.set(uvec2(left, top), symbols.top_left, style);
+        self.set(uvec2(right, top), symbols.top_right, style);
+        self.set(uvec2(left, bottom), symbols.bottom_left, style);
+        self.set(uvec2(right, bottom), symbols.bottom_right, style);
+
+        // Laid out as `┌─ title ─┐`, which needs at least five other cells.
+        if let Some(title) = title {
+            let max_width = (rect.size.x as usize).saturating_sub(5);
+            if max_width == 0 {
+                return;
+            }
+
+            let title = format!(" {} ", title.visual_truncate(max_width).trim_end());
+            let title_rect = Rect::new(uvec2(left + 2, top), uvec2(right - left - 2, 1));
+            self.draw_text_clipped(title_rect, &title, style);
+        }
+    }
+
+    /// Draw a single line of text, styled with `style` and clipped to `clip`.
+    /// Formatting escape sequences in `text` are applied on top of `style`.
+    pub fn draw_text_clipped(&mut self, clip: Rect, text: &str, style: CellStyle) {
+        let mut render_buffer = RenderBuffer::new();
+        render_buffer.push(&style.get_transition(None));
+        render_buffer.push(text);
+
+        self.draw_render_clipped(clip, &Render::new(render_buffer));
+    }
+}
+
+mod tests {
+    #[test]
+    fn border_with_title_test() {
+        use super::*;
+
+        let mut buffer = CellBuffer::new(uvec2(10, 3));
+        buffer.draw_border(
+            Rect::from_size(uvec2(10, 3)),
+            Border::Plain,
+            CellStyle::default(),
+            Some("Long title"),
+        );
+
+        let row = |y: u32| {
+            (0..10)
+                .map(|x| buffer.get(uvec2(x, y)).unwrap().symbol.clone())
+                .collect::<String>()
+        };
+
+        assert_eq!(row(0), "┌─ Long ─┐");
+        assert_eq!(row(1), "│        │");
+        assert_eq!(row(2), "└────────┘");
+    }
+}
@@ -0,0 +1,676 @@
+use super::*;
+use crate::ansi_term::parse::{tokenize, Token};
+use crate::ansi_term::*;
+use crate::math::*;
+// Not the line of `math`.
+use crate::ui::Line;
+use crate::utils::grapheme_width;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Set of text attributes of a cell, like bold or underlined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Attributes(u8);
+
+impl Attributes {
+    pub const NONE: Self = Self(0);
+    pub const BOLD: Self = Self(1 << 0);
+    pub const DIM: Self = Self(1 << 1);
+    pub const ITALIC: Self = Self(1 << 2);
+    pub const UNDERLINE: Self = Self(1 << 3);
+    pub const BLINKING: Self = Self(1 << 4);
+    pub const REVERSE: Self = Self(1 << 5);
+    pub const HIDDEN: Self = Self(1 << 6);
+    pub const STRIKETHROUGH: Self = Self(1 << 7);
+
+    const ALL: [(Self, Format); 8] = [
+        (Self::BOLD, Format::Bold),
+        (Self::DIM, Format::Dim),
+        (Self::ITALIC, Format::Italic),
+        (Self::UNDERLINE, Format::Underline),
+        (Self::BLINKING, Format::BlinkingCursor),
+        (Self::REVERSE, Format::ReverseColors),
+        (Self::HIDDEN, Format::Hidden),
+        (Self::STRIKETHROUGH, Format::Strikethrough),
+    ];
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the formats enabling every attribute in `self`.
+    pub fn formats(self) -> impl Iterator<Item = Format> {
+        Self::ALL
+            .into_iter()
+            .filter(move |(attribute, _)| self.contains(*attribute))
+            .map(|(_, format)| format)
+    }
+}
+
+/// The colors and attributes of a single cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CellStyle {
+    pub fg: Color,
+    pub bg: Color,
+    pub attributes: Attributes,
+}
+
+impl CellStyle {
+    /// Update the style according to the parameters of an SGR escape
+    /// sequence (`CSI ... m`).
+    ///
+    /// Source: https://en.wikipedia.org/wiki/ANSI_escape_code#SGR_(Select_Graphic_Rendition)_parameters
+    pub fn apply_sgr(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            *self = Self::default();
+            return;
+        }
+
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Self::default(),
+                1 => self.attributes.insert(Attributes::BOLD),
+                2 => self.attributes.insert(Attributes::DIM),
+                3 => self.attributes.insert(Attributes::ITALIC),
+                4 => self.attributes.insert(Attributes::UNDERLINE),
+                5 => self.attributes.insert(Attributes::BLINKING),
+                7 => self.attributes.insert(Attributes::REVERSE),
+                8 => self.attributes.insert(Attributes::HIDDEN),
+                9 => self.attributes.insert(Attributes::STRIKETHROUGH),
+                22 => self
+                    .attributes
+                    .remove(Attributes::BOLD.union(Attributes::DIM)),
+                23 => self.attributes.remove(Attributes::ITALIC),
+                24 => self.attributes.remove(Attributes::UNDERLINE),
+                25 => self.attributes.remove(Attributes::BLINKING),
+                27 => self.attributes.remove(Attributes::REVERSE),
+                28 => self.attributes.remove(Attributes::HIDDEN),
+                29 => self.attributes.remove(Attributes::STRIKETHROUGH),
+                30..=37 => self.fg = basic_color(param - 30),
+                38 => self.fg = extended_color(&mut params).unwrap_or(self.fg),
+                39 => self.fg = Color::Default,
+                40..=47 => self.bg = basic_color(param - 40),
+                48 => self.bg = extended_color(&mut params).unwrap_or(self.bg),
+                49 => self.bg = Color::Default,
+                90..=97 => self.fg = Color::ColorId((param - 90 + 8) as u8),
+                100..=107 => self.bg = Color::ColorId((param - 100 + 8) as u8),
+                _ => {}
+            }
+        }
+    }
+
+    /// Get the shortest escape sequence changing the terminal from style
+    /// `from` to `self`. If `from` is `None`, the current style is unknown.
+    pub fn get_transition(self, from: Option<CellStyle>) -> String {
+        if from == Some(self) {
+            return String::new();
+        }
+
+        let mut formats = Vec::new();
+
+        let base = match from {
+            Some(from) if from.attributes.difference(self.attributes).is_empty() => from,
+            _ => {
+                formats.push(Format::Reset);
+                CellStyle::default()
+            }
+        };
+
+        formats.extend(self.attributes.difference(base.attributes).formats());
+
+        if self.fg != base.fg {
+            formats.push(Format::Fg(self.fg));
+        }
+        if self.bg != base.bg {
+            formats.push(Format::Bg(self.bg));
+        }
+
+        format::get_formats(&formats)
+    }
+}
+
+fn basic_color(index: u16) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        _ => Color::White,
+    }
+}
+
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match params.next()? {
+        5 => Some(Color::ColorId(params.next()? as u8)),
+        2 => Some(Color::RGB(
+            params.next()? as u8,
+            params.next()? as u8,
+            params.next()? as u8,
+        )),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    /// The grapheme displayed in the cell. Empty for the second cell of a
+    /// wide grapheme, which is covered by the cell to its left.
+    pub symbol: String,
+    pub style: CellStyle,
+}
+
+impl Cell {
+    /// Whether the cell is covered by a wide grapheme to its left.
+    pub fn is_continuation(&self) -> bool {
+        self.symbol.is_empty()
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            symbol: " ".to_owned(),
+            style: CellStyle::default(),
+        }
+    }
+}
+
+/// A grid of character cells representing the contents of the screen.
+///
+/// Frames are drawn into a `CellBuffer`, which is then compared with the
+/// previous frame so only the cells that changed have to be printed.
+#[derive(Debug, Clone)]
+pub struct CellBuffer {
+    size: UVec2,
+    cells: Vec<Cell>,
+    /// Escape sequences that don't map to cells (like inline images), and the
+    /// position they should be printed at.
+    passthrough: Vec<(UVec2, String)>,
+}
+
+impl CellBuffer {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            cells: vec![Cell::default(); (size.x * size.y) as usize],
+            passthrough: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    fn index(&self, position: UVec2) -> Option<usize> {
+        if position.x >= self.size.x || position.y >= self.size.y {
+            return None;
+        }
+
+        Some((position.y * self.size.x + position.x) as usize)
+    }
+
+    pub fn get(&self, position: UVec2) -> Option<&Cell> {
+        self.cells.get(self.index(position)?)
+    }
+
+    /// Set the cell at `position`, returning `false` if it's outside the
+    /// buffer.
+    ///
+    /// A wide grapheme also covers the cell to its right, or is replaced by a
+    /// space if it's in the last column. Wide graphemes partly overwritten
+    /// are replaced by spaces.
+    pub fn set(&mut self, position: UVec2, symbol: &str, style: CellStyle) -> bool {
+        let Some(index) = self.index(position) else {
+            return false;
+        };
+
+        let is_wide = grapheme_width(symbol) == 2;
+        let fits = position.x + 1 < self.size.x;
+        self.split_wide(position);
+        if is_wide && fits {
+            self.split_wide(uvec2(position.x + 1, position.y));
+        }
+
+        let symbol = if is_wide && !fits { " " } else { symbol };
+        let cell = &mut self.cells[index];
+        cell.symbol.clear();
+        cell.symbol.push_str(symbol);
+        cell.style = style;
+
+        if is_wide && fits {
+            let continuation = &mut self.cells[index + 1];
+            continuation.symbol.clear();
+            continuation.style = style;
+        }
+
+        true
+    }
+
+    /// Replace the wide grapheme covering `position` with spaces, if there
+    /// is one.
+    fn split_wide(&mut self, position: UVec2) {
+        let Some(index) = self.index(position) else {
+            return;
+        };
+
+        let head = match self.cells[index].is_continuation() && position.x > 0 {
+            true => index - 1,
+            false => index,
+        };
+        // Wide graphemes are only set where their second cell fits in the row.
+        if grapheme_width(&self.cells[head].symbol) != 2 {
+            return;
+        }
+        for cell in &mut self.cells[head..head + 2] {
+            cell.symbol.clear();
+            cell.symbol.push(' ');
+        }
+    }
+
+    /// Convert every rgb color to the 256 color palette, see `Color::to_palette`.
+    pub fn convert_to_palette(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.style.fg = cell.style.fg.to_palette();
+            cell.style.bg = cell.style.bg.to_palette();
+        }
+    }
+
+    /// Reset every cell to an empty space.
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            *cell = Cell::default();
+        }
+        self.passthrough.clear();
+    }
+
+    /// Reset every cell inside `rect` to an empty space styled with `style`,
+    /// like the background of a dialog.
+    pub fn fill(&mut self, rect: Rect, style: CellStyle) {
+        let rect = rect.intersection(&Rect::from_size(self.size));
+        for y in rect.position.y..rect.bottom() {
+            for x in rect.position.x..rect.right() {
+                self.set(uvec2(x, y), " ", style);
+            }
+        }
+    }
+
+    /// Draw `render` with its top left corner at `position` (zero based).
+    ///
+    /// Text formatting escape sequences are applied to the cells, while other
+    /// sequences without a visual width (like inline images) are kept and
+    /// printed at the position they appeared. Cursor movement sequences are
+    /// ignored.
+    pub fn draw_render(&mut self, position: UVec2, render: &Render) {
+        let clip = Rect::new(position, self.size).intersection(&Rect::from_size(self.size));
+        self.draw_render_clipped(clip, render);
+    }
+
+    /// Like `draw_render`, drawing at the top left corner of `clip` and
+    /// leaving every cell outside of it untouched.
+    pub fn draw_render_clipped(&mut self, clip: Rect, render: &Render) {
+        if !clip.is_empty() {
+            for sequence in render.sequences() {
+                self.passthrough.push((clip.position, sequence.clone()));
+            }
+        }
+
+        if let Some(text) = render.text() {
+            for (row, line) in text.lines.iter().enumerate() {
+                let y = clip.position.y + row as u32;
+                if y >= clip.bottom() {
+                    break;
+                }
+                let line_rect = Rect::new(uvec2(clip.position.x, y), uvec2(clip.size.x, 1));
+                self.draw_line_clipped(line_rect, line);
+            }
+            return;
+        }
+
+        let mut style = CellStyle::default();
+
+        for (row, line) in render.lines().enumerate() {
+            let y = clip.position.y + row as u32;
+            if y >= clip.bottom() {
+                break;
+            }
+
+            let mut x = clip.position.x;
+
+            for token in tokenize(line) {
+                match token {
+                    Token::Text(text) => {
+                        for grapheme in text.graphemes(true) {
+                            x += self.set_clipped(clip, uvec2(x, y), grapheme, style);
+                        }
+                    }
+                    // Styles still have to be tracked past the edge, as they
+                    // carry over to the next line.
+                    Token::Sgr(params) => style.apply_sgr(&params),
+                    Token::Csi { .. } => {}
+                    Token::Other(sequence) => {
+                        if x < clip.right() {
+                            self.passthrough.push((uvec2(x, y), sequence.to_owned()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draw `line` into the first row of `clip`, cut off at its right edge.
+    pub fn draw_line_clipped(&mut self, clip: Rect, line: &Line) {
+        if clip.size.y == 0 {
+            return;
+        }
+
+        let y = clip.position.y;
+        let mut x = clip.position.x;
+        for span in &line.spans {
+            let style = line.style.patch(span.style).to_cell_style();
+            for grapheme in span.content.graphemes(true) {
+                if x >= clip.right() {
+                    return;
+                }
+                x += self.set_clipped(clip, uvec2(x, y), grapheme, style);
+            }
+        }
+    }
+
+    /// Set the cell at `position` if `grapheme` fits inside `clip`, returning
+    /// the number of columns it takes up. Control characters are skipped, and
+    /// a wide grapheme cut off by the edge leaves a space.
+    fn set_clipped(
+        &mut self,
+        clip: Rect,
+        position: UVec2,
+        grapheme: &str,
+        style: CellStyle,
+    ) -> u32 {
+        let width = grapheme_width(grapheme) as u32;
+        if width == 0 {
+            return 0;
+        }
+
+        if position.x + width <= clip.right() {
+            self.set(position, grapheme, style);
+        } else if position.x < clip.right() {
+            self.set(position, " ", style);
+        }
+        width
+    }
+
+    /// Returns the amount of cells that differ from `previous`.
+    pub fn changed_cells(&self, previous: Option<&CellBuffer>) -> usize {
+        match previous.filter(|previous| previous.size == self.size) {
+            Some(previous) => self
+                .cells
+                .iter()
+                .zip(previous.cells.iter())
+                .filter(|(cell, previous)| cell != previous)
+                .count(),
+            None => self.cells.len(),
+        }
+    }
+
+    /// Get the output transforming a screen showing `previous` into one
+    /// showing `self`.
+    ///
+    /// Only changed cells are printed, using relative cursor movement and
+    /// minimal style changes where possible. If `previous` is `None` or has a
+    /// different size, the whole screen is cleared and repainted.
+    pub fn diff(&self, previous: Option<&CellBuffer>) -> String {
+        let previous = previous.filter(|previous| previous.size == self.size);
+
+        let mut out = String::new();
+        let mut cursor: Option<UVec2> = None;
+        // Every frame ends by resetting the style.
+        let mut style = Some(CellStyle::default());
+
+        if previous.is_none() {
+            out.push_str(&format::get_format(Format::Reset));
+            out.push_str(&screen::get_clear());
+        }
+
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                let position = uvec2(x, y);
+                let index = self.index(position).unwrap();
+                let cell = &self.cells[index];
+
+                if let Some(previous) = previous {
+                    if &previous.cells[index] == cell {
+                        continue;
+                    }
+                } else if cell == &Cell::default() {
+                    // The screen was just cleared.
+                    continue;
+                }
+
+                // Printed along with the wide grapheme covering it.
+                if cell.is_continuation() {
+                    continue;
+                }
+
+                push_cursor_move(&mut out, cursor, position);
+                out.push_str(&cell.style.get_transition(style));
+                out.push_str(&cell.symbol);
+
+                style = Some(cell.style);
+                // The cursor position is uncertain when writing in the last column.
+                let width = grapheme_width(&cell.symbol).max(1) as u32;
+                cursor = (x + width < self.size.x).then_some(uvec2(x + width, y));
+            }
+        }
+
+        for (position, sequence) in self.passthrough.iter() {
+            let unchanged = previous.is_some_and(|previous| {
+                previous
+                    .passthrough
+                    .iter()
+                    .any(|(previous_position, previous_sequence)| {
+                        previous_position == position && previous_sequence == sequence
+                    })
+            });
+            if unchanged {
+                continue;
+            }
+
+            push_cursor_move(&mut out, cursor, *position);
+            out.push_str(sequence);
+            cursor = None;
+        }
+
+        if style.is_some_and(|style| style != CellStyle::default()) {
+            out.push_str(&format::get_format(Format::Reset));
+        }
+
+        out
+    }
+}
+
+/// Move the cursor from `from` to `to`, using the shortest sequence.
+fn push_cursor_move(out: &mut String, from: Option<UVec2>, to: UVec2) {
+    match from {
+        Some(from) if from == to => {}
+        Some(from) if from.y == to.y && from.x < to.x => {
+            out.push_str(&cursor::get_move_right((to.x - from.x) as u16));
+        }
+        _ => out.push_str(&cursor::get_move_to_position(
+            to.x as u16 + 1,
+            to.y as u16 + 1,
+        )),
+    }
+}
+
+/// Statistics about the output written to the terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputStats {
+    pub frames: u64,
+    pub bytes_written: u64,
+    pub last_frame_bytes: usize,
+    /// Number of cells printed during the last frame.
+    pub last_frame_cells: usize,
+    /// Total number of cells on the screen during the last frame.
+    pub screen_cells: usize,
+}
+
+impl OutputStats {
+    pub fn record_frame(&mut self, bytes: usize, cells: usize, screen_cells: usize) {
+        self.frames += 1;
+        self.bytes_written += bytes as u64;
+        self.last_frame_bytes = bytes;
+        self.last_frame_cells = cells;
+        self.screen_cells = screen_cells;
+    }
+
+    /// Average amount of bytes written per frame.
+    pub fn average_frame_bytes(&self) -> f32 {
+        if self.frames == 0 {
+            return 0.;
+        }
+        self.bytes_written as f32 / self.frames as f32
+    }
+}
+
+mod tests {
+    #[test]
+    fn diff_only_changed_cells_test() {
+        use super::*;
+
+        let mut previous = CellBuffer::new(uvec2(10, 3));
+        previous.draw_render(
+            uvec2(0, 0),
+            &Render::new(RenderBuffer::from_string("hello".to_owned())),
+        );
+
+        let mut next = previous.clone();
+        next.draw_render(
+            uvec2(1, 0),
+            &Render::new(RenderBuffer::from_string("a".to_owned())),
+        );
+
+        assert_eq!(next.diff(Some(&previous)), "\x1b[1;2Ha");
+        assert_eq!(next.diff(Some(&next)), "");
+    }
+
+    #[test]
+    fn diff_style_transition_test() {
+        use super::*;
+
+        let previous = CellBuffer::new(uvec2(4, 1));
+        let mut next = previous.clone();
+        next.draw_render(
+            uvec2(0, 0),
+            &Render::new(RenderBuffer::from_string(
+                "\x1b[1;31mab\x1b[22mc".to_owned(),
+            )),
+        );
+
+        assert_eq!(
+            next.diff(Some(&previous)),
+            "\x1b[1;1H\x1b[1;31mab\x1b[0;31mc\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn diff_cursor_skip_test() {
+        use super::*;
+
+        let previous = CellBuffer::new(uvec2(10, 2));
+        let mut next = previous.clone();
+        next.set(uvec2(1, 1), "x", CellStyle::default());
+        next.set(uvec2(5, 1), "y", CellStyle::default());
+
+        assert_eq!(next.diff(Some(&previous)), "\x1b[2;2Hx\x1b[3Cy");
+    }
+
+    #[test]
+    fn draw_text_clipped_test() {
+        use super::*;
+        use crate::ui::{Span, Style, Text};
+
+        let red = Style::new().fg(Color::Red);
+        let text: Text = [
+            Line::raw("ab").with(Span::styled("cdef", red)),
+            Line::raw("second"),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut buffer = CellBuffer::new(uvec2(6, 2));
+        buffer.draw_render_clipped(
+            Rect::new(uvec2(1, 0), uvec2(3, 1)),
+            &Render::from_text(text),
+        );
+
+        let row: String = (0..6)
+            .map(|x| buffer.get(uvec2(x, 0)).unwrap().symbol.clone())
+            .collect();
+        assert_eq!(row, " abc  ");
+        assert_eq!(buffer.get(uvec2(3, 0)).unwrap().style.fg, Color::Red);
+        // Lines past the clip are left out.
+        assert_eq!(buffer.get(uvec2(1, 1)).unwrap().symbol, " ");
+    }
+
+    #[test]
+    fn draw_wide_graphemes_test() {
+        use super::*;
+
+        let row = |buffer: &CellBuffer| -> Vec<String> {
+            (0..buffer.size().x)
+                .map(|x| buffer.get(uvec2(x, 0)).unwrap().symbol.clone())
+                .collect()
+        };
+
+        let previous = CellBuffer::new(uvec2(6, 1));
+        let mut next = previous.clone();
+        next.draw_render(
+            uvec2(0, 0),
+            &Render::new(RenderBuffer::from_string("a中b".to_owned())),
+        );
+        // The second cell of the wide grapheme is a continuation, so later
+        // cells don't shift.
+        assert_eq!(row(&next), ["a", "中", "", "b", " ", " "]);
+        assert_eq!(next.diff(Some(&previous)), "\x1b[1;1Ha中b");
+
+        // Half of a wide grapheme doesn't fit in the clip.
+        let mut clipped = CellBuffer::new(uvec2(6, 1));
+        clipped.draw_render_clipped(
+            Rect::new(uvec2(0, 0), uvec2(2, 1)),
+            &Render::new(RenderBuffer::from_string("a中b".to_owned())),
+        );
+        assert_eq!(row(&clipped), ["a", " ", " ", " ", " ", " "]);
+
+        // Lines of text advance the same way.
+        let mut text = CellBuffer::new(uvec2(6, 1));
+        text.draw_line_clipped(Rect::new(uvec2(0, 0), uvec2(3, 1)), &Line::raw("中文b"));
+        assert_eq!(row(&text), ["中", "", " ", " ", " ", " "]);
+
+        // Overwriting half of a wide grapheme clears the other half.
+        next.set(uvec2(2, 0), "x", CellStyle::default());
+        assert_eq!(row(&next), ["a", " ", "x", "b", " ", " "]);
+    }
+}
@@ -0,0 +1,100 @@
+use super::*;
+use crate::utils::RefVec;
+use std::fmt;
+
+/// A command listed in the command palette, see `Gui::open_command_palette`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandInfo {
+    /// Identifies the command. Keys bound to an action with the same name in
+    /// the keymap are shown next to it.
+    pub name: String,
+    /// What the palette shows, like "Reset camera".
+    pub title: String,
+}
+
+impl CommandInfo {
+    pub fn new(name: impl Into<String>, title: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            title: title.into(),
+        }
+    }
+}
+
+pub trait CommandFn: FnMut(&mut RefVec<PanelEntity>) {}
+
+impl<T> CommandFn for T where T: FnMut(&mut RefVec<PanelEntity>) {}
+
+struct Command {
+    info: CommandInfo,
+    run: Box<dyn CommandFn>,
+}
+
+/// Commands registered by the app, which are run with access to the panels
+/// like tickers.
+///
+/// Panels offer commands of their own with `Panel::commands`.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: Vec<Command>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a command, replacing any command with the same name.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        title: impl Into<String>,
+        run: impl FnMut(&mut RefVec<PanelEntity>) + 'static,
+    ) {
+        let info = CommandInfo::new(name, title);
+        self.unregister(&info.name);
+        self.commands.push(Command {
+            info,
+            run: Box::new(run),
+        });
+    }
+
+    /// Remove the command called `name`, returning `false` if there is none.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        let count = self.commands.len();
+        self.commands.retain(|command| command.info.name != name);
+        self.commands.len() != count
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.commands
+            .iter()
+            .any(|command| command.info.name == name)
+    }
+
+    /// The registered commands, in the order they were registered.
+    pub fn commands(&self) -> impl Iterator<Item = &CommandInfo> {
+        self.commands.iter().map(|command| &command.info)
+    }
+
+    /// Run the command called `name`, returning `false` if there is none.
+    pub fn run(&mut self, name: &str, panels: &mut RefVec<PanelEntity>) -> bool {
+        match self
+            .commands
+            .iter_mut()
+            .find(|command| command.info.name == name)
+        {
+            Some(command) => {
+                (command.run)(panels);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl fmt::Debug for CommandRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.commands()).finish()
+    }
+}
@@ -1,10 +1,18 @@
 use super::*;
 use crate::ansi_term::*;
-use crate::math::UVec2;
+use crate::math::*;
+// Not the line of `math`.
+use crate::ui::Line;
 // use crate::events;
+use crate::capabilities::{TerminalCapabilities, DEFAULT_PROBE_TIMEOUT};
 use crate::flags::TerminalFlags;
+use crate::input::keymap::{self, Keymap, KeymapState};
+use crate::input::{CrosstermInputSource, InputSource, KeyCode, MouseEvent, MouseEventKind};
 use crate::utils::{Ref, RefVec};
 use crate::CleanUp;
+use std::io::{self, Write};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
 const MAX_FPS: f32 = 20.;
 
@@ -14,36 +22,214 @@ pub trait TickerFn: FnMut(&mut RefVec<PanelEntity>, &InputStream) {}
 
 impl<T> TickerFn for T where T: FnMut(&mut RefVec<PanelEntity>, &InputStream) {}
 
-pub struct Gui<'a> {
+/// A short message shown in a corner of the screen for a while.
+#[derive(Debug, Clone)]
+struct Toast {
+    message: String,
+    expires: Instant,
+}
+
+pub struct Gui {
     panels: RefVec<PanelEntity>,
-    inputs: InputManager<'a>,
+    inputs: InputManager,
     external_tickers: Vec<Box<dyn TickerFn>>,
-    _clean_up: CleanUp,
+    capabilities: TerminalCapabilities,
+    /// The last frame printed to the screen, or `None` if the screen should
+    /// be fully repainted.
+    front_buffer: Option<CellBuffer>,
+    output_stats: OutputStats,
+    /// The terminal size the panels were last laid out for, or `None` if they
+    /// have to be laid out again.
+    layout_size: Option<UVec2>,
+    layout: Option<Layout>,
+    focused: Option<PanelRef>,
+    /// The panel a mouse button was pressed on, which receives the drag and
+    /// release events until the button is released.
+    mouse_grab: Option<PanelRef>,
+    recorder: Option<CastRecorder>,
+    keymap: Keymap,
+    keymap_state: KeymapState,
+    /// Whether the overlay listing the key bindings is shown.
+    show_help: bool,
+    /// Panels shown over all others, the top most last. Only the top most
+    /// one receives input.
+    modals: Vec<PanelEntity>,
+    toasts: Vec<Toast>,
+    commands: CommandRegistry,
+    /// Where the open command palette sends the picked command, along with
+    /// the names of the commands it lists.
+    palette_reply: Option<(mpsc::Receiver<Option<usize>>, Vec<String>)>,
+    output: Box<dyn Write>,
+    /// The screen size of a headless gui, which doesn't draw to a terminal
+    /// and isn't limited to `MAX_FPS`.
+    headless_size: Option<UVec2>,
+    /// Whether a headless gui was asked to quit, see `has_quit`.
+    has_quit: bool,
+    _clean_up: Option<CleanUp>,
 }
 
-impl<'a> Gui<'a> {
+impl Gui {
+    /// Take over the terminal, reading input from it.
     pub fn new() -> Self {
+        // Ctrl+C is handled by the `quit` action instead, so it can be
+        // rebound.
+        Self::with_input_source(CrosstermInputSource::new().with_exit_on_ctrl_c(false))
+    }
+
+    /// Take over the terminal, but read input from `source`, like a script
+    /// replaying a session.
+    pub fn with_input_source(source: impl InputSource + 'static) -> Self {
+        // This has to happen before anything reads from the terminal.
+        let capabilities = TerminalCapabilities::probe(DEFAULT_PROBE_TIMEOUT);
+
         TerminalFlags::set_alternative_buffer(true);
+        TerminalFlags::set_mouse_capture(true);
+        TerminalFlags::set_bracketed_paste(true);
         // screen::activate_alternative_buffer();
         screen::clear();
         flush_commands();
 
+        Self {
+            _clean_up: Some(CleanUp),
+            ..Self::headless(uvec2(0, 0), source)
+        }
+        .with_terminal(capabilities)
+    }
+
+    /// A gui that doesn't touch the terminal, with a screen of `size` cells.
+    /// Frames are drawn to `screen` instead, which lets panels be tested with
+    /// scripted input.
+    ///
+    /// ```
+    /// use terminal_renderer::input::*;
+    /// use terminal_renderer::math::*;
+    /// use terminal_renderer::ui::*;
+    ///
+    /// let source = ScriptedInputSource::parse("key tab").unwrap();
+    /// let mut gui = Gui::headless(uvec2(20, 3), source);
+    /// gui.add_panel(uvec2(1, 1), TextPanel::new("Hi".to_owned()));
+    /// gui.tick();
+    ///
+    /// let cell = gui.screen().unwrap().get(uvec2(1, 0)).unwrap();
+    /// assert_eq!(cell.symbol, "i");
+    /// ```
+    pub fn headless(size: UVec2, source: impl InputSource + 'static) -> Self {
         Self {
             panels: RefVec::new(),
-            inputs: InputManager::new(),
+            inputs: InputManager::new(source),
             external_tickers: Vec::new(),
-            _clean_up: CleanUp,
+            capabilities: TerminalCapabilities {
+                truecolor: true,
+                ..TerminalCapabilities::none()
+            },
+            front_buffer: None,
+            output_stats: OutputStats::default(),
+            layout_size: None,
+            layout: None,
+            focused: None,
+            mouse_grab: None,
+            recorder: None,
+            keymap: Keymap::default(),
+            keymap_state: KeymapState::new(),
+            show_help: false,
+            modals: Vec::new(),
+            toasts: Vec::new(),
+            commands: CommandRegistry::new(),
+            palette_reply: None,
+            output: Box::new(io::sink()),
+            headless_size: Some(size),
+            has_quit: false,
+            _clean_up: None,
+        }
+    }
+
+    fn with_terminal(mut self, capabilities: TerminalCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self.output = Box::new(io::stdout());
+        self.headless_size = None;
+        self
+    }
+
+    /// Whether the `quit` action ran. The terminal is restored once the gui
+    /// is dropped.
+    pub fn has_quit(&self) -> bool {
+        self.has_quit
+    }
+
+    /// Tick until the gui quits, or until its input source runs out of
+    /// events, like at the end of a script.
+    pub fn run(&mut self) {
+        while !self.has_quit && !self.inputs.is_finished() {
+            self.tick();
+        }
+    }
+
+    fn is_headless(&self) -> bool {
+        self.headless_size.is_some()
+    }
+
+    /// The size of the screen in cells.
+    fn screen_size(&self) -> io::Result<UVec2> {
+        match self.headless_size {
+            Some(size) => Ok(size),
+            None => crossterm::terminal::size()
+                .map(|(width, height)| uvec2(width as u32, height as u32)),
         }
     }
 
+    /// The last frame drawn, or `None` before the first tick and after
+    /// `redraw`.
+    pub fn screen(&self) -> Option<&CellBuffer> {
+        self.front_buffer.as_ref()
+    }
+
     pub fn add_panel(&mut self, pos: UVec2, panel: impl Panel + 'static) -> PanelRef {
-        self.panels.push(PanelEntity::new(pos, panel))
+        self.add_entity(PanelEntity::new(pos, panel))
+    }
+
+    /// Add a panel with its size, z-index, title or border already set.
+    pub fn add_entity(&mut self, entity: PanelEntity) -> PanelRef {
+        self.layout_size = None;
+        self.panels.push(entity)
     }
 
     pub fn remove_panel(&mut self, panel: &PanelRef) -> Option<PanelEntity> {
+        self.layout_size = None;
         self.panels.remove(panel)
     }
 
+    /// Place panels using `layout` instead of their positions. The layout is
+    /// recomputed whenever the terminal is resized.
+    ///
+    /// Panels that aren't part of the layout keep their position and size.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.clear_layout();
+        self.layout = Some(layout);
+    }
+
+    /// Stop placing panels using the layout. The panels it placed keep their
+    /// position, but extend to the edges of the screen again.
+    pub fn clear_layout(&mut self) {
+        if let Some(layout) = self.layout.take() {
+            for panel in layout.panels() {
+                if let Some(panel) = self.panels.get_mut(&panel) {
+                    panel.size = None;
+                }
+            }
+        }
+        self.layout_size = None;
+    }
+
+    pub fn get_entity(&self, panel: &PanelRef) -> Option<&PanelEntity> {
+        self.panels.get(panel)
+    }
+    /// Changes to the size or border of the entity are picked up on the next
+    /// tick.
+    pub fn get_entity_mut(&mut self, panel: &PanelRef) -> Option<&mut PanelEntity> {
+        self.layout_size = None;
+        self.panels.get_mut(panel)
+    }
+
     pub fn get_panel<T: Panel + 'static>(&self, panel: &PanelRef) -> Option<&T> {
         self.panels.get(panel)?.as_panel()
     }
@@ -54,42 +240,85 @@ impl<'a> Gui<'a> {
     pub fn tick(&mut self) {
         let before = std::time::SystemTime::now();
 
-        let key_events = self.inputs.tick();
+        let mut key_events = self.inputs.tick();
+        key_events.resolve_actions(&self.keymap, &mut self.keymap_state);
 
-        for ticker in &mut self.external_tickers {
-            ticker(&mut self.panels, &key_events);
+        if let (Some(size), Some(_)) = (key_events.resized(), self.headless_size) {
+            self.headless_size = Some(size);
         }
+        let terminal_size = match key_events.resized() {
+            Some(size) => size,
+            None => self.screen_size().expect("couldn't get terminal size"),
+        };
+        let (width, height) = (terminal_size.x, terminal_size.y);
+        if self.layout_size != Some(terminal_size) {
+            self.layout(terminal_size);
+        }
+        self.place_modals(terminal_size);
+        self.toasts.retain(|toast| toast.expires > Instant::now());
 
-        let mut buffer = RenderBuffer::new();
-        // buffer.push(&screen::get_clear());
-        buffer.push(&screen::get_fill_with_char(' '));
-        buffer.push(&cursor::get_hide());
+        self.dispatch_input(&key_events);
 
-        buffer.push(&cursor::get_move_home());
-        // buffer.push(&format!("panels: {:?}", &self.panels));
+        let mut back_buffer = CellBuffer::new(terminal_size);
+
+        for reference in self.panels_by_z_index() {
+            let focused = self.focused == Some(reference);
+            let panel = self.panels.get_mut(&reference).unwrap();
 
-        for panel in self.panels.iter_mut() {
-            panel.tick(&key_events);
             let render = panel.render();
+            panel.draw(&mut back_buffer, &render, focused);
+        }
 
-            let pos = panel.pos;
+        let top_modal = self.modals.len().checked_sub(1);
+        let modal_style = Theme::current().text.to_cell_style();
+        for (index, modal) in self.modals.iter_mut().enumerate() {
+            back_buffer.fill(modal.rect(terminal_size), modal_style);
+            let render = modal.render();
+            modal.draw(&mut back_buffer, &render, Some(index) == top_modal);
+        }
+        self.draw_toasts(&mut back_buffer);
 
-            buffer.push(&cursor::get_move_to_position(pos.x as u16, pos.y as u16));
+        if self.show_help {
+            self.draw_help(&mut back_buffer);
+        }
 
-            render.print(&mut buffer, pos.x as u16);
+        if !self.capabilities.truecolor {
+            back_buffer.convert_to_palette();
         }
 
-        buffer.push(&cursor::get_move_home());
+        let mut buffer = RenderBuffer::new();
+        if self.capabilities.synchronized_output {
+            buffer.push(&screen::get_begin_synchronized_update());
+        }
+        buffer.push(&cursor::get_hide());
+        buffer.push(&back_buffer.diff(self.front_buffer.as_ref()));
+        if self.capabilities.synchronized_output {
+            buffer.push(&screen::get_end_synchronized_update());
+        }
 
-        flush_commands();
-        // Source: https://gitlab.com/gnachman/iterm2/-/wikis/synchronized-updates-spec
-        // They didn't work :(
-        // print!("\x1b[?2026h");
-        // print!("\x1bP=1s\x1b\\");
-        print!("{}", buffer.content);
-        // print!("\x1b[?2026l");
-        // print!("\x1bP=2s\x1b\\");
-        flush_commands();
+        let changed_cells = back_buffer.changed_cells(self.front_buffer.as_ref());
+        self.output_stats.record_frame(
+            buffer.content.len(),
+            changed_cells,
+            (width as usize) * (height as usize),
+        );
+        self.front_buffer = Some(back_buffer);
+
+        if let Some(recorder) = &mut self.recorder {
+            // A recording that can't be written anymore is dropped, rather than
+            // interrupting the gui.
+            if recorder.record_output(&buffer.content).is_err() {
+                self.recorder = None;
+            }
+        }
+
+        // Like with the recording, a gui can't do much about failing output.
+        let _ = self.output.write_all(buffer.content.as_bytes());
+        let _ = self.output.flush();
+
+        if self.is_headless() {
+            return;
+        }
 
         const MIN_DELTA_SECS: f32 = 1. / MAX_FPS;
 
@@ -101,6 +330,715 @@ impl<'a> Gui<'a> {
         std::thread::sleep(std::time::Duration::from_secs_f32(sleep_secs));
     }
 
+    /// Place the panels of the layout, and notify every panel of the space it
+    /// has available.
+    fn layout(&mut self, terminal_size: UVec2) {
+        if let Some(layout) = &self.layout {
+            for (panel, rect) in layout.resolve(Rect::from_size(terminal_size)) {
+                if let Some(panel) = self.panels.get_mut(&panel) {
+                    // Panel positions are one based, like terminal coordinates.
+                    panel.pos = uvec2(rect.position.x + 1, rect.position.y + 1);
+                    panel.size = Some(rect.size);
+                }
+            }
+        }
+
+        for panel in self.panels.iter_mut() {
+            let size = panel.content_rect(terminal_size).size;
+            panel.on_resize(size);
+        }
+
+        self.layout_size = Some(terminal_size);
+        self.redraw();
+    }
+
+    /// Tick every panel, giving key events to the focused panel first. Keys it
+    /// doesn't consume are used for moving focus, opening the command palette
+    /// and running registered commands bound in the keymap, and are then
+    /// passed on to the tickers. An unconsumed `quit` action quits, see
+    /// `quit`.
+    ///
+    /// While the help overlay is shown, it swallows all input. While a modal
+    /// is shown, only the top most one receives input, and the other panels
+    /// and tickers get none.
+    ///
+    /// Mouse events go to the panel under the mouse, relative to its
+    /// contents, and pressing a button focuses it. Unconsumed mouse events
+    /// are passed on to the tickers as well.
+    fn dispatch_input(&mut self, key_events: &InputStream) {
+        let screen_size = self.layout_size.unwrap_or(uvec2(0, 0));
+        // Panels under an overlay can't be clicked.
+        let is_covered = self.show_help || !self.modals.is_empty();
+
+        if self.show_help {
+            if key_events.consume_action(keymap::HELP) || key_events.consume_key_code(KeyCode::Esc)
+            {
+                self.show_help = false;
+            }
+            if key_events.consume_action(keymap::QUIT) {
+                self.quit();
+            }
+            key_events.consume_all();
+        } else if let Some(modal) = self.modals.last_mut() {
+            let content = modal.content_rect(screen_size);
+            let stream = key_events.derive(true, |event| {
+                let position = uvec2(event.position.x as u32, event.position.y as u32);
+                (event.position.x >= 0 && event.position.y >= 0 && content.contains(position))
+                    .then(|| event.relative_to(content.position.into()))
+            });
+            modal.tick(&stream);
+
+            if key_events.consume_action(keymap::QUIT) {
+                self.quit();
+            }
+            key_events.consume_all();
+        }
+
+        if !self
+            .focused
+            .is_some_and(|focused| self.is_focusable(&focused))
+        {
+            self.set_focused(None);
+            self.focus_next();
+        }
+
+        // Nothing is consumed yet, so this matches the order `derive` visits
+        // the mouse events in.
+        let mouse_events: Vec<MouseEvent> = key_events.mouse_events().copied().collect();
+        let targets: Vec<Option<PanelRef>> = mouse_events
+            .iter()
+            .map(|event| match is_covered {
+                true => None,
+                false => self.mouse_target(event),
+            })
+            .collect();
+
+        let panel_stream = |gui: &Self, panel: PanelRef, with_keys: bool| {
+            let origin = gui
+                .panels
+                .get(&panel)
+                .map(|entity| entity.content_rect(screen_size).position)
+                .unwrap_or(uvec2(0, 0));
+            let mut targets = targets.iter();
+
+            key_events.derive(with_keys, move |event| {
+                (*targets.next()? == Some(panel)).then(|| event.relative_to(origin.into()))
+            })
+        };
+
+        let focused = self.focused;
+        if let Some(focused) = focused {
+            let stream = panel_stream(self, focused, true);
+            if let Some(panel) = self.panels.get_mut(&focused) {
+                panel.tick(&stream);
+            }
+        }
+
+        if key_events.consume_action(keymap::FOCUS_NEXT) {
+            self.focus_next();
+        } else if key_events.consume_action(keymap::FOCUS_PREVIOUS) {
+            self.focus_previous();
+        }
+        if key_events.consume_action(keymap::HELP) {
+            self.show_help = true;
+        }
+        if key_events.consume_action(keymap::COMMAND_PALETTE) {
+            self.open_command_palette();
+        }
+
+        let triggered: Vec<String> = key_events
+            .actions()
+            .filter(|action| self.commands.contains(action))
+            .map(str::to_owned)
+            .collect();
+        for name in triggered {
+            if key_events.consume_action(&name) {
+                self.commands.run(&name, &mut self.panels);
+            }
+        }
+
+        for ticker in &mut self.external_tickers {
+            ticker(&mut self.panels, key_events);
+        }
+
+        if key_events.consume_action(keymap::QUIT) {
+            self.quit();
+        }
+
+        // Other panels still have to tick, for animations and the like.
+        let references: Vec<PanelRef> = self.panels.iter_refs().map(|(r, _)| r).collect();
+        for reference in references {
+            if Some(reference) == focused {
+                continue;
+            }
+
+            let stream = panel_stream(self, reference, false);
+            if let Some(panel) = self.panels.get_mut(&reference) {
+                panel.tick(&stream);
+            }
+        }
+
+        self.close_finished_modals();
+        self.run_palette_command();
+    }
+
+    /// Register a command for the command palette, replacing any command with
+    /// the same name. Binding keys to an action with the same name in the
+    /// keymap runs it as well, unless the focused panel uses them.
+    ///
+    /// ```
+    /// use terminal_renderer::input::*;
+    /// use terminal_renderer::math::*;
+    /// use terminal_renderer::ui::*;
+    ///
+    /// let source = ScriptedInputSource::parse("key ctrl+p\ntick\ntext clr\nkey enter").unwrap();
+    /// let mut gui = Gui::headless(uvec2(60, 20), source);
+    /// let log = gui.add_panel(uvec2(1, 1), TextPanel::new("Lots of text".to_owned()));
+    /// gui.register_command("log.clear", "Clear the log", move |panels| {
+    ///     let panel = panels.get_mut(&log).unwrap().as_panel_mut::<TextPanel>().unwrap();
+    ///     panel.set_contents(String::new());
+    /// });
+    ///
+    /// gui.tick();
+    /// assert!(gui.has_modal());
+    /// gui.tick();
+    /// assert_eq!(gui.screen().unwrap().get(uvec2(0, 0)).unwrap().symbol, " ");
+    /// ```
+    pub fn register_command(
+        &mut self,
+        name: impl Into<String>,
+        title: impl Into<String>,
+        run: impl FnMut(&mut RefVec<PanelEntity>) + 'static,
+    ) {
+        self.commands.register(name, title, run);
+    }
+
+    /// The commands registered with `register_command`.
+    pub fn commands_mut(&mut self) -> &mut CommandRegistry {
+        &mut self.commands
+    }
+
+    /// Every command that can be run: showing the help and quitting, the
+    /// registered commands, and the commands offered by panels, starting with
+    /// the focused one.
+    pub fn command_list(&self) -> Vec<CommandInfo> {
+        let mut commands = vec![
+            CommandInfo::new(keymap::HELP, "Show key bindings"),
+            CommandInfo::new(keymap::QUIT, "Quit"),
+        ];
+        commands.extend(self.commands.commands().cloned());
+
+        let focused = self.focused.and_then(|focused| self.panels.get(&focused));
+        let others = self
+            .panels
+            .iter_refs()
+            .filter(|(reference, _)| Some(*reference) != self.focused)
+            .map(|(_, panel)| panel);
+        for panel in focused.into_iter().chain(others) {
+            commands.extend(panel.commands());
+        }
+
+        // The first command with a name wins, like when running it.
+        let mut names = std::collections::HashSet::new();
+        commands.retain(|command| names.insert(command.name.clone()));
+        commands
+    }
+
+    /// Run the command called `name` from `command_list`, returning `false`
+    /// if there is none. Commands offered by several panels are run on the
+    /// focused one, or on the first one offering it.
+    pub fn run_command(&mut self, name: &str) -> bool {
+        match name {
+            keymap::HELP => {
+                self.show_help = true;
+                return true;
+            }
+            keymap::QUIT => {
+                self.quit();
+                return true;
+            }
+            _ => {}
+        }
+
+        if self.commands.run(name, &mut self.panels) {
+            return true;
+        }
+
+        let offers =
+            |panel: &PanelEntity| panel.commands().iter().any(|command| command.name == name);
+        let panel = self
+            .focused
+            .filter(|focused| self.panels.get(focused).is_some_and(offers))
+            .or_else(|| {
+                self.panels
+                    .iter_refs()
+                    .find(|(_, panel)| offers(panel))
+                    .map(|(reference, _)| reference)
+            });
+
+        match panel.and_then(|panel| self.panels.get_mut(&panel)) {
+            Some(panel) => {
+                panel.run_command(name);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Open a palette listing `command_list`, with the keys bound to them,
+    /// which runs the picked command. It's opened with the `command_palette`
+    /// action as well.
+    pub fn open_command_palette(&mut self) {
+        let commands = self.command_list();
+        let titles = commands
+            .iter()
+            .map(|command| command.title.clone())
+            .collect();
+        let hints = commands
+            .iter()
+            .map(|command| {
+                let keys = self.keymap.bindings_for(&command.name);
+                keys.map(|binding| binding.keys_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .collect();
+        let names = commands.into_iter().map(|command| command.name).collect();
+
+        let (reply, receiver) = Reply::channel();
+        let palette = Palette::new(titles, reply)
+            .with_hints(hints)
+            .with_placeholder("Type a command");
+        self.open_modal(palette, "Commands");
+        self.palette_reply = Some((receiver, names));
+    }
+
+    /// Run the command picked in the command palette, once it's closed.
+    fn run_palette_command(&mut self) {
+        let Some((receiver, names)) = &self.palette_reply else {
+            return;
+        };
+
+        match receiver.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => {}
+            Ok(Some(index)) => {
+                let name = names[index].clone();
+                self.palette_reply = None;
+                self.run_command(&name);
+            }
+            // Closed without picking anything, or removed with
+            // `close_modals`.
+            _ => self.palette_reply = None,
+        }
+    }
+
+    /// Show `panel` in a box in the middle of the screen, on top of all other
+    /// panels, sized to its `Panel::preferred_size`. An empty `title` leaves
+    /// the box without one.
+    ///
+    /// The modal receives all input until it closes itself, which it lets the
+    /// gui know with `Panel::is_closed`. The panels below keep ticking
+    /// without input, so animations go on. Modals opened while another one
+    /// is shown are stacked on top of it.
+    ///
+    /// ```
+    /// use terminal_renderer::input::*;
+    /// use terminal_renderer::math::*;
+    /// use terminal_renderer::ui::*;
+    ///
+    /// let source = ScriptedInputSource::parse("key y").unwrap();
+    /// let mut gui = Gui::headless(uvec2(40, 10), source);
+    /// let (reply, answer) = Reply::channel();
+    /// gui.open_modal(ConfirmDialog::new("Delete the mesh?", reply), "Confirm");
+    ///
+    /// // Nothing blocks, the answer arrives in a later tick.
+    /// assert!(answer.try_recv().is_err());
+    /// gui.tick();
+    /// assert_eq!(answer.try_recv(), Ok(true));
+    /// assert!(!gui.has_modal());
+    /// ```
+    pub fn open_modal(&mut self, panel: impl Panel + 'static, title: &str) {
+        let mut entity = PanelEntity::new(uvec2(1, 1), panel).with_border(Border::Rounded);
+        if !title.is_empty() {
+            entity = entity.with_title(title);
+        }
+
+        match self.modals.last_mut() {
+            Some(top) => top.on_focus_change(false),
+            None => {
+                if let Some(panel) = self.focused.and_then(|panel| self.panels.get_mut(&panel)) {
+                    panel.on_focus_change(false);
+                }
+            }
+        }
+        entity.on_focus_change(true);
+        self.modals.push(entity);
+
+        if let Some(size) = self.layout_size {
+            self.place_modals(size);
+        }
+    }
+
+    pub fn has_modal(&self) -> bool {
+        !self.modals.is_empty()
+    }
+
+    /// The top most modal, if it's a `T`.
+    pub fn modal_mut<T: Panel + 'static>(&mut self) -> Option<&mut T> {
+        self.modals.last_mut()?.as_panel_mut()
+    }
+
+    /// Remove every modal without waiting for them to close, so they don't
+    /// send a result.
+    pub fn close_modals(&mut self) {
+        self.modals.clear();
+        self.refocus_below_modals();
+    }
+
+    /// Show `message` in the bottom right corner of the screen for
+    /// `duration`. Toasts don't take focus, and newer ones are stacked above
+    /// older ones.
+    pub fn show_toast(&mut self, message: impl Into<String>, duration: Duration) {
+        self.toasts.push(Toast {
+            message: message.into(),
+            expires: Instant::now() + duration,
+        });
+    }
+
+    /// The messages of the toasts currently shown, oldest first.
+    pub fn toasts(&self) -> impl Iterator<Item = &str> {
+        self.toasts.iter().map(|toast| toast.message.as_str())
+    }
+
+    fn close_finished_modals(&mut self) {
+        let count = self.modals.len();
+        self.modals.retain(|modal| !modal.is_closed());
+
+        if self.modals.len() != count {
+            self.refocus_below_modals();
+        }
+    }
+
+    /// Give focus back to the top most modal, or the focused panel without
+    /// one.
+    fn refocus_below_modals(&mut self) {
+        match self.modals.last_mut() {
+            Some(top) => top.on_focus_change(true),
+            None => {
+                if let Some(panel) = self.focused.and_then(|panel| self.panels.get_mut(&panel)) {
+                    panel.on_focus_change(true);
+                }
+            }
+        }
+    }
+
+    /// Center the modals on the screen, letting them know when their size
+    /// changes.
+    fn place_modals(&mut self, screen_size: UVec2) {
+        for modal in &mut self.modals {
+            let preferred = modal.preferred_size().unwrap_or(screen_size / 2);
+            // Room for the border.
+            let size = uvec2(
+                (preferred.x + 2).min(screen_size.x),
+                (preferred.y + 2).min(screen_size.y),
+            );
+            let position = (screen_size - size) / 2 + uvec2(1, 1);
+
+            if modal.size != Some(size) || modal.pos != position {
+                modal.pos = position;
+                modal.size = Some(size);
+                let content_size = modal.content_rect(screen_size).size;
+                modal.on_resize(content_size);
+            }
+        }
+    }
+
+    /// Draw the toasts in boxes in the bottom right corner of the screen.
+    fn draw_toasts(&self, buffer: &mut CellBuffer) {
+        let screen_size = buffer.size();
+        let mut bottom = screen_size.y;
+
+        let theme = Theme::current();
+        let border_style = theme.accent.to_cell_style();
+        let text_style = theme.text.to_cell_style();
+
+        for toast in self.toasts.iter().rev() {
+            let width = (toast.message.visual_len() as u32 + 4).min(screen_size.x);
+            if bottom < 3 {
+                break;
+            }
+            let rect = Rect::new(uvec2(screen_size.x - width, bottom - 3), uvec2(width, 3));
+            bottom -= 3;
+
+            buffer.fill(rect, text_style);
+            buffer.draw_border(rect, Border::Rounded, border_style, None);
+            let content = rect.inset(Padding::symmetric(1, 2));
+            buffer.draw_text_clipped(content, &toast.message, text_style);
+        }
+    }
+
+    /// Stop `run`, finishing the recording if there is one.
+    fn quit(&mut self) {
+        let _ = self.stop_recording();
+        self.has_quit = true;
+    }
+
+    /// Draw the bindings of the keymap in a box in the middle of the screen.
+    fn draw_help(&self, buffer: &mut CellBuffer) {
+        let actions = self.keymap.actions();
+        let action_width = actions
+            .iter()
+            .map(|(action, _)| action.chars().count())
+            .max()
+            .unwrap_or(0);
+        let theme = Theme::current();
+        let text: Text = actions
+            .iter()
+            .map(|(action, keys)| {
+                Line::raw(format!("{:<width$}  ", action, width = action_width))
+                    .with(Span::styled(keys.join(", "), theme.accent))
+                    .patch_style(theme.text)
+            })
+            .collect();
+
+        let screen_size = buffer.size();
+        let size = uvec2(
+            (text.width() as u32 + 4).min(screen_size.x),
+            (text.height() as u32 + 2).min(screen_size.y),
+        );
+        let rect = Rect::new((screen_size - size) / 2, size);
+
+        let border_style = theme.accent.bold().to_cell_style();
+        let text_style = theme.text.to_cell_style();
+
+        buffer.fill(rect, text_style);
+        buffer.draw_border(rect, Border::Rounded, border_style, Some("Keys"));
+
+        let content = rect.inset(Padding::symmetric(1, 2));
+        buffer.draw_render_clipped(content, &Render::from_text(text));
+    }
+
+    /// Make the terminal report key repeats and releases if it supports the
+    /// kitty keyboard protocol, which lets panels track held keys with
+    /// `InputStream::is_held`. Returns whether it's supported.
+    ///
+    /// Without it, held keys are reported as repeated presses.
+    pub fn enable_keyboard_enhancement(&mut self) -> bool {
+        if self.capabilities.kitty_keyboard {
+            TerminalFlags::set_keyboard_enhancement(true);
+        }
+        self.capabilities.kitty_keyboard
+    }
+
+    /// The keymap used for resolving actions.
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Replace the keymap, like with one loaded from a config file with
+    /// `Keymap::load`.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+        self.keymap_state.reset();
+    }
+
+    /// The theme widgets and panels are drawn with, see `Theme::current`.
+    pub fn theme(&self) -> Theme {
+        Theme::current()
+    }
+
+    /// Draw everything with `theme` from the next frame on, like a built-in
+    /// theme or one loaded from a config file with `Theme::load`.
+    pub fn set_theme(&mut self, theme: Theme) {
+        Theme::set_current(theme);
+    }
+
+    pub fn keymap_mut(&mut self) -> &mut Keymap {
+        self.keymap_state.reset();
+        &mut self.keymap
+    }
+
+    pub fn is_help_shown(&self) -> bool {
+        self.show_help
+    }
+
+    pub fn set_help_shown(&mut self, shown: bool) {
+        self.show_help = shown;
+    }
+
+    /// Find the panel `event` should be delivered to, updating focus and the
+    /// mouse grab.
+    fn mouse_target(&mut self, event: &MouseEvent) -> Option<PanelRef> {
+        let position = uvec2(
+            event.position.x.max(0) as u32,
+            event.position.y.max(0) as u32,
+        );
+
+        match event.kind {
+            MouseEventKind::Down(_) => {
+                let target = self.hit_test(position);
+                if let Some(target) = target {
+                    self.focus(&target);
+                }
+                self.mouse_grab = target;
+                target
+            }
+            MouseEventKind::Drag(_) => self.mouse_grab.or_else(|| self.hit_test(position)),
+            MouseEventKind::Up(_) => self.mouse_grab.take().or_else(|| self.hit_test(position)),
+            _ => self.hit_test(position),
+        }
+    }
+
+    /// The panel receiving key events, if any.
+    pub fn focused(&self) -> Option<PanelRef> {
+        self.focused
+    }
+
+    /// Focus `panel`, returning `false` if it doesn't exist or can't be
+    /// focused.
+    pub fn focus(&mut self, panel: &PanelRef) -> bool {
+        if !self.is_focusable(panel) {
+            return false;
+        }
+
+        self.set_focused(Some(*panel));
+        true
+    }
+
+    /// Move focus to the next focusable panel, in the order they were added.
+    pub fn focus_next(&mut self) {
+        self.cycle_focus(false);
+    }
+
+    /// Move focus to the previous focusable panel.
+    pub fn focus_previous(&mut self) {
+        self.cycle_focus(true);
+    }
+
+    fn cycle_focus(&mut self, backwards: bool) {
+        let mut focusable: Vec<PanelRef> = self
+            .panels
+            .iter_refs()
+            .filter(|(_, panel)| panel.focusable())
+            .map(|(reference, _)| reference)
+            .collect();
+        if backwards {
+            focusable.reverse();
+        }
+
+        let next = match self
+            .focused
+            .and_then(|focused| focusable.iter().position(|panel| *panel == focused))
+        {
+            Some(index) => focusable.get(index + 1).or(focusable.first()),
+            None => focusable.first(),
+        };
+
+        self.set_focused(next.copied());
+    }
+
+    /// Move focus to `focused`, letting both panels know.
+    fn set_focused(&mut self, focused: Option<PanelRef>) {
+        if self.focused == focused {
+            return;
+        }
+
+        if let Some(panel) = self.focused.and_then(|panel| self.panels.get_mut(&panel)) {
+            panel.on_focus_change(false);
+        }
+        if let Some(panel) = focused.and_then(|panel| self.panels.get_mut(&panel)) {
+            panel.on_focus_change(true);
+        }
+        self.focused = focused;
+    }
+
+    /// Focus the top most panel covering the zero based screen `position`,
+    /// like when it's clicked. Returns `false` if there is none or it can't
+    /// be focused.
+    pub fn focus_at(&mut self, position: UVec2) -> bool {
+        match self.hit_test(position) {
+            Some(panel) => self.focus(&panel),
+            None => false,
+        }
+    }
+
+    /// The top most panel covering the zero based screen `position`,
+    /// including its border.
+    pub fn hit_test(&self, position: UVec2) -> Option<PanelRef> {
+        let screen_size = self.layout_size?;
+
+        self.panels_by_z_index()
+            .into_iter()
+            .rev()
+            .find(|reference| {
+                let panel = self.panels.get(reference).unwrap();
+                panel.rect(screen_size).contains(position)
+            })
+    }
+
+    fn is_focusable(&self, panel: &PanelRef) -> bool {
+        self.panels
+            .get(panel)
+            .is_some_and(|panel| panel.focusable())
+    }
+
+    /// References to all panels, from the bottom most to the top most.
+    fn panels_by_z_index(&self) -> Vec<PanelRef> {
+        let mut panels: Vec<(PanelRef, i32)> = self
+            .panels
+            .iter_refs()
+            .map(|(reference, panel)| (reference, panel.z_index))
+            .collect();
+        // Stable, so panels with the same z-index keep their order.
+        panels.sort_by_key(|(_, z_index)| *z_index);
+
+        panels.into_iter().map(|(reference, _)| reference).collect()
+    }
+
+    /// The features supported by the terminal, detected when the gui was
+    /// created.
+    pub fn capabilities(&self) -> &TerminalCapabilities {
+        &self.capabilities
+    }
+
+    /// Statistics about the amount of output written to the terminal.
+    pub fn output_stats(&self) -> OutputStats {
+        self.output_stats
+    }
+
+    /// Make the next tick repaint the entire screen, instead of only the
+    /// cells that changed.
+    pub fn redraw(&mut self) {
+        self.front_buffer = None;
+    }
+
+    /// Start recording every frame written to the terminal into an asciinema
+    /// `.cast` file at `path`, replacing any previous recording.
+    ///
+    /// Recording stops when writing to the file fails.
+    pub fn start_recording(&mut self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        self.stop_recording()?;
+
+        let size = self.screen_size()?;
+        self.recorder = Some(CastRecorder::create(path, size.x as u16, size.y as u16)?);
+        // The recording has to start with a complete frame.
+        self.redraw();
+
+        Ok(())
+    }
+
+    /// Stop the current recording, if any, flushing it to its file.
+    pub fn stop_recording(&mut self) -> std::io::Result<()> {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
     pub fn add_ticker(
         &mut self,
         tick: impl FnMut(&mut RefVec<PanelEntity>, &InputStream) + 'static,
@@ -109,10 +1047,210 @@ impl<'a> Gui<'a> {
     }
 }
 
-impl<'a> Default for Gui<'a> {
+impl Default for Gui {
     fn default() -> Self {
         Self::new()
     }
 }
 
 // impl events::EventManager<>
+
+mod tests {
+    #[allow(dead_code)]
+    fn row(gui: &super::Gui, y: u32) -> String {
+        use crate::math::*;
+
+        let screen = gui.screen().expect("nothing drawn");
+        (0..screen.size().x)
+            .map(|x| screen.get(uvec2(x, y)).unwrap().symbol.clone())
+            .collect::<String>()
+            .trim_end()
+            .to_owned()
+    }
+
+    #[test]
+    fn headless_focus_test() {
+        use super::*;
+        use crate::fun::CounterPanel;
+        use crate::input::ScriptedInputSource;
+
+        let source = ScriptedInputSource::parse(
+            "tick\nkey up\ntick\nkey tab\ntick\nkey up\nkey up\ntick\nclick 3 0\nkey down\n",
+        )
+        .unwrap();
+        let mut gui = Gui::headless(uvec2(40, 8), source);
+        let left = gui.add_panel(uvec2(1, 1), CounterPanel::new(0));
+        let right = gui.add_panel(uvec2(21, 1), CounterPanel::new(0));
+
+        gui.tick();
+        assert_eq!(gui.focused(), Some(left));
+        assert!(row(&gui, 0).starts_with("Count: 0            Count: 0"));
+
+        gui.tick();
+        assert!(row(&gui, 0).starts_with("Count: 1            Count: 0"));
+
+        gui.tick();
+        assert_eq!(gui.focused(), Some(right));
+
+        // Only the focused panel receives keys.
+        gui.tick();
+        assert!(row(&gui, 0).starts_with("Count: 1            Count: 1"));
+
+        // Clicking focuses the panel before the keys are handled.
+        gui.tick();
+        assert_eq!(gui.focused(), Some(left));
+        assert!(row(&gui, 0).starts_with("Count: 0            Count: 1"));
+    }
+
+    #[test]
+    fn headless_help_overlay_test() {
+        use super::*;
+        use crate::input::ScriptedInputSource;
+
+        let source = ScriptedInputSource::parse("key ?\ntick\nkey esc").unwrap();
+        let mut gui = Gui::headless(uvec2(60, 30), source);
+        gui.add_panel(uvec2(1, 1), TextPanel::new("Hello".to_owned()));
+
+        gui.tick();
+        assert!(gui.is_help_shown());
+        let screen: Vec<String> = (0..30).map(|y| row(&gui, y)).collect();
+        assert!(screen.iter().any(|line| line.contains("─ Keys ─")));
+        assert!(screen
+            .iter()
+            .any(|line| line.contains("quit") && line.contains("ctrl+c")));
+
+        gui.tick();
+        assert!(!gui.is_help_shown());
+        assert_eq!(row(&gui, 0), "Hello");
+    }
+
+    #[test]
+    fn headless_clear_layout_test() {
+        use super::*;
+        use crate::input::ScriptedInputSource;
+
+        let source = ScriptedInputSource::parse("tick\ntick").unwrap();
+        let mut gui = Gui::headless(uvec2(40, 4), source);
+        let panel = gui.add_panel(uvec2(1, 1), TextPanel::new("Hello world".to_owned()));
+        gui.set_layout(Layout::horizontal(vec![
+            (Constraint::Fixed(5), Layout::panel(panel)),
+            (Constraint::Flex(1), Layout::empty()),
+        ]));
+
+        gui.tick();
+        assert_eq!(gui.get_entity(&panel).unwrap().size, Some(uvec2(5, 4)));
+        assert_eq!(row(&gui, 0), "Hello");
+
+        // The panel isn't clipped to the space the layout gave it anymore.
+        gui.clear_layout();
+        gui.tick();
+        assert_eq!(gui.get_entity(&panel).unwrap().size, None);
+        assert_eq!(row(&gui, 0), "Hello world");
+    }
+
+    #[test]
+    fn headless_modal_test() {
+        use super::*;
+        use crate::fun::CounterPanel;
+        use crate::input::ScriptedInputSource;
+
+        let source = ScriptedInputSource::parse(
+            "key up\nkey down\nkey enter\ntick\ntick\ntext cam\nkey enter\n",
+        )
+        .unwrap();
+        let mut gui = Gui::headless(uvec2(50, 12), source);
+        gui.add_panel(uvec2(1, 1), CounterPanel::new(0));
+
+        let (reply, picked) = Reply::channel();
+        let items = vec!["Cube".to_owned(), "Sphere".to_owned()];
+        gui.open_modal(Menu::new(items, reply), "Add");
+
+        // The menu takes all keys, the counter doesn't see them.
+        gui.tick();
+        assert_eq!(picked.try_recv(), Ok(Some(1)));
+        assert!(!gui.has_modal());
+        assert!(row(&gui, 0).starts_with("Count: 0"));
+
+        let (reply, picked) = Reply::channel();
+        let commands = ["Load mesh", "Reset camera", "Toggle wireframe"];
+        let items = commands.iter().map(|item| item.to_string()).collect();
+        gui.open_modal(Palette::new(items, reply), "Commands");
+        gui.show_toast("Saved", Duration::from_secs(60));
+
+        gui.tick();
+        let screen: Vec<String> = (0..12).map(|y| row(&gui, y)).collect();
+        assert!(screen.iter().any(|line| line.contains("─ Commands ─")));
+        assert!(screen.iter().any(|line| line.contains("Toggle wireframe")));
+        assert!(screen[11].ends_with("╰───────╯"));
+        assert!(screen[10].ends_with("│ Saved │"));
+
+        gui.tick();
+        assert_eq!(picked.try_recv(), Ok(Some(1)));
+        assert_eq!(gui.toasts().collect::<Vec<_>>(), ["Saved"]);
+    }
+
+    #[test]
+    fn headless_command_test() {
+        use super::*;
+        use crate::input::ScriptedInputSource;
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let source = ScriptedInputSource::parse("key ctrl+l\ntick\nkey ctrl+p\n").unwrap();
+        let mut gui = Gui::headless(uvec2(60, 20), source);
+        gui.keymap_mut().bind("ctrl+l", "log.clear").unwrap();
+
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+        gui.register_command("log.clear", "Clear the log", move |_| {
+            counter.set(counter.get() + 1);
+        });
+        let names: Vec<String> = gui
+            .command_list()
+            .into_iter()
+            .map(|command| command.name)
+            .collect();
+        assert_eq!(names, ["help", "quit", "log.clear"]);
+
+        // Commands bound in the keymap run without the palette.
+        gui.tick();
+        assert_eq!(count.get(), 1);
+
+        gui.tick();
+        let screen: Vec<String> = (0..20).map(|y| row(&gui, y)).collect();
+        assert!(screen
+            .iter()
+            .any(|line| line.contains("Clear the log") && line.contains("ctrl+l")));
+        assert!(screen
+            .iter()
+            .any(|line| line.contains("Show key bindings") && line.contains('?')));
+    }
+
+    #[test]
+    fn headless_quit_test() {
+        use super::*;
+        use crate::input::ScriptedInputSource;
+
+        let source = ScriptedInputSource::parse("tick\ntick\nkey ctrl+c\ntick").unwrap();
+        let mut gui = Gui::headless(uvec2(20, 4), source);
+        gui.add_panel(uvec2(1, 1), TextPanel::new("Hello".to_owned()));
+
+        gui.tick();
+        assert!(!gui.has_quit());
+
+        // Returns on the tick quitting, rather than exiting the process.
+        gui.run();
+        assert!(gui.has_quit());
+        assert_eq!(row(&gui, 0), "Hello");
+
+        let mut gui = Gui::headless(uvec2(20, 4), ScriptedInputSource::new());
+        assert!(gui.run_command(keymap::QUIT));
+        assert!(gui.has_quit());
+
+        // A script without a quit stops after its last event.
+        let source = ScriptedInputSource::parse("key a\ntick\nkey b\ntick\ntick").unwrap();
+        let mut gui = Gui::headless(uvec2(20, 4), source);
+        gui.run();
+        assert!(!gui.has_quit());
+    }
+}
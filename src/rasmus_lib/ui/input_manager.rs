@@ -1,56 +1,579 @@
-use crate::input;
+use crate::input::keymap::{Keymap, KeymapState};
+use crate::input::unshifted_char;
 use crate::input::InputEvent;
+use crate::input::InputSource;
 use crate::input::KeyCode;
-use crate::INPUT;
+use crate::input::KeyModifiers;
+use crate::input::MouseEvent;
+use crate::input::MouseEventKind;
+use crate::input::TerminalEvent;
+use crate::math::*;
 // use crossterm::event::KeyCode;
-use itertools::Itertools;
+use std::cell::Cell;
+use std::rc::Rc;
 // use std::collections::HashMap;
 // use std::collections::VecDeque;
 
-// This logic could probably be moved to some other place...
-pub struct InputManager<'a> {
-    input: &'a input::Input,
+/// Collects the events of an `InputSource` into an `InputStream` every tick.
+pub struct InputManager {
+    source: Box<dyn InputSource>,
+    /// Keys that have been pressed but not released yet. Only tracked while
+    /// the terminal reports key releases.
+    held: Vec<KeyCode>,
 }
 
-impl<'a> InputManager<'a> {
-    pub fn new() -> Self {
-        Self { input: &INPUT }
+impl InputManager {
+    pub fn new(source: impl InputSource + 'static) -> Self {
+        Self {
+            source: Box::new(source),
+            held: Vec::new(),
+        }
     }
 
     pub fn tick(&mut self) -> InputStream {
-        InputStream::new(self.input.iter().collect())
+        let mut stream = InputStream::empty();
+        stream.reports_releases = self.source.reports_releases();
+        if !stream.reports_releases {
+            self.held.clear();
+        }
+        // Keys pressed and released during the same tick still count as held
+        // for it, so quick taps aren't lost.
+        stream.held = self.held.clone();
+
+        while let Some(event) = self.source.poll_event() {
+            match event {
+                TerminalEvent::Key(event) => {
+                    let key = held_key(event.code);
+                    if event.is_release() {
+                        self.held.retain(|held| *held != key);
+                    } else {
+                        if stream.reports_releases && !self.held.contains(&key) {
+                            self.held.push(key);
+                        }
+                        if !stream.held.contains(&key) {
+                            stream.held.push(key);
+                        }
+                    }
+
+                    stream.events.push((event, Flag::default()));
+                }
+                TerminalEvent::Mouse(event) => stream.mouse_events.push((event, Flag::default())),
+                TerminalEvent::Paste(text) => stream.pastes.push((text, Flag::default())),
+                // Only the final size matters when resized multiple times.
+                TerminalEvent::Resize { columns, rows } => {
+                    stream.resize = Some(uvec2(columns as u32, rows as u32))
+                }
+            }
+        }
+
+        stream
+    }
+
+    /// Whether the source has run out of events, see
+    /// `InputSource::is_finished`.
+    pub fn is_finished(&self) -> bool {
+        self.source.is_finished()
     }
 }
 
-impl<'a> Default for InputManager<'a> {
+impl Default for InputManager {
+    /// Read input from the terminal.
     fn default() -> Self {
-        Self::new()
+        Self::new(crate::input::CrosstermInputSource::new())
+    }
+}
+
+/// Held keys are tracked regardless of modifiers, since shift can be released
+/// before the key it modified. See `unshifted_char`.
+fn held_key(code: KeyCode) -> KeyCode {
+    match code {
+        KeyCode::Char(char) => KeyCode::Char(unshifted_char(char)),
+        code => code,
     }
 }
 
+/// Whether an event has been consumed, shared between the streams derived
+/// from the same input.
+type Flag = Rc<Cell<bool>>;
+
+/// The input received during a single tick.
+///
+/// Events can be consumed by whoever handles them, which hides them from
+/// everyone the stream is passed to afterwards. This is how the focused panel
+/// gets the first chance to handle a key, before it bubbles up to the gui and
+/// its tickers. Streams derived from another stream, like the ones passed to
+/// panels, share whether events have been consumed with it.
+///
+/// Key presses can also trigger named actions, see `resolve_actions`. An
+/// action shares its consumption with the key that completed it, so
+/// consuming one hides the other.
+///
+/// Key releases are only part of the stream when the terminal reports them,
+/// and are skipped by everything except `releases` and `is_held`.
 pub struct InputStream {
-    events: Vec<InputEvent>,
+    events: Vec<(InputEvent, Flag)>,
+    actions: Vec<(String, Flag)>,
+    mouse_events: Vec<(MouseEvent, Flag)>,
+    pastes: Vec<(String, Flag)>,
+    resize: Option<UVec2>,
+    held: Vec<KeyCode>,
+    held_actions: Vec<String>,
+    reports_releases: bool,
 }
 
 impl InputStream {
-    fn new(events: Vec<InputEvent>) -> Self {
-        Self { events }
-    }
-
     /// Create a new empty `InputStream`
     pub fn empty() -> Self {
-        Self { events: Vec::new() }
+        Self {
+            events: Vec::new(),
+            actions: Vec::new(),
+            mouse_events: Vec::new(),
+            pastes: Vec::new(),
+            resize: None,
+            held: Vec::new(),
+            held_actions: Vec::new(),
+            reports_releases: false,
+        }
+    }
+
+    /// Create a stream from key events, as if they were just pressed.
+    pub fn from_events(events: impl IntoIterator<Item = InputEvent>) -> Self {
+        Self {
+            events: events
+                .into_iter()
+                .map(|event| (event, Flag::default()))
+                .collect(),
+            ..Self::empty()
+        }
+    }
+
+    /// Create a stream from key events as reported with keyboard enhancement,
+    /// where `held` are the keys still held from earlier ticks.
+    pub fn from_events_with_releases(
+        events: impl IntoIterator<Item = InputEvent>,
+        held: impl IntoIterator<Item = KeyCode>,
+    ) -> Self {
+        let mut stream = Self::from_events(events);
+        stream.reports_releases = true;
+        stream.held = held.into_iter().map(held_key).collect();
+        for (event, _) in &stream.events {
+            let key = held_key(event.code);
+            if !event.is_release() && !stream.held.contains(&key) {
+                stream.held.push(key);
+            }
+        }
+        stream
+    }
+
+    /// Create a stream from mouse events, with positions relative to the
+    /// screen.
+    pub fn from_mouse_events(events: impl IntoIterator<Item = MouseEvent>) -> Self {
+        Self {
+            mouse_events: events
+                .into_iter()
+                .map(|event| (event, Flag::default()))
+                .collect(),
+            ..Self::empty()
+        }
+    }
+
+    /// Create a stream from pasted texts.
+    pub fn from_pastes(pastes: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            pastes: pastes
+                .into_iter()
+                .map(|text| (text, Flag::default()))
+                .collect(),
+            ..Self::empty()
+        }
+    }
+
+    /// A stream with the same resize information, but without any key or
+    /// mouse events.
+    pub fn without_keys(&self) -> Self {
+        Self {
+            resize: self.resize,
+            ..Self::empty()
+        }
+    }
+
+    /// A stream for a single panel, with the key events and pastes if
+    /// `with_keys` is set
+    /// and the mouse events for which `map_mouse` returns an event, like one
+    /// with a position relative to the panel.
+    pub fn derive(
+        &self,
+        with_keys: bool,
+        mut map_mouse: impl FnMut(&MouseEvent) -> Option<MouseEvent>,
+    ) -> Self {
+        Self {
+            events: if with_keys {
+                self.events.clone()
+            } else {
+                Vec::new()
+            },
+            actions: if with_keys {
+                self.actions.clone()
+            } else {
+                Vec::new()
+            },
+            mouse_events: self
+                .mouse_events
+                .iter()
+                .filter_map(|(event, flag)| Some((map_mouse(event)?, flag.clone())))
+                .collect(),
+            pastes: if with_keys {
+                self.pastes.clone()
+            } else {
+                Vec::new()
+            },
+            resize: self.resize,
+            held: if with_keys {
+                self.held.clone()
+            } else {
+                Vec::new()
+            },
+            held_actions: if with_keys {
+                self.held_actions.clone()
+            } else {
+                Vec::new()
+            },
+            reports_releases: self.reports_releases,
+        }
     }
 
-    pub fn iter(&self) -> std::slice::Iter<InputEvent> {
-        self.events.iter()
+    /// Look up the actions triggered by the unconsumed key events in
+    /// `keymap`. `state` keeps track of sequences spanning multiple ticks.
+    ///
+    /// Actions bound to a single key without ctrl or alt are also held while
+    /// that key is, see `is_action_held`.
+    pub fn resolve_actions(&mut self, keymap: &Keymap, state: &mut KeymapState) {
+        for (event, consumed) in &self.events {
+            if consumed.get() || event.is_release() {
+                continue;
+            }
+
+            for action in state.feed(keymap, *event) {
+                self.actions.push((action.to_owned(), consumed.clone()));
+            }
+        }
+
+        for binding in keymap.bindings() {
+            let [chord] = binding.keys.as_slice() else {
+                continue;
+            };
+            let modified = chord
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+
+            if !modified && self.is_held(chord.code) && !self.held_actions.contains(&binding.action)
+            {
+                self.held_actions.push(binding.action.clone());
+            }
+        }
+    }
+
+    /// Whether the terminal reports key releases, in which case `is_held`
+    /// tracks keys until they're released. Otherwise keys only count as held
+    /// during the ticks they're pressed or repeated in.
+    pub fn reports_releases(&self) -> bool {
+        self.reports_releases
+    }
+
+    /// Whether `key` was held down during this tick, regardless of
+    /// modifiers. Letters match regardless of case.
+    ///
+    /// Holding a key isn't consumed, since it can span many ticks.
+    pub fn is_held(&self, key: KeyCode) -> bool {
+        self.held.contains(&held_key(key))
+    }
+
+    /// Whether a key bound to `action` was held down during this tick, see
+    /// `resolve_actions`.
+    pub fn is_action_held(&self, action: &str) -> bool {
+        self.held_actions.iter().any(|held| held == action)
     }
 
+    /// Returns an iterator over the key releases, if the terminal reports
+    /// them.
+    pub fn releases(&self) -> impl Iterator<Item = &InputEvent> + '_ {
+        self.events
+            .iter()
+            .filter(|(event, consumed)| event.is_release() && !consumed.get())
+            .map(|(event, _)| event)
+    }
+
+    /// The new size of the terminal in cells, if it was resized since the
+    /// last tick.
+    pub fn resized(&self) -> Option<UVec2> {
+        self.resize
+    }
+
+    /// Returns an iterator over the key presses and repeats that haven't been
+    /// consumed.
+    pub fn iter(&self) -> impl Iterator<Item = &InputEvent> + '_ {
+        self.events
+            .iter()
+            .filter(|(event, consumed)| !event.is_release() && !consumed.get())
+            .map(|(event, _)| event)
+    }
+
+    /// Returns an iterator over the mouse events that haven't been consumed.
+    pub fn mouse_events(&self) -> impl Iterator<Item = &MouseEvent> + '_ {
+        self.mouse_events
+            .iter()
+            .filter(|(_, consumed)| !consumed.get())
+            .map(|(event, _)| event)
+    }
+
+    /// Returns an iterator over the triggered actions that haven't been
+    /// consumed.
+    pub fn actions(&self) -> impl Iterator<Item = &str> + '_ {
+        self.actions
+            .iter()
+            .filter(|(_, consumed)| !consumed.get())
+            .map(|(action, _)| action.as_str())
+    }
+
+    pub fn contains_action(&self, action: &str) -> bool {
+        self.actions().any(|other| other == action)
+    }
+
+    /// Consume the first unconsumed occurrence of `action`, along with the
+    /// key that triggered it, returning whether there was one.
+    pub fn consume_action(&self, action: &str) -> bool {
+        let Some((_, consumed)) = self
+            .actions
+            .iter()
+            .find(|(other, consumed)| !consumed.get() && other == action)
+        else {
+            return false;
+        };
+
+        consumed.set(true);
+        true
+    }
+
+    /// Whether there is an unconsumed event matching `event`, see
+    /// `InputEvent::matches`.
     pub fn contains(&self, event: &InputEvent) -> bool {
-        self.iter().contains(event)
+        self.events
+            .iter()
+            .any(|(other, consumed)| !consumed.get() && other.matches(event))
     }
 
     pub fn contains_key_code(&self, key_code: KeyCode) -> bool {
         self.iter().any(|event| event.code == key_code)
     }
+
+    /// Consume the first unconsumed event matching `event`, returning whether
+    /// there was one.
+    pub fn consume(&self, event: &InputEvent) -> bool {
+        consume_first(&self.events, |other| other.matches(event)).is_some()
+    }
+
+    /// Consume the first unconsumed press or repeat with `key_code`,
+    /// regardless of its modifiers, returning whether there was one.
+    pub fn consume_key_code(&self, key_code: KeyCode) -> bool {
+        consume_first(&self.events, |event| {
+            event.code == key_code && !event.is_release()
+        })
+        .is_some()
+    }
+
+    /// Consume and return the first unconsumed mouse event matching
+    /// `predicate`.
+    pub fn consume_mouse(&self, predicate: impl Fn(&MouseEvent) -> bool) -> Option<MouseEvent> {
+        consume_first(&self.mouse_events, predicate)
+    }
+
+    /// Consume every scroll event, returning how far was scrolled. Scrolling
+    /// down is positive.
+    pub fn consume_scroll(&self) -> i32 {
+        let mut delta = 0;
+        while let Some(event) = self.consume_mouse(|event| {
+            matches!(
+                event.kind,
+                MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+            )
+        }) {
+            delta += match event.kind {
+                MouseEventKind::ScrollDown => 1,
+                _ => -1,
+            };
+        }
+        delta
+    }
+
+    /// Consume and return the first unconsumed pasted text.
+    pub fn consume_paste(&self) -> Option<String> {
+        let (text, consumed) = self.pastes.iter().find(|(_, consumed)| !consumed.get())?;
+
+        consumed.set(true);
+        Some(text.clone())
+    }
+
+    /// Consume every remaining event, like a modal dialog that handles all
+    /// input.
+    pub fn consume_all(&self) {
+        let flags = self.events.iter().map(|(_, flag)| flag);
+        let flags = flags.chain(self.mouse_events.iter().map(|(_, flag)| flag));
+        for consumed in flags.chain(self.pastes.iter().map(|(_, flag)| flag)) {
+            consumed.set(true);
+        }
+    }
+}
+
+fn consume_first<T: Copy>(events: &[(T, Flag)], predicate: impl Fn(&T) -> bool) -> Option<T> {
+    let (event, consumed) = events
+        .iter()
+        .find(|(event, consumed)| !consumed.get() && predicate(event))?;
+
+    consumed.set(true);
+    Some(*event)
+}
+
+mod tests {
+    #[test]
+    fn consume_events_test() {
+        use super::*;
+
+        let stream = InputStream::from_events([
+            InputEvent::new(KeyCode::Char(' '), KeyModifiers::NONE),
+            InputEvent::new(KeyCode::Tab, KeyModifiers::NONE),
+        ]);
+
+        assert!(stream.consume_key_code(KeyCode::Char(' ')));
+        assert!(!stream.consume_key_code(KeyCode::Char(' ')));
+        assert!(!stream.contains_key_code(KeyCode::Char(' ')));
+        assert_eq!(stream.iter().count(), 1);
+
+        stream.consume_all();
+        assert!(!stream.contains_key_code(KeyCode::Tab));
+    }
+
+    #[test]
+    fn resolve_actions_test() {
+        use super::*;
+        use crate::input::keymap::{self, Keymap, KeymapState};
+
+        let mut stream = InputStream::from_events([
+            InputEvent::new(KeyCode::Char('x'), KeyModifiers::NONE),
+            InputEvent::new(KeyCode::Tab, KeyModifiers::NONE),
+            InputEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+        ]);
+        stream.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+
+        assert_eq!(
+            stream.actions().collect::<Vec<_>>(),
+            vec![keymap::FOCUS_NEXT, keymap::QUIT]
+        );
+
+        // Actions and the keys triggering them are consumed together.
+        assert!(stream.consume_action(keymap::FOCUS_NEXT));
+        assert!(!stream.contains_key_code(KeyCode::Tab));
+        assert!(stream.consume_key_code(KeyCode::Char('c')));
+        assert!(!stream.contains_action(keymap::QUIT));
+        assert!(stream.contains_key_code(KeyCode::Char('x')));
+    }
+
+    #[test]
+    fn held_keys_test() {
+        use super::*;
+        use crate::input::keymap::{Keymap, KeymapState};
+        use crate::input::KeyEventKind;
+
+        let event =
+            |char, kind| InputEvent::new_with_kind(KeyCode::Char(char), KeyModifiers::NONE, kind);
+
+        // `w` is still held from before, `d` is tapped and `s` is released.
+        let mut stream = InputStream::from_events_with_releases(
+            [
+                event('d', KeyEventKind::Press),
+                event('d', KeyEventKind::Release),
+                event('w', KeyEventKind::Repeat),
+                event('s', KeyEventKind::Release),
+            ],
+            [KeyCode::Char('w'), KeyCode::Char('s')],
+        );
+        stream.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+
+        assert!(stream.is_held(KeyCode::Char('W')));
+        assert!(stream.is_held(KeyCode::Char('d')));
+        assert!(stream.is_action_held("camera.forward"));
+        assert!(stream.is_action_held("camera.right"));
+
+        // Releases don't count as presses.
+        assert_eq!(stream.iter().count(), 2);
+        assert_eq!(stream.releases().count(), 2);
+        assert!(!stream.contains(&InputEvent::new(KeyCode::Char('s'), KeyModifiers::NONE)));
+        assert!(stream.consume(&InputEvent::new(KeyCode::Char('w'), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn shifted_terminal_keys_test() {
+        use super::*;
+        use crate::input::keymap::{self, Keymap, KeymapState};
+        use crate::input::{KeyEventKind, ScriptedInputSource};
+        use crossterm::event::{Event, KeyEvent, ModifierKeyCode};
+
+        let key = |code, modifiers, kind| {
+            TerminalEvent::from_crossterm(Event::Key(KeyEvent::new_with_kind(
+                code, modifiers, kind,
+            )))
+        };
+        let shift = KeyCode::Modifier(ModifierKeyCode::LeftShift);
+
+        // Typing `?` with keyboard enhancement, releasing shift first.
+        let events = [
+            key(shift, KeyModifiers::SHIFT, KeyEventKind::Press),
+            key(KeyCode::Char('/'), KeyModifiers::SHIFT, KeyEventKind::Press),
+            key(shift, KeyModifiers::NONE, KeyEventKind::Release),
+            key(
+                KeyCode::Char('/'),
+                KeyModifiers::NONE,
+                KeyEventKind::Release,
+            ),
+        ];
+        // Modifier keys on their own are left out.
+        assert_eq!(events.iter().flatten().count(), 2);
+
+        let source = ScriptedInputSource::from_events(events.into_iter().flatten());
+        let mut manager = InputManager::new(source);
+        let mut stream = manager.tick();
+        stream.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+
+        assert!(stream.contains_key_code(KeyCode::Char('?')));
+        assert!(stream.consume_action(keymap::HELP));
+        // The release of the unshifted key releases the shifted one.
+        assert!(stream.is_held(KeyCode::Char('?')));
+        assert!(!manager.tick().is_held(KeyCode::Char('?')));
+    }
+
+    #[test]
+    fn derived_stream_test() {
+        use super::*;
+        use crate::input::MouseButton;
+
+        let click = |x, y| {
+            MouseEvent::new(
+                MouseEventKind::Down(MouseButton::Left),
+                ivec2(x, y),
+                KeyModifiers::NONE,
+            )
+        };
+        let stream = InputStream::from_mouse_events([click(5, 5), click(20, 5)]);
+
+        // A panel at (4, 4), covering the first click.
+        let panel_stream = stream.derive(false, |event| {
+            (event.position.x < 10).then(|| event.relative_to(ivec2(4, 4)))
+        });
+        let consumed = panel_stream.consume_mouse(|_| true);
+
+        assert_eq!(consumed, Some(click(1, 1)));
+        assert_eq!(
+            stream.mouse_events().copied().collect::<Vec<_>>(),
+            vec![click(20, 5)]
+        );
+    }
 }
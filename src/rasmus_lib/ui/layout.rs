@@ -0,0 +1,405 @@
+//! Placement of panels using nested horizontal and vertical splits.
+
+use super::*;
+use crate::math::*;
+
+/// A rectangle of cells, with a zero based position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub position: UVec2,
+    pub size: UVec2,
+}
+
+impl Rect {
+    pub const fn new(position: UVec2, size: UVec2) -> Self {
+        Self { position, size }
+    }
+
+    /// A rectangle at the origin, like the whole screen.
+    pub const fn from_size(size: UVec2) -> Self {
+        Self::new(UVec2::new(0, 0), size)
+    }
+
+    /// The column after the right edge.
+    pub fn right(&self) -> u32 {
+        self.position.x + self.size.x
+    }
+
+    /// The row after the bottom edge.
+    pub fn bottom(&self) -> u32 {
+        self.position.y + self.size.y
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size.x == 0 || self.size.y == 0
+    }
+
+    pub fn contains(&self, point: UVec2) -> bool {
+        (self.position.x..self.right()).contains(&point.x)
+            && (self.position.y..self.bottom()).contains(&point.y)
+    }
+
+    /// The overlapping part of two rectangles, which is empty if they don't
+    /// overlap.
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let x = self.position.x.max(other.position.x);
+        let y = self.position.y.max(other.position.y);
+        let right = self.right().min(other.right()).max(x);
+        let bottom = self.bottom().min(other.bottom()).max(y);
+
+        Rect::new(uvec2(x, y), uvec2(right - x, bottom - y))
+    }
+
+    /// Shrink the rectangle by `padding`, without going below a size of zero.
+    pub fn inset(&self, padding: Padding) -> Rect {
+        let width = self.size.x.saturating_sub(padding.left + padding.right);
+        let height = self.size.y.saturating_sub(padding.top + padding.bottom);
+
+        Rect::new(
+            uvec2(
+                self.position.x + padding.left.min(self.size.x),
+                self.position.y + padding.top.min(self.size.y),
+            ),
+            uvec2(width, height),
+        )
+    }
+}
+
+/// Empty space around the contents of a layout, in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Padding {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+impl Padding {
+    pub const NONE: Padding = Padding::uniform(0);
+
+    pub const fn new(top: u32, right: u32, bottom: u32, left: u32) -> Self {
+        Self {
+            top,
+            right,
+            bottom,
+            left,
+        }
+    }
+
+    pub const fn uniform(padding: u32) -> Self {
+        Self::new(padding, padding, padding, padding)
+    }
+
+    pub const fn symmetric(vertical: u32, horizontal: u32) -> Self {
+        Self::new(vertical, horizontal, vertical, horizontal)
+    }
+}
+
+/// How much space a child of a split takes up along the split direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    /// An exact amount of cells.
+    Fixed(u32),
+    /// A percentage of the space of the split.
+    Percentage(u32),
+    /// A share of the space left over by the other constraints, proportional
+    /// to its weight.
+    Flex(u32),
+}
+
+impl Constraint {
+    /// Divide `length` cells between `constraints`.
+    ///
+    /// Fixed and percentage constraints are satisfied in order until the space
+    /// runs out, and the rest is shared by the flex constraints. Rounding
+    /// leftovers go to the first flex constraints.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use terminal_renderer::ui::Constraint;
+    ///
+    /// let sizes = Constraint::split(
+    ///     100,
+    ///     &[Constraint::Fixed(10), Constraint::Percentage(50), Constraint::Flex(1)],
+    /// );
+    /// assert_eq!(sizes, vec![10, 50, 40]);
+    /// ```
+    pub fn split(length: u32, constraints: &[Constraint]) -> Vec<u32> {
+        let mut remaining = length;
+
+        let mut sizes: Vec<u32> = constraints
+            .iter()
+            .map(|constraint| {
+                let wanted = match *constraint {
+                    Constraint::Fixed(size) => size,
+                    Constraint::Percentage(percentage) => {
+                        (length as u64 * percentage.min(100) as u64 / 100) as u32
+                    }
+                    Constraint::Flex(_) => 0,
+                };
+                let size = wanted.min(remaining);
+                remaining -= size;
+                size
+            })
+            .collect();
+
+        let total_weight: u32 = constraints
+            .iter()
+            .map(|constraint| match constraint {
+                Constraint::Flex(weight) => *weight,
+                _ => 0,
+            })
+            .sum();
+
+        if total_weight == 0 {
+            return sizes;
+        }
+
+        let flex_space = remaining;
+        for (size, constraint) in sizes.iter_mut().zip(constraints) {
+            if let Constraint::Flex(weight) = constraint {
+                *size = (flex_space as u64 * *weight as u64 / total_weight as u64) as u32;
+                remaining -= *size;
+            }
+        }
+
+        for (size, constraint) in sizes.iter_mut().zip(constraints) {
+            if remaining == 0 {
+                break;
+            }
+            if matches!(constraint, Constraint::Flex(weight) if *weight > 0) {
+                *size += 1;
+                remaining -= 1;
+            }
+        }
+
+        sizes
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Children are placed left to right.
+    Horizontal,
+    /// Children are placed top to bottom.
+    Vertical,
+}
+
+#[derive(Debug, Clone)]
+enum LayoutKind {
+    Empty,
+    Panel(PanelRef),
+    Split {
+        direction: Direction,
+        children: Vec<(Constraint, Layout)>,
+    },
+}
+
+/// A tree of splits, with panels as leaves. Set it with `Gui::set_layout`.
+///
+/// # Examples
+///
+/// ```
+/// use terminal_renderer::math::*;
+/// use terminal_renderer::ui::*;
+/// use terminal_renderer::utils::RefVec;
+///
+/// let mut panels = RefVec::new();
+/// let header = panels.push(TextPanel::create_panel(uvec2(1, 1), String::new()));
+/// let sidebar = panels.push(TextPanel::create_panel(uvec2(1, 1), String::new()));
+/// let content = panels.push(TextPanel::create_panel(uvec2(1, 1), String::new()));
+///
+/// let layout = Layout::vertical(vec![
+///     (Constraint::Fixed(3), Layout::panel(header)),
+///     (
+///         Constraint::Flex(1),
+///         Layout::horizontal(vec![
+///             (Constraint::Percentage(30), Layout::panel(sidebar)),
+///             (Constraint::Flex(1), Layout::panel(content)),
+///         ]),
+///     ),
+/// ])
+/// .with_padding(Padding::uniform(1));
+///
+/// let placements = layout.resolve(Rect::from_size(uvec2(82, 25)));
+/// assert_eq!(placements[2], (content, Rect::new(uvec2(25, 4), uvec2(56, 20))));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Layout {
+    kind: LayoutKind,
+    padding: Padding,
+}
+
+impl Layout {
+    /// Space left empty.
+    pub fn empty() -> Self {
+        Self::new(LayoutKind::Empty)
+    }
+
+    /// Space filled by a panel.
+    pub fn panel(panel: PanelRef) -> Self {
+        Self::new(LayoutKind::Panel(panel))
+    }
+
+    pub fn split(direction: Direction, children: Vec<(Constraint, Layout)>) -> Self {
+        Self::new(LayoutKind::Split {
+            direction,
+            children,
+        })
+    }
+
+    pub fn horizontal(children: Vec<(Constraint, Layout)>) -> Self {
+        Self::split(Direction::Horizontal, children)
+    }
+
+    pub fn vertical(children: Vec<(Constraint, Layout)>) -> Self {
+        Self::split(Direction::Vertical, children)
+    }
+
+    pub fn with_padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    fn new(kind: LayoutKind) -> Self {
+        Self {
+            kind,
+            padding: Padding::NONE,
+        }
+    }
+
+    /// Every panel of the layout, in the order they are placed.
+    pub fn panels(&self) -> Vec<PanelRef> {
+        match &self.kind {
+            LayoutKind::Empty => Vec::new(),
+            LayoutKind::Panel(panel) => vec![*panel],
+            LayoutKind::Split { children, .. } => children
+                .iter()
+                .flat_map(|(_, child)| child.panels())
+                .collect(),
+        }
+    }
+
+    /// Assign a rectangle inside of `area` to every panel of the layout.
+    pub fn resolve(&self, area: Rect) -> Vec<(PanelRef, Rect)> {
+        let mut placements = Vec::new();
+        self.resolve_into(area, &mut placements);
+        placements
+    }
+
+    fn resolve_into(&self, area: Rect, placements: &mut Vec<(PanelRef, Rect)>) {
+        let area = area.inset(self.padding);
+
+        match &self.kind {
+            LayoutKind::Empty => {}
+            LayoutKind::Panel(panel) => placements.push((*panel, area)),
+            LayoutKind::Split {
+                direction,
+                children,
+            } => {
+                let constraints: Vec<Constraint> =
+                    children.iter().map(|(constraint, _)| *constraint).collect();
+
+                let length = match direction {
+                    Direction::Horizontal => area.size.x,
+                    Direction::Vertical => area.size.y,
+                };
+
+                let mut offset = 0;
+                for ((_, child), size) in
+                    children.iter().zip(Constraint::split(length, &constraints))
+                {
+                    let child_area = match direction {
+                        Direction::Horizontal => Rect::new(
+                            uvec2(area.position.x + offset, area.position.y),
+                            uvec2(size, area.size.y),
+                        ),
+                        Direction::Vertical => Rect::new(
+                            uvec2(area.position.x, area.position.y + offset),
+                            uvec2(area.size.x, size),
+                        ),
+                    };
+                    offset += size;
+
+                    child.resolve_into(child_area, placements);
+                }
+            }
+        }
+    }
+}
+
+mod tests {
+    #[test]
+    fn constraint_split_test() {
+        use super::*;
+
+        assert_eq!(
+            Constraint::split(10, &[Constraint::Flex(1), Constraint::Flex(2)]),
+            vec![4, 6]
+        );
+        // Leftovers go to the first flex constraint.
+        assert_eq!(
+            Constraint::split(
+                10,
+                &[
+                    Constraint::Flex(1),
+                    Constraint::Flex(1),
+                    Constraint::Flex(1)
+                ]
+            ),
+            vec![4, 3, 3]
+        );
+        // Fixed sizes are clamped to the available space.
+        assert_eq!(
+            Constraint::split(
+                8,
+                &[
+                    Constraint::Fixed(5),
+                    Constraint::Fixed(5),
+                    Constraint::Flex(1)
+                ]
+            ),
+            vec![5, 3, 0]
+        );
+        assert_eq!(
+            Constraint::split(10, &[Constraint::Percentage(30), Constraint::Fixed(2)]),
+            vec![3, 2]
+        );
+    }
+
+    #[test]
+    fn nested_layout_test() {
+        use super::*;
+        use crate::utils::RefVec;
+
+        let mut panels = RefVec::new();
+        let header = panels.push(TextPanel::create_panel(uvec2(1, 1), String::new()));
+        let left = panels.push(TextPanel::create_panel(uvec2(1, 1), String::new()));
+        let right = panels.push(TextPanel::create_panel(uvec2(1, 1), String::new()));
+
+        let layout = Layout::vertical(vec![
+            (Constraint::Fixed(2), Layout::panel(header)),
+            (
+                Constraint::Flex(1),
+                Layout::horizontal(vec![
+                    (Constraint::Percentage(25), Layout::panel(left)),
+                    (Constraint::Flex(1), Layout::empty()),
+                    (Constraint::Fixed(10), Layout::panel(right)),
+                ])
+                .with_padding(Padding::symmetric(0, 1)),
+            ),
+        ]);
+
+        let placements = layout.resolve(Rect::from_size(uvec2(42, 12)));
+
+        assert_eq!(
+            placements,
+            vec![
+                (header, Rect::new(uvec2(0, 0), uvec2(42, 2))),
+                (left, Rect::new(uvec2(1, 2), uvec2(10, 10))),
+                (right, Rect::new(uvec2(31, 2), uvec2(10, 10))),
+            ]
+        );
+    }
+}
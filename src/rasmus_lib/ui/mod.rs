@@ -1,14 +1,32 @@
 mod app;
+mod border;
+mod cell_buffer;
+mod commands;
 // pub mod events;
 mod gui;
 mod input_manager;
+mod layout;
 mod panel;
+mod recording;
 mod render;
-
+mod style;
+mod text;
+pub mod theme;
+mod widgets;
+
+pub use border::*;
+pub use cell_buffer::*;
+pub use commands::*;
 pub use gui::*;
 pub use input_manager::*;
+pub use layout::*;
 pub use panel::*;
+pub use recording::*;
 pub use render::*;
+pub use style::*;
+pub use text::*;
+pub use theme::{Theme, ThemeError, BUILTIN_THEMES};
+pub use widgets::*;
 
 use crate::utils::StrUtils;
 
@@ -82,7 +100,7 @@ impl RenderBuffer {
     ///
     /// assert_eq!(None, lines.next());
     /// ```
-    pub fn lines(&self) -> std::str::Lines {
+    pub fn lines(&self) -> std::str::Lines<'_> {
         self.content.lines()
     }
 
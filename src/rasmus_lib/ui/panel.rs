@@ -10,19 +10,56 @@ pub trait Panel: std::fmt::Debug {
 
     fn render(&mut self) -> Render;
 
+    /// Whether the panel can receive focus, and with it key events.
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    /// Called when the space available to the panel changes, like when the
+    /// terminal is resized. `size` is measured in cells.
+    fn on_resize(&mut self, _size: UVec2) {}
+
+    /// Called when the panel gains or loses focus, so it can highlight
+    /// itself, like a button or the cursor of a text input.
+    fn on_focus_change(&mut self, _focused: bool) {}
+
+    /// The size the panel's contents would like to have, used for sizing
+    /// modals. Modals take up half the screen without one.
+    fn preferred_size(&self) -> Option<UVec2> {
+        None
+    }
+
+    /// Whether a modal is done, like a dialog that was answered, and should
+    /// be removed from the screen. See `Gui::open_modal`.
+    fn is_closed(&self) -> bool {
+        false
+    }
+
+    /// Commands the panel offers in the command palette, which are run with
+    /// `run_command`.
+    fn commands(&self) -> Vec<CommandInfo> {
+        Vec::new()
+    }
+
+    /// Run one of the panel's `commands`, called `name`.
+    #[allow(unused)]
+    fn run_command(&mut self, name: &str) {}
+
     fn as_any(&self) -> &dyn any::Any;
     fn as_any_mut(&mut self) -> &mut dyn any::Any;
 }
 
+/// Shows a fixed text. Formatting escape sequences in the text are parsed
+/// when it's set, see `Text::from_ansi`.
 #[derive(Debug)]
 pub struct TextPanel {
-    contents: RenderBuffer,
+    contents: Text,
 }
 
 impl TextPanel {
     pub fn new(contents: String) -> Self {
         Self {
-            contents: RenderBuffer::from_string(contents),
+            contents: Text::from_ansi(&contents),
         }
     }
 
@@ -31,13 +68,16 @@ impl TextPanel {
     }
 
     pub fn set_contents(&mut self, contents: String) {
-        self.contents = RenderBuffer::from_string(contents);
+        self.contents = Text::from_ansi(&contents);
     }
 }
 
 impl Panel for TextPanel {
+    fn focusable(&self) -> bool {
+        false
+    }
     fn render(&mut self) -> Render {
-        Render::new(self.contents.clone())
+        Render::from_text(self.contents.clone())
     }
 
     fn as_any(&self) -> &dyn any::Any {
@@ -50,7 +90,17 @@ impl Panel for TextPanel {
 
 #[derive(Debug)]
 pub struct PanelEntity {
+    /// One based position of the top left corner, like terminal coordinates.
     pub pos: UVec2,
+    /// The size the render is clipped to. `None` lets the panel extend to the
+    /// edges of the screen.
+    pub size: Option<UVec2>,
+    /// Panels with a higher z-index are drawn on top of lower ones. Panels
+    /// with the same z-index are drawn in the order they were added.
+    pub z_index: i32,
+    pub title: Option<String>,
+    /// A box drawn along the edges of the panel, around its contents.
+    pub border: Option<Border>,
     panel: Box<dyn Panel>,
 }
 impl PanelEntity {
@@ -60,10 +110,86 @@ impl PanelEntity {
     {
         Self {
             pos,
+            size: None,
+            z_index: 0,
+            title: None,
+            border: None,
             panel: Box::new(panel),
         }
     }
 
+    pub fn with_size(mut self, size: UVec2) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_z_index(mut self, z_index: i32) -> Self {
+        self.z_index = z_index;
+        self
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_border(mut self, border: Border) -> Self {
+        self.border = Some(border);
+        self
+    }
+
+    /// The zero based area covered by the panel on a screen of
+    /// `screen_size` cells.
+    pub fn rect(&self, screen_size: UVec2) -> Rect {
+        let position = uvec2(self.pos.x.saturating_sub(1), self.pos.y.saturating_sub(1));
+        let size = self.size.unwrap_or(uvec2(
+            screen_size.x.saturating_sub(position.x),
+            screen_size.y.saturating_sub(position.y),
+        ));
+
+        Rect::new(position, size).intersection(&Rect::from_size(screen_size))
+    }
+
+    /// The part of `rect` the panel's own render is drawn in, inside of the
+    /// border. Without a border, the title takes up the first row.
+    pub fn content_rect(&self, screen_size: UVec2) -> Rect {
+        let rect = self.rect(screen_size);
+
+        match (self.border, &self.title) {
+            (Some(_), _) => rect.inset(Padding::uniform(1)),
+            (None, Some(_)) => rect.inset(Padding::new(1, 0, 0, 0)),
+            (None, None) => rect,
+        }
+    }
+
+    /// Draw the panel's border and title into `buffer`, followed by `render`
+    /// clipped to the content rect.
+    ///
+    /// The border and title are drawn in the border style of the current
+    /// theme, or in bold with its accent style if the panel is `focused`.
+    pub fn draw(&self, buffer: &mut CellBuffer, render: &Render, focused: bool) {
+        let screen_size = buffer.size();
+        let rect = self.rect(screen_size);
+
+        let theme = Theme::current();
+        let mut style = match focused {
+            true => theme.accent.bold().to_cell_style(),
+            false => theme.border.to_cell_style(),
+        };
+
+        match (self.border, &self.title) {
+            (Some(border), title) => buffer.draw_border(rect, border, style, title.as_deref()),
+            (None, Some(title)) => {
+                style.attributes.insert(Attributes::BOLD);
+                let title_rect = Rect::new(rect.position, uvec2(rect.size.x, 1.min(rect.size.y)));
+                buffer.draw_text_clipped(title_rect, title, style);
+            }
+            (None, None) => {}
+        }
+
+        buffer.draw_render_clipped(self.content_rect(screen_size), render);
+    }
+
     pub fn tick(&mut self, key_events: &InputStream) {
         self.panel.tick(key_events);
     }
@@ -72,6 +198,34 @@ impl PanelEntity {
         self.panel.render()
     }
 
+    pub fn on_resize(&mut self, size: UVec2) {
+        self.panel.on_resize(size);
+    }
+
+    pub fn focusable(&self) -> bool {
+        self.panel.focusable()
+    }
+
+    pub fn on_focus_change(&mut self, focused: bool) {
+        self.panel.on_focus_change(focused);
+    }
+
+    pub fn preferred_size(&self) -> Option<UVec2> {
+        self.panel.preferred_size()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.panel.is_closed()
+    }
+
+    pub fn commands(&self) -> Vec<CommandInfo> {
+        self.panel.commands()
+    }
+
+    pub fn run_command(&mut self, name: &str) {
+        self.panel.run_command(name);
+    }
+
     pub fn as_panel<T: Panel + 'static>(&self) -> Option<&T> {
         self.panel.as_any().downcast_ref()
     }
@@ -0,0 +1,126 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Writes terminal output to an asciinema v2 `.cast` file, which can be
+/// played back with `asciinema play` or embedded with the asciinema player.
+///
+/// Source: https://docs.asciinema.org/manual/asciicast/v2/
+pub struct CastRecorder {
+    writer: Box<dyn Write>,
+    start: Instant,
+}
+
+impl CastRecorder {
+    /// Start a recording of a terminal with `width` columns and `height` rows,
+    /// writing the header to `writer` immediately.
+    pub fn new(mut writer: Box<dyn Write>, width: u16, height: u16) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        write!(
+            writer,
+            r#"{{"version": 2, "width": {}, "height": {}, "timestamp": {}"#,
+            width, height, timestamp
+        )?;
+        if let Ok(term) = std::env::var("TERM") {
+            write!(writer, r#", "env": {{"TERM": {}}}"#, json_string(&term))?;
+        }
+        writeln!(writer, "}}")?;
+
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    /// Start a recording written to the file at `path`.
+    pub fn create(path: impl AsRef<Path>, width: u16, height: u16) -> io::Result<Self> {
+        let file = io::BufWriter::new(fs::File::create(path)?);
+        Self::new(Box::new(file), width, height)
+    }
+
+    /// Record `output` written to the terminal, timestamped relative to the
+    /// start of the recording.
+    pub fn record_output(&mut self, output: &str) -> io::Result<()> {
+        if output.is_empty() {
+            return Ok(());
+        }
+
+        let time = self.start.elapsed().as_secs_f64();
+        writeln!(self.writer, "[{:.6}, \"o\", {}]", time, json_string(output))
+    }
+
+    /// Flush the recorded output.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl std::fmt::Debug for CastRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CastRecorder")
+            .field("start", &self.start)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Quote and escape `string` as a json string.
+fn json_string(string: &str) -> String {
+    let mut out = String::with_capacity(string.len() + 2);
+    out.push('"');
+
+    for char in string.chars() {
+        match char {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            char if (char as u32) < 0x20 || char == '\u{7f}' => {
+                out.push_str(&format!("\\u{:04x}", char as u32))
+            }
+            char => out.push(char),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+mod tests {
+    #[test]
+    fn cast_recording_test() {
+        use super::*;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for SharedBuffer {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let output = SharedBuffer::default();
+        let mut recorder = CastRecorder::new(Box::new(output.clone()), 80, 24).unwrap();
+        recorder.record_output("\x1b[1;1Hhi \"there\"\r\n").unwrap();
+        recorder.record_output("").unwrap();
+        recorder.finish().unwrap();
+
+        let content = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(r#"{"version": 2, "width": 80, "height": 24, "timestamp": "#));
+        assert!(lines[1].starts_with('['));
+        assert!(lines[1].ends_with(r#", "o", "\u001b[1;1Hhi \"there\"\r\n"]"#));
+    }
+}
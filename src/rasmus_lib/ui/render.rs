@@ -2,21 +2,87 @@ use super::*;
 use crate::ansi_term::*;
 // use crate::utils::*;
 
+/// What a panel shows, either as styled `Text` or as a `RenderBuffer` with
+/// escape sequences in it.
+///
+/// Text is drawn without having to parse escape sequences, and can't have
+/// them cut in half when it's clipped. A render buffer is still needed for
+/// escape sequences placed among the text, like inline images, while
+/// sequences that don't show anything can be added to text with
+/// `with_sequence`.
 pub struct Render {
     buffer: RenderBuffer,
+    text: Option<Text>,
+    sequences: Vec<String>,
 }
 
 impl Render {
     pub fn new(buffer: RenderBuffer) -> Self {
-        Self { buffer }
+        Self {
+            buffer,
+            text: None,
+            sequences: Vec::new(),
+        }
+    }
+
+    pub fn from_text(text: impl Into<Text>) -> Self {
+        let text = text.into();
+        let mut buffer = RenderBuffer::new();
+        for line in &text.lines {
+            buffer.pushln(&line.to_ansi());
+        }
+
+        Self {
+            buffer,
+            text: Some(text),
+            sequences: Vec::new(),
+        }
+    }
+
+    /// The render with `sequence` printed before it, for escape sequences
+    /// that don't show anything, like setting the clipboard.
+    pub fn with_sequence(mut self, sequence: impl Into<String>) -> Self {
+        self.sequences.push(sequence.into());
+        self
+    }
+
+    /// The sequences added with `with_sequence`.
+    pub fn sequences(&self) -> &[String] {
+        &self.sequences
+    }
+
+    /// The text of the render, if it was made from one.
+    pub fn text(&self) -> Option<&Text> {
+        self.text.as_ref()
+    }
+
+    /// The render as text, parsing its escape sequences if it wasn't made
+    /// from text, see `Text::from_ansi`.
+    pub fn to_text(&self) -> Text {
+        match &self.text {
+            Some(text) => text.clone(),
+            None => Text::from_ansi(self.buffer.as_no_trailing_newline()),
+        }
     }
 
     pub fn width(&self) -> u16 {
-        self.buffer.width()
+        match &self.text {
+            Some(text) => text.width() as u16,
+            None => self.buffer.width(),
+        }
     }
 
     pub fn height(&self) -> u16 {
-        self.buffer.line_count()
+        match &self.text {
+            Some(text) => text.height() as u16,
+            None => self.buffer.line_count(),
+        }
+    }
+
+    /// An iterator over the lines of the render, see `RenderBuffer::lines`.
+    /// Text is serialized with `Line::to_ansi`.
+    pub fn lines(&self) -> std::str::Lines<'_> {
+        self.buffer.lines()
     }
 
     /// Print the contents of render to current screen position.
@@ -30,6 +96,9 @@ impl Render {
     ///
     pub fn print(&self, buffer: &mut RenderBuffer, column: u16) {
         buffer.push(&cursor::get_save_position());
+        for sequence in &self.sequences {
+            buffer.push(sequence);
+        }
 
         let mut is_first = true;
         for line in self.buffer.lines() {
@@ -54,3 +123,9 @@ impl Render {
         buffer.push(&cursor::get_restore_position());
     }
 }
+
+impl From<Text> for Render {
+    fn from(text: Text) -> Self {
+        Self::from_text(text)
+    }
+}
@@ -0,0 +1,261 @@
+use super::*;
+use crate::ansi_term::*;
+use std::error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Colors and attributes applied to text, which can be layered on top of
+/// each other with `Style::patch`.
+///
+/// Colors that are `None` are left as they are, so a style that only makes
+/// text bold keeps the colors of whatever it's applied to.
+///
+/// ```
+/// use terminal_renderer::ansi_term::Color;
+/// use terminal_renderer::ui::Style;
+///
+/// let error = Style::new().fg(Color::Red);
+/// let style = error.patch(Style::new().bold());
+/// assert_eq!(style, Style::new().fg(Color::Red).bold());
+/// assert_eq!("red bold".parse(), Ok(style));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub attributes: Attributes,
+}
+
+impl Style {
+    /// A style that doesn't change anything.
+    pub const fn new() -> Self {
+        Self {
+            fg: None,
+            bg: None,
+            attributes: Attributes::NONE,
+        }
+    }
+
+    pub const fn fg(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    pub const fn bg(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    pub const fn add(mut self, attributes: Attributes) -> Self {
+        self.attributes = self.attributes.union(attributes);
+        self
+    }
+
+    pub const fn bold(self) -> Self {
+        self.add(Attributes::BOLD)
+    }
+
+    pub const fn dim(self) -> Self {
+        self.add(Attributes::DIM)
+    }
+
+    pub const fn italic(self) -> Self {
+        self.add(Attributes::ITALIC)
+    }
+
+    pub const fn underline(self) -> Self {
+        self.add(Attributes::UNDERLINE)
+    }
+
+    pub const fn reverse(self) -> Self {
+        self.add(Attributes::REVERSE)
+    }
+
+    /// `other` layered on top of `self`: its colors replace those of `self`
+    /// where they are set, and the attributes of both are combined.
+    pub const fn patch(self, other: Style) -> Self {
+        Self {
+            fg: match other.fg {
+                Some(color) => Some(color),
+                None => self.fg,
+            },
+            bg: match other.bg {
+                Some(color) => Some(color),
+                None => self.bg,
+            },
+            attributes: self.attributes.union(other.attributes),
+        }
+    }
+
+    /// `cell` with the style applied to it.
+    pub fn apply_to(self, cell: CellStyle) -> CellStyle {
+        CellStyle {
+            fg: self.fg.unwrap_or(cell.fg),
+            bg: self.bg.unwrap_or(cell.bg),
+            attributes: cell.attributes.union(self.attributes),
+        }
+    }
+
+    /// The cell style of text drawn with only this style.
+    pub fn to_cell_style(self) -> CellStyle {
+        self.apply_to(CellStyle::default())
+    }
+
+    /// The formats turning on the style.
+    pub fn formats(self) -> Vec<Format> {
+        let mut formats: Vec<Format> = self.attributes.formats().collect();
+        formats.extend(self.fg.map(Format::Fg));
+        formats.extend(self.bg.map(Format::Bg));
+        formats
+    }
+
+    /// `text` with the style turned on before it and reset after it, like
+    /// `format::format_str`. Text is returned as is by an empty style.
+    pub fn paint(self, text: &str) -> String {
+        if self == Style::new() {
+            return text.to_owned();
+        }
+        format::format_str(text, &self.formats())
+    }
+}
+
+/// Default colors are left unset, so they don't replace other colors when
+/// patched on top of them.
+impl From<CellStyle> for Style {
+    fn from(cell: CellStyle) -> Self {
+        Self {
+            fg: (cell.fg != Color::Default).then_some(cell.fg),
+            bg: (cell.bg != Color::Default).then_some(cell.bg),
+            attributes: cell.attributes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseStyleError {
+    /// The word that couldn't be parsed.
+    pub word: String,
+}
+
+impl Display for ParseStyleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown color or attribute \"{}\"", self.word)
+    }
+}
+
+impl error::Error for ParseStyleError {}
+
+impl FromStr for Style {
+    type Err = ParseStyleError;
+
+    /// Parse a space separated list of attributes and colors, like
+    /// `"bold fg:#ff8800 bg:black"`.
+    ///
+    /// Attributes are `bold`, `dim`, `italic`, `underline`, `blink`,
+    /// `reverse`, `hidden` and `strikethrough`. Colors are one of the eight
+    /// color names, `default`, an index into the 256 color palette or a hex
+    /// color like `#ff8800`. A color without `fg:` or `bg:` is a foreground
+    /// color, and `none` is a style without anything.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let mut style = Style::new();
+
+        for word in string.split_whitespace() {
+            let error = || ParseStyleError {
+                word: word.to_owned(),
+            };
+            let lowercase = word.to_ascii_lowercase();
+
+            let attribute = match lowercase.as_str() {
+                "none" => Some(Attributes::NONE),
+                "bold" => Some(Attributes::BOLD),
+                "dim" => Some(Attributes::DIM),
+                "italic" => Some(Attributes::ITALIC),
+                "underline" => Some(Attributes::UNDERLINE),
+                "blink" => Some(Attributes::BLINKING),
+                "reverse" => Some(Attributes::REVERSE),
+                "hidden" => Some(Attributes::HIDDEN),
+                "strikethrough" => Some(Attributes::STRIKETHROUGH),
+                _ => None,
+            };
+            if let Some(attribute) = attribute {
+                style = style.add(attribute);
+                continue;
+            }
+
+            if let Some(color) = lowercase.strip_prefix("bg:") {
+                style.bg = Some(parse_color(color).ok_or_else(error)?);
+            } else {
+                let color = lowercase.strip_prefix("fg:").unwrap_or(&lowercase);
+                style.fg = Some(parse_color(color).ok_or_else(error)?);
+            }
+        }
+
+        Ok(style)
+    }
+}
+
+fn parse_color(string: &str) -> Option<Color> {
+    let color = match string {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        "default" => Color::Default,
+        _ => {
+            if let Some(hex) = string.strip_prefix('#') {
+                if hex.len() != 6 || !hex.is_ascii() {
+                    return None;
+                }
+                let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+                return Some(Color::RGB(channel(0)?, channel(2)?, channel(4)?));
+            }
+            Color::ColorId(string.parse().ok()?)
+        }
+    };
+    Some(color)
+}
+
+mod tests {
+    #[test]
+    fn parse_style_test() {
+        use super::*;
+
+        assert_eq!("".parse(), Ok(Style::new()));
+        assert_eq!(
+            "Bold fg:#FF8800 bg:238 underline".parse(),
+            Ok(Style::new()
+                .fg(Color::RGB(255, 136, 0))
+                .bg(Color::ColorId(238))
+                .bold()
+                .underline())
+        );
+        assert_eq!(
+            "cyan bg:default".parse(),
+            Ok(Style::new().fg(Color::Cyan).bg(Color::Default))
+        );
+        assert_eq!(
+            "bold fg:#12345".parse::<Style>(),
+            Err(ParseStyleError {
+                word: "fg:#12345".to_owned()
+            })
+        );
+        assert!("256".parse::<Style>().is_err());
+
+        // Patching keeps the colors of the cell that aren't set.
+        let cell = CellStyle {
+            fg: Color::Green,
+            bg: Color::Blue,
+            attributes: Attributes::BOLD,
+        };
+        let styled = Style::new().bg(Color::Black).italic().apply_to(cell);
+        assert_eq!(styled.fg, Color::Green);
+        assert_eq!(styled.bg, Color::Black);
+        assert!(styled
+            .attributes
+            .contains(Attributes::BOLD.union(Attributes::ITALIC)));
+    }
+}
@@ -0,0 +1,506 @@
+//! Styled text that panels render, instead of strings with escape sequences
+//! in them.
+//!
+//! A `Text` is made of `Line`s, which are made of `Span`s of text with the
+//! same `Style`. As the styles are kept apart from the text, lines can be
+//! measured, cut off, aligned and wrapped without breaking up escape
+//! sequences. The escape sequences are only added when drawing, see
+//! `Text::to_ansi`.
+//!
+//! Widths are counted in terminal columns, like `StrUtils::visual_len`, so
+//! wide characters count twice. See `utils::grapheme_width`.
+
+use super::*;
+use crate::ansi_term::parse::{tokenize, Token};
+use crate::ansi_term::*;
+use crate::math::UVec2;
+use crate::utils::grapheme_width;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How a line is placed in a space wider than itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Alignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// A piece of text drawn in one style.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Span {
+    pub content: String,
+    pub style: Style,
+}
+
+impl Span {
+    pub fn raw(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            style: Style::new(),
+        }
+    }
+
+    pub fn styled(content: impl Into<String>, style: Style) -> Self {
+        Self {
+            content: content.into(),
+            style,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        graphemes(&self.content).map(grapheme_width).sum()
+    }
+}
+
+impl From<&str> for Span {
+    fn from(content: &str) -> Self {
+        Self::raw(content)
+    }
+}
+
+impl From<String> for Span {
+    fn from(content: String) -> Self {
+        Self::raw(content)
+    }
+}
+
+/// A row of spans.
+///
+/// The style of the line is applied below the styles of its spans, like for
+/// highlighting a selected row.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Line {
+    pub spans: Vec<Span>,
+    pub style: Style,
+}
+
+impl Line {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn raw(content: impl Into<String>) -> Self {
+        Span::raw(content).into()
+    }
+
+    pub fn styled(content: impl Into<String>, style: Style) -> Self {
+        Span::styled(content, style).into()
+    }
+
+    /// The line with `style` layered on top of its own style.
+    pub fn patch_style(mut self, style: Style) -> Self {
+        self.style = self.style.patch(style);
+        self
+    }
+
+    pub fn push(&mut self, span: impl Into<Span>) {
+        self.spans.push(span.into());
+    }
+
+    /// The line with `span` added to its end.
+    pub fn with(mut self, span: impl Into<Span>) -> Self {
+        self.push(span);
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.spans.iter().map(Span::width).sum()
+    }
+
+    /// The spans with the style of the line layered below their own, for
+    /// joining them into another line.
+    pub fn into_spans(self) -> Vec<Span> {
+        let style = self.style;
+        self.spans
+            .into_iter()
+            .map(|span| Span::styled(span.content, style.patch(span.style)))
+            .collect()
+    }
+
+    /// The text of the spans, without any styles.
+    pub fn plain(&self) -> String {
+        self.spans
+            .iter()
+            .map(|span| span.content.as_str())
+            .collect()
+    }
+
+    /// The line cut down to at most `width` columns. A wide grapheme that
+    /// would only fit halfway is left out.
+    pub fn truncate(&self, width: usize) -> Line {
+        let mut used = 0;
+        let graphemes = self.styled_graphemes().take_while(|(grapheme, _)| {
+            used += grapheme_width(grapheme);
+            used <= width
+        });
+        Self::from_graphemes(graphemes, self.style)
+    }
+
+    /// The line cut down or padded with spaces to exactly `width` columns,
+    /// placed according to `alignment`. The padding has the style of the
+    /// line.
+    pub fn aligned(&self, width: usize, alignment: Alignment) -> Line {
+        let line = self.truncate(width);
+        let padding = width - line.width();
+        let (left, right) = match alignment {
+            Alignment::Left => (0, padding),
+            Alignment::Center => (padding / 2, padding - padding / 2),
+            Alignment::Right => (padding, 0),
+        };
+
+        let mut spans = Vec::with_capacity(line.spans.len() + 2);
+        if left > 0 {
+            spans.push(Span::raw(" ".repeat(left)));
+        }
+        spans.extend(line.spans);
+        if right > 0 {
+            spans.push(Span::raw(" ".repeat(right)));
+        }
+        Line {
+            spans,
+            style: self.style,
+        }
+    }
+
+    /// Break the line into lines of at most `width` columns, between words
+    /// where possible. The whitespace a line is broken at is left out, and
+    /// words longer than `width` are broken up.
+    ///
+    /// ```
+    /// use terminal_renderer::ui::Line;
+    ///
+    /// let rows: Vec<String> = Line::raw("the quick brown fox")
+    ///     .wrap(10)
+    ///     .iter()
+    ///     .map(Line::plain)
+    ///     .collect();
+    /// assert_eq!(rows, ["the quick", "brown fox"]);
+    /// ```
+    pub fn wrap(&self, width: usize) -> Vec<Line> {
+        fn row_width(row: &[(&str, Style)]) -> usize {
+            row.iter()
+                .map(|(grapheme, _)| grapheme_width(grapheme))
+                .sum()
+        }
+
+        let width = width.max(1);
+        let mut rows = Vec::new();
+        let mut row: Vec<(&str, Style)> = Vec::new();
+        // Where in the row the last whitespace is.
+        let mut break_at = None;
+
+        for (grapheme, style) in self.styled_graphemes() {
+            let is_whitespace = grapheme.chars().all(char::is_whitespace);
+            let fits = |row: &[(&str, Style)]| row_width(row) + grapheme_width(grapheme) <= width;
+
+            // An empty row takes any grapheme, so ones wider than `width` get
+            // a row of their own.
+            if !row.is_empty() && !fits(&row) {
+                if is_whitespace {
+                    rows.push(std::mem::take(&mut row));
+                    break_at = None;
+                    continue;
+                }
+                match break_at.take() {
+                    Some(index) => {
+                        // The rest of the row has no whitespace, as the break
+                        // was at the last one.
+                        let rest = row.split_off(index + 1);
+                        row.pop();
+                        rows.push(std::mem::replace(&mut row, rest));
+                    }
+                    None => rows.push(std::mem::take(&mut row)),
+                }
+                // A wide grapheme can still overflow what's left of the word.
+                if !row.is_empty() && !fits(&row) {
+                    rows.push(std::mem::take(&mut row));
+                }
+            }
+
+            if is_whitespace {
+                break_at = Some(row.len());
+            }
+            row.push((grapheme, style));
+        }
+        rows.push(row);
+
+        rows.into_iter()
+            .map(|row| Self::from_graphemes(row, self.style))
+            .collect()
+    }
+
+    /// The line with the escape sequences for its styles, which are reset at
+    /// the end.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        let mut current = CellStyle::default();
+
+        for span in &self.spans {
+            let style = self.style.patch(span.style).to_cell_style();
+            out.push_str(&style.get_transition(Some(current)));
+            out.push_str(&span.content);
+            current = style;
+        }
+        if current != CellStyle::default() {
+            out.push_str(&format::get_format(Format::Reset));
+        }
+
+        out
+    }
+
+    /// The graphemes with a width, along with the style of their span.
+    fn styled_graphemes(&self) -> impl Iterator<Item = (&str, Style)> {
+        self.spans
+            .iter()
+            .flat_map(|span| graphemes(&span.content).map(move |grapheme| (grapheme, span.style)))
+    }
+
+    /// A line of `graphemes`, joining runs with the same style into spans.
+    fn from_graphemes<'a>(
+        graphemes: impl IntoIterator<Item = (&'a str, Style)>,
+        style: Style,
+    ) -> Line {
+        let mut spans: Vec<Span> = Vec::new();
+        for (grapheme, grapheme_style) in graphemes {
+            match spans.last_mut() {
+                Some(span) if span.style == grapheme_style => span.content.push_str(grapheme),
+                _ => spans.push(Span::styled(grapheme, grapheme_style)),
+            }
+        }
+        Line { spans, style }
+    }
+}
+
+impl From<Span> for Line {
+    fn from(span: Span) -> Self {
+        Self {
+            spans: vec![span],
+            style: Style::new(),
+        }
+    }
+}
+
+impl From<Vec<Span>> for Line {
+    fn from(spans: Vec<Span>) -> Self {
+        Self {
+            spans,
+            style: Style::new(),
+        }
+    }
+}
+
+impl From<&str> for Line {
+    fn from(content: &str) -> Self {
+        Self::raw(content)
+    }
+}
+
+impl From<String> for Line {
+    fn from(content: String) -> Self {
+        Self::raw(content)
+    }
+}
+
+/// Lines of styled text.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Text {
+    pub lines: Vec<Line>,
+}
+
+impl Text {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Unstyled text, with a line for every line of `content`.
+    pub fn raw(content: &str) -> Self {
+        content.lines().map(Line::raw).collect()
+    }
+
+    /// Text from a string with formatting escape sequences in it, like the
+    /// ones made with `format::format_str`. Other escape sequences, like
+    /// cursor movement, are left out.
+    ///
+    /// ```
+    /// use terminal_renderer::ansi_term::{format, Color, Format};
+    /// use terminal_renderer::ui::{Line, Span, Style, Text};
+    ///
+    /// let text = Text::from_ansi(&format::format_str("error", &[Format::Fg(Color::Red)]));
+    /// let red = Style::new().fg(Color::Red);
+    /// assert_eq!(text.lines, [Line::from(Span::styled("error", red))]);
+    /// ```
+    pub fn from_ansi(string: &str) -> Self {
+        let mut style = CellStyle::default();
+        let mut text = Text::new();
+
+        for string_line in string.lines() {
+            let mut line = Line::new();
+            for token in tokenize(string_line) {
+                match token {
+                    Token::Text(content) => {
+                        let content: String = content
+                            .graphemes(true)
+                            .filter(|grapheme| !grapheme.chars().all(char::is_control))
+                            .collect();
+                        if !content.is_empty() {
+                            line.push(Span::styled(content, style.into()));
+                        }
+                    }
+                    // Styles carry over to the next line.
+                    Token::Sgr(params) => style.apply_sgr(&params),
+                    Token::Csi { .. } | Token::Other(_) => {}
+                }
+            }
+            text.push_line(line);
+        }
+
+        text
+    }
+
+    pub fn push_line(&mut self, line: impl Into<Line>) {
+        self.lines.push(line.into());
+    }
+
+    /// Add `span` to the last line, or a new line if there is none.
+    pub fn push_span(&mut self, span: impl Into<Span>) {
+        match self.lines.last_mut() {
+            Some(line) => line.push(span),
+            None => self.push_line(span.into()),
+        }
+    }
+
+    pub fn extend(&mut self, text: Text) {
+        self.lines.extend(text.lines);
+    }
+
+    pub fn width(&self) -> usize {
+        self.lines.iter().map(Line::width).max().unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Every line wrapped to `width`, see `Line::wrap`.
+    pub fn wrap(&self, width: usize) -> Text {
+        self.lines
+            .iter()
+            .flat_map(|line| line.wrap(width))
+            .collect()
+    }
+
+    /// The text cut down to `size`.
+    pub fn truncate(&self, size: UVec2) -> Text {
+        self.lines
+            .iter()
+            .take(size.y as usize)
+            .map(|line| line.truncate(size.x as usize))
+            .collect()
+    }
+
+    /// The lines with the escape sequences for their styles, separated by
+    /// `\r\n`.
+    pub fn to_ansi(&self) -> String {
+        let lines: Vec<String> = self.lines.iter().map(Line::to_ansi).collect();
+        lines.join("\r\n")
+    }
+}
+
+impl<T: Into<Line>> FromIterator<T> for Text {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            lines: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<Line> for Text {
+    fn from(line: Line) -> Self {
+        Self { lines: vec![line] }
+    }
+}
+
+impl From<Span> for Text {
+    fn from(span: Span) -> Self {
+        Line::from(span).into()
+    }
+}
+
+impl From<&str> for Text {
+    fn from(content: &str) -> Self {
+        Self::raw(content)
+    }
+}
+
+/// The graphemes of `string` that take up space.
+fn graphemes(string: &str) -> impl Iterator<Item = &str> {
+    string
+        .graphemes(true)
+        .filter(|grapheme| !grapheme.chars().all(char::is_control))
+}
+
+mod tests {
+    #[test]
+    fn text_layout_test() {
+        use super::*;
+        use crate::utils::StrUtils;
+
+        let red = Style::new().fg(Color::Red);
+        let line = Line::raw("error: ").with(Span::styled("file not found", red));
+        assert_eq!(line.width(), 21);
+
+        // Cutting off keeps the styles of what's left, without any escape
+        // sequences broken up.
+        let truncated = line.truncate(9);
+        assert_eq!(
+            truncated.spans,
+            [Span::raw("error: "), Span::styled("fi", red)]
+        );
+        assert_eq!(truncated.to_ansi(), "error: \x1b[31mfi\x1b[0m");
+        assert_eq!(truncated.to_ansi().visual_len(), 9);
+
+        let wrapped = line.wrap(10);
+        let rows: Vec<String> = wrapped.iter().map(Line::plain).collect();
+        assert_eq!(rows, ["error:", "file not", "found"]);
+        assert_eq!(wrapped[1].spans, [Span::styled("file not", red)]);
+        let rows: Vec<String> = Line::raw("abcdefgh ij")
+            .wrap(3)
+            .iter()
+            .map(Line::plain)
+            .collect();
+        assert_eq!(rows, ["abc", "def", "gh", "ij"]);
+
+        let right = Line::raw("42").aligned(5, Alignment::Right);
+        assert_eq!(right.plain(), "   42");
+        assert_eq!(
+            Line::raw("ab").aligned(5, Alignment::Center).plain(),
+            " ab  "
+        );
+        assert_eq!(
+            Line::raw("abcdef").aligned(3, Alignment::Left).plain(),
+            "abc"
+        );
+
+        // Wide characters take up two columns.
+        let wide = Line::raw("中文 abc");
+        assert_eq!(wide.width(), 8);
+        assert_eq!(wide.truncate(3).plain(), "中");
+        assert_eq!(wide.aligned(5, Alignment::Left).plain(), "中文 ");
+        assert_eq!(wide.aligned(3, Alignment::Right).plain(), " 中");
+        let rows: Vec<String> = wide.wrap(3).iter().map(Line::plain).collect();
+        assert_eq!(rows, ["中", "文", "abc"]);
+        let rows: Vec<String> = Line::raw("中文字")
+            .wrap(1)
+            .iter()
+            .map(Line::plain)
+            .collect();
+        assert_eq!(rows, ["中", "文", "字"]);
+
+        // Serializing and parsing again gives back the same text.
+        let text: Text = [line.clone(), Line::styled("next", Style::new().bold())]
+            .into_iter()
+            .collect();
+        assert_eq!(Text::from_ansi(&text.to_ansi()), text);
+    }
+}
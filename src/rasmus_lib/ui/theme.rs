@@ -0,0 +1,277 @@
+//! The styles widgets and panels draw with, by what they're used for.
+//!
+//! Themes can be loaded from a config file, which sets the style of a role on
+//! every line, see `Style::from_str` for the style format:
+//!
+//! ```text
+//! # Comments start with '#'.
+//! # Start from one of the built-in themes, replacing everything set before.
+//! base = light
+//! accent = magenta bold
+//! selection = fg:black bg:#88c0d0
+//! # An empty style removes the style of a role.
+//! muted =
+//! ```
+
+use super::*;
+use crate::ansi_term::*;
+use static_init::dynamic;
+use std::error;
+use std::fmt::{self, Display};
+use std::io;
+use std::path::Path;
+
+#[dynamic]
+static mut THEME: Theme = Theme::dark();
+
+/// The names of the built-in themes, see `Theme::builtin`.
+pub const BUILTIN_THEMES: [&str; 3] = ["dark", "light", "high-contrast"];
+
+/// Styles for the roles text plays in the ui.
+///
+/// The theme everything is drawn with is set with `Theme::set_current`, or
+/// `Gui::set_theme`. Widgets read it when rendering, so a new theme shows up
+/// on the next frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Things that should stand out, like the border of the focused panel
+    /// and matched characters.
+    pub accent: Style,
+    /// The borders of panels without focus.
+    pub border: Style,
+    /// Selected items and text, and focused buttons.
+    pub selection: Style,
+    /// Regular text in boxes drawn by the gui, like toasts.
+    pub text: Style,
+    /// Text of lesser importance, like hints, placeholders and line numbers.
+    pub muted: Style,
+    pub error: Style,
+    pub warning: Style,
+    /// Search matches.
+    pub highlight: Style,
+}
+
+impl Theme {
+    /// The default theme, made for terminals with a dark background.
+    pub const fn dark() -> Self {
+        Self {
+            accent: Style::new().fg(Color::Green),
+            border: Style::new(),
+            selection: Style::new().reverse(),
+            text: Style::new(),
+            muted: Style::new().dim(),
+            error: Style::new().fg(Color::Red).bold(),
+            warning: Style::new().fg(Color::Yellow),
+            highlight: Style::new().fg(Color::Black).bg(Color::Yellow),
+        }
+    }
+
+    /// A theme for terminals with a light background, where yellow and green
+    /// are hard to read.
+    pub const fn light() -> Self {
+        Self {
+            accent: Style::new().fg(Color::Blue),
+            border: Style::new(),
+            selection: Style::new().reverse(),
+            text: Style::new(),
+            muted: Style::new().fg(Color::ColorId(244)),
+            error: Style::new().fg(Color::Red).bold(),
+            warning: Style::new().fg(Color::Magenta),
+            highlight: Style::new().fg(Color::Black).bg(Color::Cyan),
+        }
+    }
+
+    /// A theme that avoids dim text and tells roles apart by more than just
+    /// their color.
+    pub const fn high_contrast() -> Self {
+        Self {
+            accent: Style::new().fg(Color::Yellow).bold(),
+            border: Style::new().fg(Color::White),
+            selection: Style::new().reverse().bold(),
+            text: Style::new().fg(Color::White),
+            muted: Style::new().italic(),
+            error: Style::new().fg(Color::Red).bold().underline(),
+            warning: Style::new().fg(Color::Yellow).underline(),
+            highlight: Style::new().fg(Color::Black).bg(Color::White).bold(),
+        }
+    }
+
+    /// The built-in theme called `name`, which is one of `BUILTIN_THEMES`.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// The theme widgets and panels are drawn with.
+    pub fn current() -> Self {
+        *THEME.read()
+    }
+
+    pub fn set_current(theme: Theme) {
+        *THEME.write() = theme;
+    }
+
+    /// Parse a theme from a config file, see the module documentation for
+    /// the format. Roles the config doesn't set are those of the dark theme.
+    pub fn parse(config: &str) -> Result<Self, ThemeError> {
+        let mut theme = Self::default();
+        theme.apply_config(config)?;
+        Ok(theme)
+    }
+
+    /// Parse the config file at `path`, like `Theme::parse`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ThemeError> {
+        let config = std::fs::read_to_string(path)?;
+        Self::parse(&config)
+    }
+
+    /// Apply the styles in `config`, replacing the styles of the roles it
+    /// mentions.
+    ///
+    /// Nothing is changed if the config is invalid.
+    pub fn apply_config(&mut self, config: &str) -> Result<(), ThemeError> {
+        let mut theme = *self;
+
+        for (index, line) in config.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (role, value) = line
+                .split_once('=')
+                .ok_or(ThemeError::MissingEquals { line: line_number })?;
+            let (role, value) = (role.trim(), value.trim());
+
+            if role == "base" {
+                theme = Self::builtin(value).ok_or_else(|| ThemeError::UnknownTheme {
+                    line: line_number,
+                    name: value.to_owned(),
+                })?;
+                continue;
+            }
+
+            let style =
+                value
+                    .parse()
+                    .map_err(|error: ParseStyleError| ThemeError::InvalidStyle {
+                        line: line_number,
+                        word: error.word,
+                    })?;
+            *theme
+                .role_mut(role)
+                .ok_or_else(|| ThemeError::UnknownRole {
+                    line: line_number,
+                    role: role.to_owned(),
+                })? = style;
+        }
+
+        *self = theme;
+        Ok(())
+    }
+
+    fn role_mut(&mut self, role: &str) -> Option<&mut Style> {
+        let style = match role {
+            "accent" => &mut self.accent,
+            "border" => &mut self.border,
+            "selection" => &mut self.selection,
+            "text" => &mut self.text,
+            "muted" => &mut self.muted,
+            "error" => &mut self.error,
+            "warning" => &mut self.warning,
+            "highlight" => &mut self.highlight,
+            _ => return None,
+        };
+        Some(style)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(io::Error),
+    MissingEquals { line: usize },
+    UnknownRole { line: usize, role: String },
+    UnknownTheme { line: usize, name: String },
+    InvalidStyle { line: usize, word: String },
+}
+
+impl Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::Io(error) => write!(f, "couldn't read theme: {}", error),
+            ThemeError::MissingEquals { line } => {
+                write!(f, "expected \"role = style\" at line {}", line)
+            }
+            ThemeError::UnknownRole { line, role } => {
+                write!(f, "unknown role \"{}\" at line {}", role, line)
+            }
+            ThemeError::UnknownTheme { line, name } => {
+                write!(f, "unknown theme \"{}\" at line {}", name, line)
+            }
+            ThemeError::InvalidStyle { line, word } => {
+                write!(
+                    f,
+                    "unknown color or attribute \"{}\" at line {}",
+                    word, line
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for ThemeError {}
+
+impl From<io::Error> for ThemeError {
+    fn from(error: io::Error) -> Self {
+        ThemeError::Io(error)
+    }
+}
+
+mod tests {
+    #[test]
+    fn parse_theme_test() {
+        use super::*;
+
+        let theme =
+            Theme::parse("# light, but louder\nbase = light\naccent = magenta bold\n\nmuted =\n")
+                .unwrap();
+        assert_eq!(theme.accent, Style::new().fg(Color::Magenta).bold());
+        assert_eq!(theme.muted, Style::new());
+        assert_eq!(theme.error, Theme::light().error);
+
+        for name in BUILTIN_THEMES {
+            assert!(Theme::builtin(name).is_some());
+        }
+
+        let mut theme = Theme::dark();
+        assert!(matches!(
+            theme.apply_config("accent = blue\nbase = solarized"),
+            Err(ThemeError::UnknownTheme { line: 2, .. })
+        ));
+        assert!(matches!(
+            theme.apply_config("acent = blue"),
+            Err(ThemeError::UnknownRole { line: 1, .. })
+        ));
+        assert!(matches!(
+            theme.apply_config("accent = bleu"),
+            Err(ThemeError::InvalidStyle { line: 1, .. })
+        ));
+        assert!(matches!(
+            theme.apply_config("accent blue"),
+            Err(ThemeError::MissingEquals { line: 1 })
+        ));
+        // Failed configs leave the theme untouched.
+        assert_eq!(theme, Theme::dark());
+    }
+}
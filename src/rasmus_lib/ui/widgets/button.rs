@@ -0,0 +1,62 @@
+use super::*;
+use crate::input::keymap;
+use crate::ui::{InputStream, Panel, Render};
+use std::any;
+
+/// A button pressed with `ui.activate` or by clicking it. Presses are picked
+/// up with `take_pressed`.
+#[derive(Debug, Default)]
+pub struct Button {
+    label: String,
+    focused: bool,
+    pressed: bool,
+}
+
+impl Button {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn set_label(&mut self, label: impl Into<String>) {
+        self.label = label.into();
+    }
+
+    /// Whether the button was pressed since the last call.
+    pub fn take_pressed(&mut self) -> bool {
+        std::mem::take(&mut self.pressed)
+    }
+}
+
+impl Panel for Button {
+    fn tick(&mut self, key_events: &InputStream) {
+        if key_events.consume_action(keymap::ACTIVATE) {
+            self.pressed = true;
+        }
+        if key_events.consume_mouse(is_left_press).is_some() {
+            self.pressed = true;
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let label = format!("[ {} ]", self.label);
+        Render::from_text(highlight_span(&label, self.focused))
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
@@ -0,0 +1,78 @@
+use super::*;
+use crate::input::keymap;
+use crate::ui::{InputStream, Line, Panel, Render};
+use std::any;
+
+/// A labeled box toggled with `ui.activate` or by clicking it.
+#[derive(Debug, Default)]
+pub struct Checkbox {
+    label: String,
+    checked: bool,
+    focused: bool,
+    changed: bool,
+}
+
+impl Checkbox {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_checked(mut self, checked: bool) -> Self {
+        self.checked = checked;
+        self
+    }
+
+    pub fn is_checked(&self) -> bool {
+        self.checked
+    }
+
+    /// Check or uncheck the box. Unlike toggling it with the keyboard or
+    /// mouse, this doesn't count as a change for `take_changed`.
+    pub fn set_checked(&mut self, checked: bool) {
+        self.checked = checked;
+    }
+
+    /// Whether the box was toggled since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    fn toggle(&mut self) {
+        self.checked = !self.checked;
+        self.changed = true;
+    }
+}
+
+impl Panel for Checkbox {
+    fn tick(&mut self, key_events: &InputStream) {
+        while key_events.consume_action(keymap::ACTIVATE) {
+            self.toggle();
+        }
+        while key_events.consume_mouse(is_left_press).is_some() {
+            self.toggle();
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let mark = if self.checked { "[x]" } else { "[ ]" };
+
+        let line = Line::from(highlight_span(mark, self.focused))
+            .with(" ")
+            .with(self.label.as_str());
+        Render::from_text(line)
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
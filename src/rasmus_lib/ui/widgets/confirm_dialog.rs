@@ -0,0 +1,188 @@
+use super::*;
+use crate::input::keymap;
+use crate::input::KeyCode;
+use crate::math::*;
+use crate::ui::{InputStream, Line, Panel, Render, Text};
+use crate::utils::StrUtils;
+use std::any;
+
+/// A modal asking a yes or no question, see `Gui::open_modal`.
+///
+/// The buttons are chosen between with the `ui.left` and `ui.right` actions
+/// and pressed with `ui.activate` or a click. `y` and `n` answer directly, and
+/// escape answers no.
+#[derive(Debug)]
+pub struct ConfirmDialog {
+    message: String,
+    confirm_label: String,
+    cancel_label: String,
+    /// Whether the confirm button is selected, rather than the cancel one.
+    confirm_selected: bool,
+    focused: bool,
+    answer: Option<bool>,
+    reply: Reply<bool>,
+}
+
+impl ConfirmDialog {
+    pub fn new(message: impl Into<String>, reply: Reply<bool>) -> Self {
+        Self {
+            message: message.into(),
+            confirm_label: "Yes".to_owned(),
+            cancel_label: "No".to_owned(),
+            confirm_selected: true,
+            focused: false,
+            answer: None,
+            reply,
+        }
+    }
+
+    pub fn with_labels(mut self, confirm: impl Into<String>, cancel: impl Into<String>) -> Self {
+        self.confirm_label = confirm.into();
+        self.cancel_label = cancel.into();
+        self
+    }
+
+    /// The answer given, once the dialog is closed.
+    pub fn answer(&self) -> Option<bool> {
+        self.answer
+    }
+
+    fn answer_with(&mut self, answer: bool) {
+        if self.answer.is_none() {
+            self.answer = Some(answer);
+            self.reply.send(answer);
+        }
+    }
+
+    /// The message as rendered, above a blank line and the buttons.
+    fn message_text(&self) -> Text {
+        Text::raw(&self.message)
+    }
+
+    fn buttons(&self) -> (String, String) {
+        (
+            format!("[ {} ]", self.confirm_label),
+            format!("[ {} ]", self.cancel_label),
+        )
+    }
+}
+
+impl Panel for ConfirmDialog {
+    fn tick(&mut self, key_events: &InputStream) {
+        let actions = [keymap::LEFT, keymap::RIGHT, keymap::ACTIVATE];
+        for action in consume_actions(key_events, &actions) {
+            match action {
+                keymap::LEFT => self.confirm_selected = true,
+                keymap::RIGHT => self.confirm_selected = false,
+                _ => self.answer_with(self.confirm_selected),
+            }
+        }
+
+        if key_events.consume_key_code(KeyCode::Char('y')) {
+            self.answer_with(true);
+        }
+        if key_events.consume_key_code(KeyCode::Char('n'))
+            || key_events.consume_key_code(KeyCode::Esc)
+        {
+            self.answer_with(false);
+        }
+
+        let button_row = self.message_text().height() as i32 + 1;
+        if let Some(event) = key_events.consume_mouse(is_left_press) {
+            let (confirm, cancel) = self.buttons();
+            let x = event.position.x.max(0) as usize;
+            if event.position.y == button_row {
+                if x < confirm.visual_len() {
+                    self.answer_with(true);
+                } else if (confirm.visual_len() + 2..confirm.visual_len() + 2 + cancel.visual_len())
+                    .contains(&x)
+                {
+                    self.answer_with(false);
+                }
+            }
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let mut text = self.message_text();
+        text.push_line("");
+
+        let (confirm, cancel) = self.buttons();
+        let buttons = Line::from(highlight_span(
+            &confirm,
+            self.focused && self.confirm_selected,
+        ))
+        .with("  ")
+        .with(highlight_span(
+            &cancel,
+            self.focused && !self.confirm_selected,
+        ));
+        text.push_line(buttons);
+
+        Render::from_text(text)
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn preferred_size(&self) -> Option<UVec2> {
+        let (confirm, cancel) = self.buttons();
+        let buttons_width = confirm.visual_len() + 2 + cancel.visual_len();
+        let message = self.message_text();
+        let width = message
+            .lines
+            .iter()
+            .map(Line::width)
+            .chain([buttons_width])
+            .max()
+            .unwrap_or(0);
+        let height = message.height() + 2;
+
+        Some(uvec2(width as u32, height as u32))
+    }
+
+    fn is_closed(&self) -> bool {
+        self.answer.is_some()
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn confirm_dialog_click_test() {
+        use super::*;
+        use crate::input::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+        let click = |position: IVec2| {
+            InputStream::from_mouse_events([MouseEvent::new(
+                MouseEventKind::Down(MouseButton::Left),
+                position,
+                KeyModifiers::NONE,
+            )])
+        };
+
+        // The buttons are clicked on the row they are drawn on, whatever
+        // the height of the message.
+        for message in ["", "Delete?", "Delete\nthe mesh?\n"] {
+            let mut dialog = ConfirmDialog::new(message, Reply::Ignore);
+            let render = dialog.render();
+            let button_row = render
+                .lines()
+                .position(|line| line.contains("[ Yes ]"))
+                .unwrap();
+            assert_eq!(dialog.preferred_size().unwrap().y as usize, button_row + 1);
+
+            dialog.tick(&click(ivec2(2, button_row as i32 + 1)));
+            assert_eq!(dialog.answer(), None);
+            dialog.tick(&click(ivec2(9, button_row as i32)));
+            assert_eq!(dialog.answer(), Some(false));
+        }
+    }
+}
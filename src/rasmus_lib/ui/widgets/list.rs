@@ -0,0 +1,236 @@
+use super::*;
+use crate::input::keymap;
+use crate::math::*;
+use crate::ui::{Alignment, InputStream, Line, Panel, Render, Style, Text, Theme};
+use std::any;
+
+/// A scrollable list of items, one of which can be selected.
+///
+/// The selection is moved with the `ui.up`, `ui.down`, `ui.page_up`,
+/// `ui.page_down`, `ui.home` and `ui.end` actions, or by clicking an item.
+/// Activating the selected item, with `ui.activate` or by clicking it again,
+/// can be picked up with `take_activated`. The mouse wheel scrolls without
+/// changing the selection.
+#[derive(Debug, Default)]
+pub struct List {
+    items: Vec<Line>,
+    selected: Option<usize>,
+    /// The index of the first visible item.
+    offset: usize,
+    width: usize,
+    height: usize,
+    focused: bool,
+    activated: Option<usize>,
+}
+
+impl List {
+    pub fn new(items: impl IntoIterator<Item = impl Into<Line>>) -> Self {
+        let mut list = Self::default();
+        list.set_items(items);
+        list
+    }
+
+    pub fn items(&self) -> &[Line] {
+        &self.items
+    }
+
+    /// Replace the items, keeping the selected index if it's still valid.
+    /// The first item is selected otherwise.
+    pub fn set_items(&mut self, items: impl IntoIterator<Item = impl Into<Line>>) {
+        self.items = items.into_iter().map(Into::into).collect();
+        self.selected = match self.selected {
+            _ if self.items.is_empty() => None,
+            Some(selected) => Some(selected.min(self.items.len() - 1)),
+            None => Some(0),
+        };
+        self.offset = self.offset.min(self.max_offset());
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn selected_item(&self) -> Option<&Line> {
+        Some(&self.items[self.selected?])
+    }
+
+    /// Select the item at `index`, scrolling it into view. Out of range
+    /// indices select the last item.
+    pub fn select(&mut self, index: usize) {
+        if self.items.is_empty() {
+            return;
+        }
+
+        let index = index.min(self.items.len() - 1);
+        self.selected = Some(index);
+
+        let height = self.height();
+        if index < self.offset {
+            self.offset = index;
+        } else if index >= self.offset + height {
+            self.offset = index + 1 - height;
+        }
+    }
+
+    /// The index of the item activated since the last call, if any.
+    pub fn take_activated(&mut self) -> Option<usize> {
+        self.activated.take()
+    }
+
+    /// The number of visible items, at least one.
+    fn height(&self) -> usize {
+        self.height.max(1)
+    }
+
+    fn max_offset(&self) -> usize {
+        self.items.len().saturating_sub(self.height())
+    }
+}
+
+impl Panel for List {
+    fn tick(&mut self, key_events: &InputStream) {
+        let page = (self.height() - 1).max(1);
+
+        let actions = [
+            keymap::UP,
+            keymap::DOWN,
+            keymap::PAGE_UP,
+            keymap::PAGE_DOWN,
+            keymap::HOME,
+            keymap::END,
+            keymap::ACTIVATE,
+        ];
+        for action in consume_actions(key_events, &actions) {
+            let selected = self.selected.unwrap_or(0);
+            match action {
+                keymap::UP => self.select(selected.saturating_sub(1)),
+                keymap::DOWN => self.select(selected + 1),
+                keymap::PAGE_UP => self.select(selected.saturating_sub(page)),
+                keymap::PAGE_DOWN => self.select(selected + page),
+                keymap::HOME => self.select(0),
+                keymap::END => self.select(usize::MAX),
+                _ => self.activated = self.selected,
+            }
+        }
+
+        while let Some(event) = key_events.consume_mouse(|event| {
+            is_left_press(event)
+                || matches!(
+                    event.kind,
+                    MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+                )
+        }) {
+            match event.kind {
+                MouseEventKind::ScrollUp => self.offset = self.offset.saturating_sub(1),
+                MouseEventKind::ScrollDown => {
+                    self.offset = (self.offset + 1).min(self.max_offset())
+                }
+                _ => {
+                    let index = self.offset + event.position.y.max(0) as usize;
+                    if index < self.items.len() {
+                        if self.selected == Some(index) {
+                            self.activated = Some(index);
+                        }
+                        self.select(index);
+                    }
+                }
+            }
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let mut text = Text::new();
+        let width = self.width;
+
+        let visible = self.items.iter().enumerate().skip(self.offset);
+        for (index, item) in visible.take(self.height()) {
+            let line = if Some(index) != self.selected {
+                item.truncate(width)
+            } else if self.focused {
+                item.aligned(width, Alignment::Left)
+                    .patch_style(Theme::current().selection)
+            } else {
+                item.truncate(width).patch_style(Style::new().bold())
+            };
+            text.push_line(line);
+        }
+
+        Render::from_text(text)
+    }
+
+    fn on_resize(&mut self, size: UVec2) {
+        self.width = size.x as usize;
+        self.height = size.y as usize;
+        if let Some(selected) = self.selected {
+            self.select(selected);
+        }
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn list_navigation_test() {
+        use super::*;
+        use crate::input::keymap::{Keymap, KeymapState};
+        use crate::input::{
+            InputEvent, KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+        };
+
+        let items: Vec<String> = (0..10).map(|i| format!("item {}", i)).collect();
+        let mut list = List::new(items);
+        list.on_resize(uvec2(10, 3));
+
+        let keys = |codes: &[KeyCode]| {
+            let mut stream = InputStream::from_events(
+                codes
+                    .iter()
+                    .map(|code| InputEvent::new(*code, KeyModifiers::NONE)),
+            );
+            stream.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+            stream
+        };
+
+        list.tick(&keys(&[KeyCode::Down, KeyCode::Down, KeyCode::Down]));
+        assert_eq!(list.selected(), Some(3));
+        // The selection is scrolled into view.
+        let render = list.render();
+        assert_eq!(render.lines().next(), Some("item 1"));
+
+        list.tick(&keys(&[KeyCode::End]));
+        assert_eq!(
+            list.selected_item().map(Line::plain).as_deref(),
+            Some("item 9")
+        );
+        list.tick(&keys(&[KeyCode::PageUp, KeyCode::Up]));
+        assert_eq!(list.selected(), Some(6));
+
+        list.tick(&keys(&[KeyCode::Enter]));
+        assert_eq!(list.take_activated(), Some(6));
+        assert_eq!(list.take_activated(), None);
+
+        // Scrolling leaves the selection alone, clicking selects the row.
+        let scroll = MouseEvent::new(MouseEventKind::ScrollUp, ivec2(0, 0), KeyModifiers::NONE);
+        let click = MouseEvent::new(
+            MouseEventKind::Down(MouseButton::Left),
+            ivec2(2, 1),
+            KeyModifiers::NONE,
+        );
+        list.tick(&InputStream::from_mouse_events([scroll, scroll, click]));
+        assert_eq!(list.selected(), Some(5));
+        assert_eq!(list.take_activated(), None);
+
+        list.tick(&InputStream::from_mouse_events([click]));
+        assert_eq!(list.take_activated(), Some(5));
+    }
+}
@@ -0,0 +1,880 @@
+use super::*;
+use crate::input::keymap;
+use crate::input::string_editor::StringEditor;
+use crate::input::{InputEvent, KeyCode, KeyModifiers};
+use crate::math::*;
+use crate::ui::{InputStream, Line, Panel, Render, Span, Style, Text, Theme};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::any;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A line logged to a `LogBuffer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub level: Level,
+    /// Where the entry comes from, the module path for the `log` macros.
+    pub target: String,
+    pub message: String,
+    /// When the entry was pushed, since the buffer was created.
+    pub time: Duration,
+}
+
+#[derive(Debug)]
+struct LogBufferInner {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    /// The number of entries ever pushed, including the ones dropped since.
+    pushed: usize,
+}
+
+/// A ring buffer of log entries, keeping only the most recent ones.
+///
+/// Clones share the same entries, so a buffer can be written to by a
+/// `BufferLogger` on any thread while a `LogPanel` shows it.
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    inner: Arc<Mutex<LogBufferInner>>,
+    start: Instant,
+}
+
+impl LogBuffer {
+    pub const DEFAULT_CAPACITY: usize = 1000;
+
+    pub fn new() -> Self {
+        Self::with_capacity(Self::DEFAULT_CAPACITY)
+    }
+
+    /// A buffer keeping at most `capacity` entries, at least one.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            inner: Arc::new(Mutex::new(LogBufferInner {
+                entries: VecDeque::with_capacity(capacity),
+                capacity,
+                pushed: 0,
+            })),
+            start: Instant::now(),
+        }
+    }
+
+    /// Add an entry, dropping the oldest one if the buffer is full.
+    pub fn push(&self, level: Level, target: &str, message: impl Into<String>) {
+        let entry = LogEntry {
+            level,
+            target: target.to_owned(),
+            message: message.into(),
+            time: self.start.elapsed(),
+        };
+
+        let mut inner = self.lock();
+        if inner.entries.len() == inner.capacity {
+            inner.entries.pop_front();
+        }
+        inner.entries.push_back(entry);
+        inner.pushed += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.lock().capacity
+    }
+
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    /// The entries, oldest first.
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.lock().entries.iter().cloned().collect()
+    }
+
+    /// The entries, along with the number of entries ever pushed.
+    fn snapshot(&self) -> (Vec<LogEntry>, usize) {
+        let inner = self.lock();
+        (inner.entries.iter().cloned().collect(), inner.pushed)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LogBufferInner> {
+        // A panic while holding the lock can't leave the entries in an invalid
+        // state, so a poisoned buffer is still usable.
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A logger for the `log` crate writing to a `LogBuffer`, so diagnostics show
+/// up in a `LogPanel` instead of being printed over the gui.
+///
+/// Events of the `tracing` crate reach it as well when `tracing` is built with
+/// its `log` feature, which turns events into `log` records while no
+/// `tracing` subscriber is set. Otherwise, use a `BufferLayer`.
+#[derive(Debug)]
+pub struct BufferLogger {
+    buffer: LogBuffer,
+    level: LevelFilter,
+}
+
+impl BufferLogger {
+    /// A logger keeping records of `level` and more severe ones.
+    pub fn new(buffer: LogBuffer, level: LevelFilter) -> Self {
+        Self { buffer, level }
+    }
+
+    /// Set the logger as the logger of the `log` crate. This fails if a logger
+    /// has been set already.
+    pub fn install(self) -> Result<(), SetLoggerError> {
+        let level = self.level;
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+}
+
+impl Log for BufferLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.buffer
+                .push(record.level(), record.target(), record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// A layer for `tracing` subscribers writing events to a `LogBuffer`, like
+/// `BufferLogger` does for `log` records. Fields other than the message are
+/// added to it as `name=value`.
+///
+/// Only available with the `tracing` feature.
+#[cfg(feature = "tracing")]
+#[derive(Debug)]
+pub struct BufferLayer {
+    buffer: LogBuffer,
+    level: LevelFilter,
+}
+
+#[cfg(feature = "tracing")]
+impl BufferLayer {
+    /// A layer keeping events of `level` and more severe ones.
+    pub fn new(buffer: LogBuffer, level: LevelFilter) -> Self {
+        Self { buffer, level }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for BufferLayer {
+    fn enabled(
+        &self,
+        metadata: &tracing::Metadata<'_>,
+        _context: tracing_subscriber::layer::Context<'_, S>,
+    ) -> bool {
+        tracing_level(metadata.level()) <= self.level
+    }
+
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _context: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let metadata = event.metadata();
+        let level = tracing_level(metadata.level());
+        if level > self.level {
+            return;
+        }
+
+        let mut message = EventMessage::default();
+        event.record(&mut message);
+        self.buffer
+            .push(level, metadata.target(), message.message + &message.fields);
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn tracing_level(level: &tracing::Level) -> Level {
+    match *level {
+        tracing::Level::ERROR => Level::Error,
+        tracing::Level::WARN => Level::Warn,
+        tracing::Level::INFO => Level::Info,
+        tracing::Level::DEBUG => Level::Debug,
+        _ => Level::Trace,
+    }
+}
+
+/// Collects the fields of a `tracing` event into a message.
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+struct EventMessage {
+    message: String,
+    /// The other fields, each starting with a space.
+    fields: String,
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::field::Visit for EventMessage {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_owned(),
+            name => self.fields.push_str(&format!(" {}={}", name, value)),
+        }
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            name => self.fields.push_str(&format!(" {}={:?}", name, value)),
+        }
+    }
+}
+
+/// The query being typed at the bottom of a `LogPanel`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    Search,
+    Filter,
+}
+
+impl Prompt {
+    fn prefix(self) -> &'static str {
+        match self {
+            Prompt::Search => "/",
+            Prompt::Filter => "&",
+        }
+    }
+}
+
+/// A row of the log: a line of the message of an entry.
+struct Row<'a> {
+    entry: &'a LogEntry,
+    line: &'a str,
+    /// Whether this is the first line of the entry, which is prefixed with
+    /// its time, level and target.
+    is_first: bool,
+}
+
+/// A scrollable view of a `LogBuffer`, newest entries at the bottom.
+///
+/// The view follows new entries until it is scrolled up with the `ui.up`,
+/// `ui.page_up` or `ui.home` actions or the mouse wheel, and scrolling back
+/// down to the bottom follows them again. Like in `less`, other keys are:
+///
+/// | Keys | |
+/// |---|---|
+/// | `/` | search, jumping to the newest match |
+/// | `n`, `N` | jump to the next older or newer match |
+/// | `&` | only show entries containing some text |
+/// | `l` | cycle the least severe level shown |
+///
+/// Searching and filtering ignore ASCII case. While typing a query, enter
+/// keeps it and escape clears it.
+#[derive(Debug)]
+pub struct LogPanel {
+    buffer: LogBuffer,
+    level: LevelFilter,
+    filter: String,
+    search: String,
+    prompt: Option<(Prompt, StringEditor)>,
+    /// The number of rows below the view, zero while following new entries.
+    scrollback: usize,
+    /// The number of entries pushed to the buffer at the last render, to keep
+    /// the view in place while new entries arrive.
+    seen: usize,
+    width: usize,
+    height: usize,
+    focused: bool,
+}
+
+impl LogPanel {
+    pub fn new(buffer: LogBuffer) -> Self {
+        let (_, seen) = buffer.snapshot();
+        Self {
+            buffer,
+            level: LevelFilter::Trace,
+            filter: String::new(),
+            search: String::new(),
+            prompt: None,
+            scrollback: 0,
+            seen,
+            width: 0,
+            height: 0,
+            focused: false,
+        }
+    }
+
+    pub fn buffer(&self) -> &LogBuffer {
+        &self.buffer
+    }
+
+    pub fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    /// Only show entries of `level` and more severe ones.
+    pub fn set_level(&mut self, level: LevelFilter) {
+        self.level = level;
+        self.scrollback = 0;
+    }
+
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    /// Only show entries with `filter` in their message or target.
+    pub fn set_filter(&mut self, filter: impl Into<String>) {
+        self.filter = filter.into();
+        self.scrollback = 0;
+    }
+
+    pub fn search_query(&self) -> &str {
+        &self.search
+    }
+
+    /// Highlight `query` and jump to its newest match, returning `false` if
+    /// there is none.
+    pub fn search(&mut self, query: impl Into<String>) -> bool {
+        self.search = query.into();
+        self.scrollback = 0;
+        self.jump_to_match(true, true)
+    }
+
+    /// Whether new entries are scrolled into view.
+    pub fn is_following(&self) -> bool {
+        self.scrollback == 0
+    }
+
+    /// Jump to the next match of the search above the bottom of the view, or
+    /// below it if not `older`. The match at the bottom is only included if
+    /// `inclusive`.
+    fn jump_to_match(&mut self, older: bool, inclusive: bool) -> bool {
+        let (entries, _) = self.buffer.snapshot();
+        let rows = self.rows(&entries);
+        let Some(bottom) = rows.len().checked_sub(1 + self.scrollback) else {
+            return false;
+        };
+
+        let query = self.search.to_ascii_lowercase();
+        let is_match = |index: &usize| {
+            !query.is_empty() && rows[*index].line.to_ascii_lowercase().contains(&query)
+        };
+        let found = match older {
+            true => {
+                let end = bottom + inclusive as usize;
+                (0..end).rev().find(is_match)
+            }
+            false => (bottom + !inclusive as usize..rows.len()).find(is_match),
+        };
+
+        match found {
+            Some(index) => {
+                self.scrollback = rows.len() - 1 - index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The rows of the entries that pass the level and text filters.
+    fn rows<'a>(&self, entries: &'a [LogEntry]) -> Vec<Row<'a>> {
+        entries
+            .iter()
+            .filter(|entry| self.is_shown(entry))
+            .flat_map(|entry| {
+                // An empty message still gets a row for its time, level and
+                // target.
+                let empty = entry.message.is_empty().then_some("");
+                entry
+                    .message
+                    .lines()
+                    .chain(empty)
+                    .enumerate()
+                    .map(move |(index, line)| Row {
+                        entry,
+                        line,
+                        is_first: index == 0,
+                    })
+            })
+            .collect()
+    }
+
+    fn is_shown(&self, entry: &LogEntry) -> bool {
+        let filter = self.filter.to_ascii_lowercase();
+        entry.level <= self.level
+            && (filter.is_empty()
+                || entry.message.to_ascii_lowercase().contains(&filter)
+                || entry.target.to_ascii_lowercase().contains(&filter))
+    }
+
+    /// The number of rows showing entries, leaving room for the prompt.
+    fn text_height(&self) -> usize {
+        self.height
+            .saturating_sub(self.prompt.is_some() as usize)
+            .max(1)
+    }
+
+    fn scroll_by(&mut self, rows: isize, row_count: usize) {
+        let max_scrollback = row_count.saturating_sub(self.text_height());
+        self.scrollback = self
+            .scrollback
+            .saturating_add_signed(rows)
+            .min(max_scrollback);
+    }
+
+    fn handle_key(&mut self, event: &InputEvent) -> bool {
+        let shift = event.modifiers.contains(KeyModifiers::SHIFT);
+        if event
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return false;
+        }
+
+        match event.code {
+            KeyCode::Char('/') => self.prompt = Some((Prompt::Search, StringEditor::new())),
+            KeyCode::Char('&') => {
+                let filter = StringEditor::from(self.filter.as_str());
+                self.prompt = Some((Prompt::Filter, filter));
+            }
+            KeyCode::Char('n') if !shift => {
+                self.jump_to_match(true, false);
+            }
+            KeyCode::Char('N' | 'n') => {
+                self.jump_to_match(false, false);
+            }
+            KeyCode::Char('l') => {
+                let level = match self.level {
+                    LevelFilter::Trace => LevelFilter::Debug,
+                    LevelFilter::Debug => LevelFilter::Info,
+                    LevelFilter::Info => LevelFilter::Warn,
+                    LevelFilter::Warn => LevelFilter::Error,
+                    _ => LevelFilter::Trace,
+                };
+                self.set_level(level);
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn handle_prompt_key(&mut self, event: &InputEvent) -> bool {
+        let Some((prompt, editor)) = &mut self.prompt else {
+            return false;
+        };
+        let prompt = *prompt;
+
+        match event.code {
+            KeyCode::Enter => {
+                let query = editor.string().to_owned();
+                self.prompt = None;
+                match prompt {
+                    Prompt::Search => {
+                        self.search(query);
+                    }
+                    Prompt::Filter => self.set_filter(query),
+                }
+            }
+            KeyCode::Esc => {
+                self.prompt = None;
+                match prompt {
+                    Prompt::Search => self.search.clear(),
+                    Prompt::Filter => self.set_filter(""),
+                }
+            }
+            _ => {
+                if !editor.handle_key(event) {
+                    return false;
+                }
+                // Filtering is shown while typing.
+                if prompt == Prompt::Filter {
+                    self.filter = editor.string().to_owned();
+                    self.scrollback = 0;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn render_row(&self, row: &Row) -> Line {
+        let theme = Theme::current();
+        let entry = row.entry;
+        let mut line = match row.is_first {
+            true => {
+                let time = format!("{:>8.3} ", entry.time.as_secs_f64());
+                let level = format!("{:<5}", entry.level);
+                Line::styled(time, theme.muted)
+                    .with(Span::styled(level, level_style(entry.level, &theme)))
+                    .with(" ")
+                    .with(Span::styled(entry.target.as_str(), theme.muted))
+                    .with(": ")
+            }
+            // Following lines are indented past the time and level.
+            false => Line::raw(" ".repeat(15)),
+        };
+
+        let query = self.search.to_ascii_lowercase();
+        let mut rest = row.line;
+        if !query.is_empty() {
+            let lowercase = row.line.to_ascii_lowercase();
+            let mut position = 0;
+            for (start, _) in lowercase.match_indices(&query) {
+                line.push(&row.line[position..start]);
+                let found = &row.line[start..start + query.len()];
+                line.push(Span::styled(found, theme.highlight));
+                position = start + query.len();
+            }
+            rest = &row.line[position..];
+        }
+        line.push(rest);
+
+        match self.width {
+            0 => line,
+            width => line.truncate(width),
+        }
+    }
+}
+
+impl Panel for LogPanel {
+    fn tick(&mut self, key_events: &InputStream) {
+        let events: Vec<InputEvent> = key_events.iter().copied().collect();
+        for event in events {
+            let handled = match self.prompt {
+                Some(_) => self.handle_prompt_key(&event),
+                None => self.handle_key(&event),
+            };
+            if handled {
+                key_events.consume(&event);
+            }
+        }
+
+        while let Some(text) = key_events.consume_paste() {
+            if let Some((_, editor)) = &mut self.prompt {
+                editor.insert(&text);
+            }
+        }
+
+        let (entries, _) = self.buffer.snapshot();
+        let row_count = self.rows(&entries).len();
+        let page = self.text_height().saturating_sub(1).max(1) as isize;
+
+        let actions = [
+            keymap::UP,
+            keymap::DOWN,
+            keymap::PAGE_UP,
+            keymap::PAGE_DOWN,
+            keymap::HOME,
+            keymap::END,
+        ];
+        for action in consume_actions(key_events, &actions) {
+            match action {
+                keymap::UP => self.scroll_by(1, row_count),
+                keymap::DOWN => self.scroll_by(-1, row_count),
+                keymap::PAGE_UP => self.scroll_by(page, row_count),
+                keymap::PAGE_DOWN => self.scroll_by(-page, row_count),
+                keymap::HOME => self.scroll_by(isize::MAX, row_count),
+                _ => self.scrollback = 0,
+            }
+        }
+
+        while let Some(event) = key_events.consume_mouse(|event| {
+            matches!(
+                event.kind,
+                MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+            )
+        }) {
+            match event.kind {
+                MouseEventKind::ScrollUp => self.scroll_by(1, row_count),
+                _ => self.scroll_by(-1, row_count),
+            }
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let (entries, pushed) = self.buffer.snapshot();
+
+        // Keep a scrolled view in place by counting the rows added below it.
+        let new = (pushed - self.seen).min(entries.len());
+        self.seen = pushed;
+        if self.scrollback > 0 {
+            let new_entries = &entries[entries.len() - new..];
+            self.scrollback += self.rows(new_entries).len();
+        }
+
+        let rows = self.rows(&entries);
+        let height = self.text_height();
+        self.scrollback = self.scrollback.min(rows.len().saturating_sub(height));
+
+        let end = rows.len() - self.scrollback;
+        let start = end.saturating_sub(height);
+
+        let mut text: Text = rows[start..end]
+            .iter()
+            .map(|row| self.render_row(row))
+            .collect();
+
+        if let Some((prompt, editor)) = &self.prompt {
+            for _ in end - start..height {
+                text.push_line("");
+            }
+
+            let string = editor.string();
+            let cursor = editor.cursor();
+            let grapheme = string[cursor..].graphemes(true).next().unwrap_or(" ");
+            let line = Line::raw(prompt.prefix())
+                .with(&string[..cursor])
+                .with(highlight_span(grapheme, self.focused))
+                .with(&string[(cursor + grapheme.len()).min(string.len())..]);
+            text.push_line(line);
+        }
+
+        Render::from_text(text)
+    }
+
+    fn on_resize(&mut self, size: UVec2) {
+        self.width = size.x as usize;
+        self.height = size.y as usize;
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+/// How the level of an entry is styled.
+fn level_style(level: Level, theme: &Theme) -> Style {
+    match level {
+        Level::Error => theme.error,
+        Level::Warn => theme.warning,
+        Level::Info => theme.accent,
+        Level::Debug => Style::new().fg(Color::Blue),
+        Level::Trace => theme.muted,
+    }
+}
+
+mod tests {
+    #[allow(dead_code)]
+    fn keys(codes: &[crate::input::KeyCode]) -> crate::ui::InputStream {
+        use crate::input::keymap::{Keymap, KeymapState};
+        use crate::input::{InputEvent, KeyModifiers};
+
+        let mut stream = crate::ui::InputStream::from_events(
+            codes
+                .iter()
+                .map(|code| InputEvent::new(*code, KeyModifiers::NONE)),
+        );
+        stream.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+        stream
+    }
+
+    #[allow(dead_code)]
+    fn plain_lines(render: &crate::ui::Render) -> Vec<String> {
+        use ansi_parser::{AnsiParser, Output};
+
+        render
+            .lines()
+            .map(|line| {
+                line.ansi_parse()
+                    .filter_map(|block| match block {
+                        Output::TextBlock(text) => Some(text),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn log_buffer_test() {
+        use super::*;
+
+        let buffer = LogBuffer::with_capacity(3);
+        let logger = BufferLogger::new(buffer.clone(), LevelFilter::Info);
+        for i in 0..5 {
+            logger.log(
+                &Record::builder()
+                    .level(Level::Info)
+                    .target("test")
+                    .args(format_args!("message {}", i))
+                    .build(),
+            );
+        }
+        logger.log(
+            &Record::builder()
+                .level(Level::Debug)
+                .args(format_args!("too verbose"))
+                .build(),
+        );
+
+        let messages: Vec<String> = buffer
+            .entries()
+            .into_iter()
+            .map(|entry| entry.message)
+            .collect();
+        assert_eq!(messages, ["message 2", "message 3", "message 4"]);
+        assert_eq!(buffer.entries()[0].target, "test");
+    }
+
+    #[test]
+    fn log_panel_scroll_test() {
+        use super::*;
+
+        let buffer = LogBuffer::new();
+        let mut panel = LogPanel::new(buffer.clone());
+        panel.on_resize(uvec2(40, 2));
+        for i in 0..5 {
+            buffer.push(Level::Info, "app", format!("line {}", i));
+        }
+
+        let lines = plain_lines(&panel.render());
+        assert!(lines[1].ends_with("INFO  app: line 4"));
+
+        // A scrolled view stays on the same entries as new ones arrive.
+        panel.tick(&keys(&[KeyCode::Up]));
+        assert!(!panel.is_following());
+        buffer.push(Level::Warn, "app", "new");
+        let lines = plain_lines(&panel.render());
+        assert!(lines[1].ends_with("line 3"));
+
+        panel.tick(&keys(&[KeyCode::End]));
+        let lines = plain_lines(&panel.render());
+        assert!(lines[1].ends_with("WARN  app: new"));
+
+        // Entries without a message are shown as well.
+        buffer.push(Level::Info, "app", "");
+        let lines = plain_lines(&panel.render());
+        assert!(lines[1].trim_end().ends_with("INFO  app:"));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn buffer_layer_test() {
+        use super::*;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let buffer = LogBuffer::new();
+        let subscriber = tracing_subscriber::registry()
+            .with(BufferLayer::new(buffer.clone(), LevelFilter::Info));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: "render", frames = 3, "rendered {}", "suzanne");
+            tracing::debug!("too verbose");
+        });
+
+        let entries = buffer.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].level, Level::Info);
+        assert_eq!(entries[0].target, "render");
+        assert_eq!(entries[0].message, "rendered suzanne frames=3");
+    }
+
+    #[test]
+    fn log_panel_filter_test() {
+        use super::*;
+        use crate::input::TerminalEvent;
+        use crossterm::event::{Event, KeyEvent};
+
+        let buffer = LogBuffer::new();
+        let mut panel = LogPanel::new(buffer.clone());
+        panel.on_resize(uvec2(40, 10));
+        buffer.push(Level::Error, "render", "frame failed");
+        buffer.push(
+            Level::Debug,
+            "render",
+            "frame took 3 ms\nof which 1 ms shading",
+        );
+        buffer.push(Level::Info, "input", "key pressed");
+
+        assert_eq!(plain_lines(&panel.render()).len(), 4);
+
+        let key = |code| InputEvent::new(code, KeyModifiers::NONE);
+        // Typed with shift, as reported with keyboard enhancement enabled.
+        let shifted = |char| {
+            let event = KeyEvent::new(KeyCode::Char(char), KeyModifiers::SHIFT);
+            match TerminalEvent::from_crossterm(Event::Key(event)) {
+                Some(TerminalEvent::Key(event)) => event,
+                event => panic!("unexpected {:?}", event),
+            }
+        };
+        panel.tick(&InputStream::from_events([key(KeyCode::Char('l'))]));
+        assert_eq!(panel.level(), LevelFilter::Debug);
+        panel.tick(&InputStream::from_events([key(KeyCode::Char('l'))]));
+        let lines = plain_lines(&panel.render());
+        assert_eq!(lines.len(), 2);
+
+        panel.set_level(LevelFilter::Trace);
+        panel.tick(&InputStream::from_events([
+            shifted('7'),
+            shifted('r'),
+            key(KeyCode::Char('e')),
+            key(KeyCode::Char('n')),
+            key(KeyCode::Enter),
+        ]));
+        assert_eq!(panel.filter(), "Ren");
+        let lines = plain_lines(&panel.render());
+        assert_eq!(lines.len(), 3);
+        assert!(lines[2].ends_with("of which 1 ms shading"));
+
+        // Searching scrolls the match to the bottom of the view.
+        panel.set_filter("");
+        panel.on_resize(uvec2(40, 1));
+        assert!(panel.search("FRAME"));
+        let lines = plain_lines(&panel.render());
+        assert!(lines[0].ends_with("frame took 3 ms"));
+        panel.tick(&InputStream::from_events([key(KeyCode::Char('n'))]));
+        let lines = plain_lines(&panel.render());
+        assert!(lines[0].ends_with("frame failed"));
+        assert!(!panel.jump_to_match(true, false));
+    }
+
+    #[test]
+    fn log_panel_prompt_cursor_test() {
+        use super::*;
+
+        let mut panel = LogPanel::new(LogBuffer::new());
+        panel.on_resize(uvec2(40, 3));
+        panel.on_focus_change(true);
+        panel.tick(&keys(&[
+            KeyCode::Char('/'),
+            KeyCode::Char('e'),
+            KeyCode::Char('\u{301}'),
+            KeyCode::Char('x'),
+            KeyCode::Left,
+            KeyCode::Left,
+        ]));
+
+        // The cursor covers the whole grapheme, accent included.
+        let text = panel.render().to_text();
+        let prompt = text.lines.last().unwrap();
+        assert_eq!(prompt.plain(), "/e\u{301}x");
+        assert!(prompt
+            .spans
+            .iter()
+            .any(|span| span.content == "e\u{301}" && span.style == Theme::current().selection));
+    }
+}
@@ -0,0 +1,87 @@
+use super::*;
+use crate::input::KeyCode;
+use crate::math::*;
+use crate::ui::{InputStream, Line, Panel, Render};
+use std::any;
+
+/// The most items a menu shows at once before scrolling.
+const MAX_VISIBLE_ITEMS: usize = 15;
+
+/// A modal for picking one of a few items, see `Gui::open_modal`.
+///
+/// Items are picked like in a `List`, and the index of the picked item is
+/// sent to the reply. Escape closes the menu, sending `None`.
+#[derive(Debug)]
+pub struct Menu {
+    list: List,
+    closed: bool,
+    reply: Reply<Option<usize>>,
+}
+
+impl Menu {
+    pub fn new(
+        items: impl IntoIterator<Item = impl Into<Line>>,
+        reply: Reply<Option<usize>>,
+    ) -> Self {
+        Self {
+            list: List::new(items),
+            closed: false,
+            reply,
+        }
+    }
+
+    pub fn list(&self) -> &List {
+        &self.list
+    }
+
+    fn close(&mut self, picked: Option<usize>) {
+        if !self.closed {
+            self.closed = true;
+            self.reply.send(picked);
+        }
+    }
+}
+
+impl Panel for Menu {
+    fn tick(&mut self, key_events: &InputStream) {
+        self.list.tick(key_events);
+
+        if let Some(index) = self.list.take_activated() {
+            self.close(Some(index));
+        }
+        if key_events.consume_key_code(KeyCode::Esc) {
+            self.close(None);
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        self.list.render()
+    }
+
+    fn on_resize(&mut self, size: UVec2) {
+        self.list.on_resize(size);
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.list.on_focus_change(focused);
+    }
+
+    fn preferred_size(&self) -> Option<UVec2> {
+        let items = self.list.items();
+        let width = items.iter().map(Line::width).max();
+        let height = items.len().clamp(1, MAX_VISIBLE_ITEMS);
+
+        Some(uvec2(width.unwrap_or(0) as u32, height as u32))
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
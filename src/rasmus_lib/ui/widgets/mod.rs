@@ -0,0 +1,129 @@
+//! Panels for building forms, like text inputs and buttons, and the dialogs
+//! shown with `Gui::open_modal`.
+//!
+//! Widgets never block: they handle the input of a tick in `Panel::tick` and
+//! only keep track of what happened, like a button being pressed. Tickers and
+//! the code driving the gui poll for that with methods like
+//! `Button::take_pressed`. Dialogs send their result to a `Reply` instead.
+//!
+//! Widgets are controlled with the `ui.*` actions of the keymap, see
+//! `keymap::DEFAULT_KEYMAP`, and only consume the keys they use, so focus can
+//! still be moved with tab.
+
+mod button;
+mod checkbox;
+mod confirm_dialog;
+mod list;
+mod log_panel;
+mod menu;
+mod palette;
+mod progress_bar;
+mod reply;
+mod slider;
+mod table;
+mod text_editor;
+mod text_input;
+mod tree;
+
+pub use button::*;
+pub use checkbox::*;
+pub use confirm_dialog::*;
+pub use list::*;
+pub use log_panel::*;
+pub use menu::*;
+pub use palette::*;
+pub use progress_bar::*;
+pub use reply::*;
+pub use slider::*;
+pub use table::*;
+pub use text_editor::*;
+pub use text_input::*;
+pub use tree::*;
+
+use crate::ansi_term::*;
+use crate::input::{MouseButton, MouseEvent, MouseEventKind};
+use crate::ui::{InputStream, Span, Theme};
+
+/// `text` in the selection style of the theme while `highlighted`, the way
+/// widgets show focus.
+fn highlight_span(text: &str, highlighted: bool) -> Span {
+    match highlighted {
+        true => Span::styled(text, Theme::current().selection),
+        false => Span::raw(text),
+    }
+}
+
+/// Consume every unconsumed occurrence of `actions`, returning them in the
+/// order they were triggered.
+fn consume_actions<'a>(key_events: &InputStream, actions: &[&'a str]) -> Vec<&'a str> {
+    let triggered: Vec<&'a str> = key_events
+        .actions()
+        .filter_map(|action| actions.iter().find(|other| **other == action).copied())
+        .collect();
+
+    for action in &triggered {
+        key_events.consume_action(action);
+    }
+    triggered
+}
+
+fn is_left_press(event: &MouseEvent) -> bool {
+    event.kind == MouseEventKind::Down(MouseButton::Left)
+}
+
+mod tests {
+    #[test]
+    fn headless_form_test() {
+        use super::*;
+        use crate::input::ScriptedInputSource;
+        use crate::math::*;
+        use crate::ui::{Constraint, Gui, Layout};
+
+        let source = ScriptedInputSource::parse(
+            "text hi there\nkey backspace\nkey enter\ntick\n\
+             key tab\ntick\nkey space\ntick\n\
+             key tab\ntick\nkey space\ntick\n\
+             key tab\ntick\nkey down\nkey enter\n",
+        )
+        .unwrap();
+        let mut gui = Gui::headless(uvec2(30, 6), source);
+        let input = gui.add_panel(uvec2(1, 1), TextInput::new());
+        let checkbox = gui.add_panel(uvec2(1, 1), Checkbox::new("Wireframe"));
+        let button = gui.add_panel(uvec2(1, 1), Button::new("Apply"));
+        let list = gui.add_panel(
+            uvec2(1, 1),
+            List::new(vec!["Cube".to_owned(), "Suzanne".to_owned()]),
+        );
+        gui.set_layout(Layout::vertical(vec![
+            (Constraint::Fixed(1), Layout::panel(input)),
+            (Constraint::Fixed(1), Layout::panel(checkbox)),
+            (Constraint::Fixed(1), Layout::panel(button)),
+            (Constraint::Flex(1), Layout::panel(list)),
+        ]));
+
+        gui.tick();
+        let text_input = gui.get_panel_mut::<TextInput>(&input).unwrap();
+        assert_eq!(text_input.take_submitted().as_deref(), Some("hi ther"));
+
+        // Keys go to the panel focused at the start of the tick.
+        gui.tick();
+        assert_eq!(gui.focused(), Some(checkbox));
+        gui.tick();
+        let checkbox = gui.get_panel_mut::<Checkbox>(&checkbox).unwrap();
+        assert!(checkbox.is_checked());
+        assert!(checkbox.take_changed());
+
+        gui.tick();
+        gui.tick();
+        assert!(gui.get_panel_mut::<Button>(&button).unwrap().take_pressed());
+
+        gui.tick();
+        gui.tick();
+        let list = gui.get_panel_mut::<List>(&list).unwrap();
+        assert_eq!(list.take_activated(), Some(1));
+        assert_eq!(
+            list.selected_item().map(|item| item.plain()).as_deref(),
+            Some("Suzanne")
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use super::*;
+use crate::input::keymap;
+use crate::input::{InputEvent, KeyCode};
+use crate::math::*;
+use crate::ui::{InputStream, Line, Panel, Render, Span, Style, Text, Theme};
+use crate::utils::StrUtils;
+use std::any;
+
+/// The most items a palette shows at once before scrolling.
+const MAX_VISIBLE_ITEMS: usize = 12;
+/// The narrowest a palette likes to be, leaving room for typing.
+const MIN_WIDTH: usize = 40;
+const PROMPT: &str = "> ";
+
+/// How well a query matches some text, see `fuzzy_match`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    /// Higher is better.
+    pub score: i32,
+    /// Byte positions of the matched characters in the text.
+    pub positions: Vec<usize>,
+}
+
+/// Match the characters of `query` in order against `text`, ignoring case,
+/// like `rc` matching "Reset camera". Returns `None` if some character can't
+/// be matched.
+///
+/// Matches at the start of words and runs of consecutive characters score
+/// higher, while skipped characters score lower.
+///
+/// ```
+/// use terminal_renderer::ui::fuzzy_match;
+///
+/// let camera = fuzzy_match("rc", "Reset camera").unwrap();
+/// let scene = fuzzy_match("rc", "Record scene").unwrap();
+/// assert_eq!(camera.positions, [0, 6]);
+/// assert!(camera.score > scene.score);
+/// assert!(fuzzy_match("cr", "Reset").is_none());
+/// ```
+pub fn fuzzy_match(query: &str, text: &str) -> Option<FuzzyMatch> {
+    const WORD_START_BONUS: i32 = 8;
+    const CONSECUTIVE_BONUS: i32 = 5;
+    const MAX_GAP_PENALTY: i32 = 3;
+
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut positions = Vec::new();
+    let mut previous: Option<char> = None;
+    // Characters skipped since the last match.
+    let mut gap = 0;
+
+    for (position, char) in text.char_indices() {
+        let Some(wanted) = query.peek() else {
+            break;
+        };
+
+        if char.to_lowercase().eq([*wanted]) {
+            let is_word_start = match previous {
+                None => true,
+                Some(previous) => {
+                    !previous.is_alphanumeric() || (previous.is_lowercase() && char.is_uppercase())
+                }
+            };
+
+            score += 1;
+            if is_word_start {
+                score += WORD_START_BONUS;
+            }
+            if gap == 0 && !positions.is_empty() {
+                score += CONSECUTIVE_BONUS;
+            }
+            score -= gap.min(MAX_GAP_PENALTY);
+
+            positions.push(position);
+            query.next();
+            gap = 0;
+        } else {
+            gap += 1;
+        }
+        previous = Some(char);
+    }
+
+    if query.peek().is_some() {
+        return None;
+    }
+    Some(FuzzyMatch { score, positions })
+}
+
+/// A modal for picking an item by typing part of it, see `Gui::open_modal`.
+///
+/// Items are matched with `fuzzy_match` and listed best match first, with
+/// the matched characters highlighted. Up and down choose between them, and
+/// enter or a click picks one, sending its index in the items to the reply.
+/// Escape closes the palette, sending `None`.
+///
+/// Items can have a hint shown next to them, like the keys bound to a
+/// command.
+#[derive(Debug)]
+pub struct Palette {
+    input: TextInput,
+    items: Vec<String>,
+    hints: Vec<String>,
+    /// Indices of the items matching the query, in the order they're listed.
+    matches: Vec<usize>,
+    /// The matched positions of every listed item.
+    positions: Vec<Vec<usize>>,
+    list: List,
+    width: usize,
+    closed: bool,
+    reply: Reply<Option<usize>>,
+}
+
+impl Palette {
+    pub fn new(items: Vec<String>, reply: Reply<Option<usize>>) -> Self {
+        let mut palette = Self {
+            input: TextInput::new(),
+            items,
+            hints: Vec::new(),
+            matches: Vec::new(),
+            positions: Vec::new(),
+            list: List::default(),
+            width: 0,
+            closed: false,
+            reply,
+        };
+        palette.update_matches();
+        palette
+    }
+
+    pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.input = self.input.with_placeholder(placeholder);
+        self
+    }
+
+    /// Hints shown right aligned next to the items with the same index.
+    pub fn with_hints(mut self, hints: Vec<String>) -> Self {
+        self.hints = hints;
+        self.update_list();
+        self
+    }
+
+    pub fn query(&self) -> &str {
+        self.input.text()
+    }
+
+    pub fn set_query(&mut self, query: &str) {
+        self.input.set_text(query);
+        self.update_matches();
+    }
+
+    /// Indices of the items matching the query, in the order they're listed.
+    pub fn matches(&self) -> &[usize] {
+        &self.matches
+    }
+
+    fn update_matches(&mut self) {
+        let mut matches: Vec<(usize, FuzzyMatch)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| Some((index, fuzzy_match(self.query(), item)?)))
+            .collect();
+        // Stable, so equally good matches keep their order.
+        matches.sort_by_key(|(_, fuzzy)| -fuzzy.score);
+
+        (self.matches, self.positions) = matches
+            .into_iter()
+            .map(|(index, fuzzy)| (index, fuzzy.positions))
+            .unzip();
+
+        self.update_list();
+        self.list.select(0);
+    }
+
+    /// Fill the list with the matching items, highlighting the matched
+    /// characters and aligning the hints to the right.
+    fn update_list(&mut self) {
+        let theme = Theme::current();
+        let labels = self
+            .matches
+            .iter()
+            .zip(&self.positions)
+            .map(|(index, positions)| {
+                let item = &self.items[*index];
+                let hint = self.hints.get(*index).map_or("", String::as_str);
+
+                let mut label = Line::new();
+                for (position, char) in item.char_indices() {
+                    let style = match positions.contains(&position) {
+                        true => theme.accent,
+                        false => Style::new(),
+                    };
+                    label.push(Span::styled(char, style));
+                }
+
+                if hint.is_empty() {
+                    return label;
+                }
+                let hint_width = hint.visual_len();
+                let label_width = self.width.saturating_sub(hint_width + 1);
+                let mut label = label.truncate(label_width);
+                let padding = self.width.saturating_sub(label.width() + hint_width);
+                label.push(" ".repeat(padding));
+                label.with(Span::styled(hint, theme.muted))
+            });
+
+        let labels: Vec<Line> = labels.collect();
+        self.list.set_items(labels);
+    }
+
+    fn close(&mut self, picked: Option<usize>) {
+        if !self.closed {
+            self.closed = true;
+            self.reply.send(picked);
+        }
+    }
+
+    /// Handle a key, returning whether it was used.
+    fn handle_key(&mut self, event: &InputEvent) -> bool {
+        match event.code {
+            KeyCode::Esc => self.close(None),
+            KeyCode::Enter => {
+                let picked = self.list.selected().map(|index| self.matches[index]);
+                self.close(picked);
+            }
+            _ => {
+                let query = self.query().to_owned();
+                if !self.input.editor_mut().handle_key(event) {
+                    return false;
+                }
+                if self.query() != query {
+                    self.update_matches();
+                }
+            }
+        }
+
+        true
+    }
+}
+
+impl Panel for Palette {
+    fn tick(&mut self, key_events: &InputStream) {
+        let page = MAX_VISIBLE_ITEMS - 1;
+        let actions = [keymap::UP, keymap::DOWN, keymap::PAGE_UP, keymap::PAGE_DOWN];
+        for action in consume_actions(key_events, &actions) {
+            let selected = self.list.selected().unwrap_or(0);
+            match action {
+                keymap::UP => self.list.select(selected.saturating_sub(1)),
+                keymap::DOWN => self.list.select(selected + 1),
+                keymap::PAGE_UP => self.list.select(selected.saturating_sub(page)),
+                _ => self.list.select(selected + page),
+            }
+        }
+
+        let events: Vec<InputEvent> = key_events.iter().copied().collect();
+        for event in events {
+            if self.handle_key(&event) {
+                key_events.consume(&event);
+            }
+        }
+
+        while let Some(text) = key_events.consume_paste() {
+            self.input.editor_mut().insert(&text);
+            self.update_matches();
+        }
+
+        // The list starts below the query.
+        let list_events = key_events.derive(false, |event| {
+            (event.position.y >= 1).then(|| event.relative_to(ivec2(0, 1)))
+        });
+        self.list.tick(&list_events);
+        if let Some(index) = self.list.take_activated() {
+            self.close(Some(self.matches[index]));
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let mut query = Line::styled(PROMPT, Style::new().bold());
+        if let Some(line) = self.input.render().to_text().lines.pop() {
+            query.spans.extend(line.spans);
+        }
+        let mut text = Text::from(query);
+
+        if self.matches.is_empty() {
+            text.push_line(Line::styled("No matches", Theme::current().muted));
+        }
+        text.extend(self.list.render().to_text());
+
+        Render::from_text(text)
+    }
+
+    fn on_resize(&mut self, size: UVec2) {
+        self.width = size.x as usize;
+        let prompt_width = PROMPT.visual_len() as u32;
+        self.input
+            .on_resize(uvec2(size.x.saturating_sub(prompt_width), 1));
+        self.list.on_resize(uvec2(size.x, size.y.saturating_sub(1)));
+        self.update_list();
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.input.on_focus_change(focused);
+        self.list.on_focus_change(focused);
+    }
+
+    fn preferred_size(&self) -> Option<UVec2> {
+        let hint_width = |index| {
+            self.hints
+                .get(index)
+                .map_or(0, |hint: &String| hint.visual_len() + 1)
+        };
+        let width = (0..self.items.len())
+            .map(|index| self.items[index].visual_len() + hint_width(index))
+            .max();
+        let width = width.unwrap_or(0).max(MIN_WIDTH);
+        let height = 1 + self.items.len().clamp(1, MAX_VISIBLE_ITEMS);
+
+        Some(uvec2(width as u32, height as u32))
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn palette_ranking_test() {
+        use super::*;
+        use crate::input::keymap::{Keymap, KeymapState};
+        use crate::input::KeyModifiers;
+
+        let items = [
+            "Load mesh",
+            "Toggle wireframe",
+            "Reset camera",
+            "Record scene",
+        ];
+        let (reply, picked) = Reply::channel();
+        let mut palette = Palette::new(items.iter().map(|item| item.to_string()).collect(), reply)
+            .with_hints(vec!["ctrl+o".to_owned()]);
+        palette.on_resize(uvec2(30, 5));
+        assert_eq!(palette.matches(), [0, 1, 2, 3]);
+
+        let typed = "rc"
+            .chars()
+            .map(|char| InputEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+        palette.tick(&InputStream::from_events(typed));
+        assert_eq!(palette.matches(), [2, 3]);
+
+        let text = palette.render().to_text();
+        assert_eq!(text.lines[1].plain().trim_end(), "Reset camera");
+        let accent = Theme::current().accent;
+        assert_eq!(text.lines[1].spans[0], Span::styled("R", accent));
+
+        // The hint is aligned to the right edge.
+        palette.set_query("");
+        let text = palette.render().to_text();
+        let first_item = &text.lines[1];
+        assert_eq!(first_item.width(), 30);
+        assert_eq!(
+            first_item.spans.last(),
+            Some(&Span::styled("ctrl+o", Theme::current().muted))
+        );
+
+        let mut keys = InputStream::from_events([
+            InputEvent::new(KeyCode::Down, KeyModifiers::NONE),
+            InputEvent::new(KeyCode::Enter, KeyModifiers::NONE),
+        ]);
+        keys.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+        palette.tick(&keys);
+        assert!(palette.is_closed());
+        assert_eq!(picked.try_recv(), Ok(Some(1)));
+    }
+}
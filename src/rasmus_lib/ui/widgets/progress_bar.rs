@@ -0,0 +1,100 @@
+use crate::math::*;
+use crate::ui::{Line, Panel, Render};
+use crate::utils::StrUtils;
+use std::any;
+
+/// The width of the bar if it hasn't been given a size.
+const DEFAULT_BAR_WIDTH: usize = 20;
+
+/// Blocks filling a cell in steps of an eighth.
+const PARTIAL_BLOCKS: [&str; 8] = [" ", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+
+/// A bar showing how far along some work is, followed by the percentage.
+/// It can't be focused.
+#[derive(Debug, Default)]
+pub struct ProgressBar {
+    label: String,
+    progress: f32,
+    width: usize,
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    /// Set the progress, from 0 to 1.
+    pub fn set_progress(&mut self, progress: f32) {
+        self.progress = progress.clamp(0., 1.);
+    }
+}
+
+impl Panel for ProgressBar {
+    fn focusable(&self) -> bool {
+        false
+    }
+
+    fn render(&mut self) -> Render {
+        let label_width = match self.label.visual_len() {
+            0 => 0,
+            width => width + 1,
+        };
+        // The brackets and " 100%".
+        let bar_width = match self.width {
+            0 => DEFAULT_BAR_WIDTH,
+            width => width.saturating_sub(label_width + 7).max(1),
+        };
+
+        let eighths = (self.progress * (bar_width * 8) as f32).round() as usize;
+        let full = eighths / 8;
+
+        let mut bar = "█".repeat(full);
+        if full < bar_width {
+            bar.push_str(PARTIAL_BLOCKS[eighths % 8]);
+            bar.push_str(&" ".repeat(bar_width - full - 1));
+        }
+
+        let mut line = Line::new();
+        if !self.label.is_empty() {
+            line.push(format!("{} ", self.label));
+        }
+        line.push(format!("[{}] {:>3}%", bar, (self.progress * 100.).round()));
+
+        Render::from_text(line)
+    }
+
+    fn on_resize(&mut self, size: UVec2) {
+        self.width = size.x as usize;
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn progress_bar_test() {
+        use super::*;
+
+        let mut bar = ProgressBar::new();
+        bar.on_resize(uvec2(12, 1));
+
+        bar.set_progress(0.5);
+        assert_eq!(bar.render().lines().next(), Some("[██▌  ]  50%"));
+        bar.set_progress(2.);
+        assert_eq!(bar.render().lines().next(), Some("[█████] 100%"));
+    }
+}
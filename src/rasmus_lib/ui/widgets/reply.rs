@@ -0,0 +1,48 @@
+use std::fmt;
+use std::sync::mpsc;
+
+/// Where a modal sends its result when it closes: to a callback, or over a
+/// channel that a ticker can poll with `try_recv`. Neither blocks the gui.
+#[derive(Default)]
+pub enum Reply<T> {
+    /// The result is dropped.
+    #[default]
+    Ignore,
+    Callback(Box<dyn FnOnce(T)>),
+    Channel(mpsc::Sender<T>),
+}
+
+impl<T> Reply<T> {
+    pub fn callback(callback: impl FnOnce(T) + 'static) -> Self {
+        Self::Callback(Box::new(callback))
+    }
+
+    /// A reply sent over a new channel, along with its receiving end.
+    pub fn channel() -> (Self, mpsc::Receiver<T>) {
+        let (sender, receiver) = mpsc::channel();
+        (Self::Channel(sender), receiver)
+    }
+
+    /// Send `value`, if nothing has been sent yet. Later values are dropped.
+    pub fn send(&mut self, value: T) {
+        match std::mem::take(self) {
+            Reply::Ignore => {}
+            Reply::Callback(callback) => callback(value),
+            // The receiver not being around anymore means nobody is
+            // interested in the result.
+            Reply::Channel(sender) => {
+                let _ = sender.send(value);
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Reply<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reply::Ignore => write!(f, "Ignore"),
+            Reply::Callback(_) => write!(f, "Callback"),
+            Reply::Channel(_) => write!(f, "Channel"),
+        }
+    }
+}
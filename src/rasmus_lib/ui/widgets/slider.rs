@@ -0,0 +1,228 @@
+use super::*;
+use crate::input::keymap;
+use crate::math::*;
+use crate::ui::{InputStream, Line, Panel, Render};
+use crate::utils::StrUtils;
+use std::any;
+
+/// The width of the bar if the slider hasn't been given a size.
+const DEFAULT_BAR_WIDTH: usize = 20;
+
+/// A value between `min` and `max`, changed in steps with `ui.left` and
+/// `ui.right`, jumping to the ends with `ui.home` and `ui.end`, or by clicking
+/// and dragging along the bar.
+#[derive(Debug)]
+pub struct Slider {
+    label: String,
+    value: f32,
+    min: f32,
+    max: f32,
+    step: f32,
+    /// Digits shown after the decimal point.
+    precision: usize,
+    width: usize,
+    focused: bool,
+    changed: bool,
+}
+
+impl Slider {
+    /// A slider starting at `min`, moving a twentieth of the range per step.
+    /// The bounds are swapped if `min` is larger than `max`.
+    pub fn new(min: f32, max: f32) -> Self {
+        let (min, max) = (min.min(max), min.max(max));
+        Self {
+            label: String::new(),
+            value: min,
+            min,
+            max,
+            step: (max - min) / 20.,
+            precision: 2,
+            width: 0,
+            focused: false,
+            changed: false,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    pub fn with_value(mut self, value: f32) -> Self {
+        self.set_value(value);
+        self
+    }
+
+    pub fn with_step(mut self, step: f32) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Set the value, clamped to the range of the slider. This doesn't count
+    /// as a change for `take_changed`.
+    pub fn set_value(&mut self, value: f32) {
+        self.value = value.clamp(self.min, self.max);
+    }
+
+    /// Whether the value was changed by the keyboard or mouse since the last
+    /// call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    fn change_to(&mut self, value: f32) {
+        let previous = self.value;
+        self.set_value(value);
+        self.changed |= self.value != previous;
+    }
+
+    fn format_value(&self, value: f32) -> String {
+        format!("{:.*}", self.precision, value)
+    }
+
+    /// The column the bar starts at and its width.
+    fn bar_layout(&self) -> (usize, usize) {
+        let label_width = match self.label.visual_len() {
+            0 => 0,
+            width => width + 1,
+        };
+        // Wide enough for any value, so the bar doesn't jump around.
+        let value_width = 1 + self
+            .format_value(self.min)
+            .len()
+            .max(self.format_value(self.max).len());
+
+        let bar_width = match self.width {
+            0 => DEFAULT_BAR_WIDTH,
+            width => width.saturating_sub(label_width + value_width).max(2),
+        };
+        (label_width, bar_width)
+    }
+
+    /// How far the value is along the range, from 0 to 1.
+    fn fraction(&self) -> f32 {
+        if self.max > self.min {
+            (self.value - self.min) / (self.max - self.min)
+        } else {
+            0.
+        }
+    }
+}
+
+impl Panel for Slider {
+    fn tick(&mut self, key_events: &InputStream) {
+        let actions = [keymap::LEFT, keymap::RIGHT, keymap::HOME, keymap::END];
+        for action in consume_actions(key_events, &actions) {
+            let value = match action {
+                keymap::LEFT => self.value - self.step,
+                keymap::RIGHT => self.value + self.step,
+                keymap::HOME => self.min,
+                _ => self.max,
+            };
+            self.change_to(value);
+        }
+
+        let (bar_start, bar_width) = self.bar_layout();
+        while let Some(event) = key_events.consume_mouse(|event| {
+            matches!(
+                event.kind,
+                MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Drag(MouseButton::Left)
+            )
+        }) {
+            let column = event.position.x - bar_start as i32;
+            let fraction = (column as f32 / (bar_width - 1) as f32).clamp(0., 1.);
+            let mut value = self.min + fraction * (self.max - self.min);
+            if self.step > 0. {
+                value = self.min + ((value - self.min) / self.step).round() * self.step;
+            }
+            self.change_to(value);
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let (_, bar_width) = self.bar_layout();
+        let knob = (self.fraction() * (bar_width - 1) as f32).round() as usize;
+
+        let mut line = Line::new();
+        if !self.label.is_empty() {
+            line.push(format!("{} ", self.label));
+        }
+        line.push("━".repeat(knob));
+        line.push(highlight_span("●", self.focused));
+        line.push("─".repeat(bar_width - 1 - knob));
+        line.push(format!(" {}", self.format_value(self.value)));
+
+        Render::from_text(line)
+    }
+
+    fn on_resize(&mut self, size: UVec2) {
+        self.width = size.x as usize;
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn slider_test() {
+        use super::*;
+        use crate::input::keymap::{Keymap, KeymapState};
+        use crate::input::{InputEvent, KeyCode, KeyModifiers, MouseEvent};
+
+        let mut slider = Slider::new(0., 1.).with_label("Fov").with_step(0.25);
+        slider.on_resize(uvec2(14, 1));
+
+        let mut stream = InputStream::from_events(
+            [
+                KeyCode::Right,
+                KeyCode::Right,
+                KeyCode::Right,
+                KeyCode::Left,
+            ]
+            .map(|code| InputEvent::new(code, KeyModifiers::NONE)),
+        );
+        stream.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+        slider.tick(&stream);
+        assert_eq!(slider.value(), 0.5);
+        assert!(slider.take_changed());
+        assert!(!slider.take_changed());
+        assert_eq!(Slider::new(1., 0.).with_value(2.).value(), 1.);
+
+        // "Fov " + 5 cells of bar + " 0.50"
+        let render = slider.render();
+        assert_eq!(render.lines().next(), Some("Fov ━━●── 0.50"));
+
+        // Dragging to the end of the bar, and past it.
+        let drag = |x| {
+            MouseEvent::new(
+                MouseEventKind::Drag(MouseButton::Left),
+                ivec2(x, 0),
+                KeyModifiers::NONE,
+            )
+        };
+        slider.tick(&InputStream::from_mouse_events([drag(8)]));
+        assert_eq!(slider.value(), 1.);
+        slider.tick(&InputStream::from_mouse_events([drag(5)]));
+        assert_eq!(slider.value(), 0.25);
+        slider.tick(&InputStream::from_mouse_events([drag(-3)]));
+        assert_eq!(slider.value(), 0.);
+    }
+}
@@ -0,0 +1,435 @@
+use super::*;
+use crate::input::keymap;
+use crate::input::KeyCode;
+use crate::math::*;
+use crate::ui::{Alignment, InputStream, Line, Panel, Render, Style, Text, Theme};
+use crate::utils::StrUtils;
+use std::any;
+use std::cmp::Ordering;
+
+/// The space between columns.
+const COLUMN_GAP: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// A column of a `Table`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub title: String,
+    /// The width of the cells, not counting the gap between columns.
+    pub width: usize,
+    pub alignment: Alignment,
+}
+
+impl Column {
+    pub fn new(title: impl Into<String>, width: usize) -> Self {
+        Self {
+            title: title.into(),
+            width,
+            alignment: Alignment::Left,
+        }
+    }
+
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+}
+
+/// Rows of cells under a header, which can be sorted by a column.
+///
+/// Rows are selected like the items of a `List`, and activating the selected
+/// row can be picked up with `take_activated`. `ui.left` and `ui.right`
+/// choose a column, which `s` sorts by and `<` and `>` make narrower and
+/// wider. Clicking a column title sorts by it, clicking it again reverses
+/// the order.
+///
+/// Only the visible rows are laid out when rendering, so tables can have many
+/// more rows than fit on the screen.
+#[derive(Debug, Default)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<Line>>,
+    /// Indices of the rows, in the order they're shown.
+    order: Vec<usize>,
+    sort: Option<(usize, SortOrder)>,
+    /// The position of the selected row in `order`.
+    selected: Option<usize>,
+    selected_column: usize,
+    /// The position in `order` of the first visible row.
+    offset: usize,
+    width: usize,
+    height: usize,
+    focused: bool,
+    activated: Option<usize>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_rows<R, C>(mut self, rows: impl IntoIterator<Item = R>) -> Self
+    where
+        R: IntoIterator<Item = C>,
+        C: Into<Line>,
+    {
+        self.set_rows(rows);
+        self
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// The rows, in the order they were given.
+    pub fn rows(&self) -> &[Vec<Line>] {
+        &self.rows
+    }
+
+    /// Replace the rows, keeping them sorted. The row with the same index as
+    /// the selected one stays selected if there is one.
+    pub fn set_rows<R, C>(&mut self, rows: impl IntoIterator<Item = R>)
+    where
+        R: IntoIterator<Item = C>,
+        C: Into<Line>,
+    {
+        let selected_row = self.selected_row();
+        self.rows = rows
+            .into_iter()
+            .map(|row| row.into_iter().map(Into::into).collect())
+            .collect();
+        self.order = (0..self.rows.len()).collect();
+        self.apply_sort();
+
+        self.selected = None;
+        match selected_row {
+            Some(row) if row < self.rows.len() => self.select_row(row),
+            _ if !self.rows.is_empty() => self.select(0),
+            _ => {}
+        }
+        self.offset = self.offset.min(self.max_offset());
+    }
+
+    pub fn set_column_width(&mut self, column: usize, width: usize) {
+        if let Some(column) = self.columns.get_mut(column) {
+            column.width = width.max(1);
+        }
+    }
+
+    /// The column and order the rows are sorted by, if they're sorted.
+    pub fn sort(&self) -> Option<(usize, SortOrder)> {
+        self.sort
+    }
+
+    /// Sort the rows by the text of their cells in `column`, comparing
+    /// numbers by their value. `None` shows them in the order they were
+    /// given. The selected row stays selected.
+    pub fn sort_by(&mut self, sort: Option<(usize, SortOrder)>) {
+        let selected_row = self.selected_row();
+        self.sort = sort.filter(|(column, _)| *column < self.columns.len());
+        self.order = (0..self.rows.len()).collect();
+        self.apply_sort();
+
+        if let Some(row) = selected_row {
+            self.select_row(row);
+        }
+    }
+
+    /// The index of the selected row in `rows`.
+    pub fn selected_row(&self) -> Option<usize> {
+        Some(self.order[self.selected?])
+    }
+
+    /// Select the row with index `row` in `rows`, scrolling it into view.
+    pub fn select_row(&mut self, row: usize) {
+        if let Some(position) = self.order.iter().position(|index| *index == row) {
+            self.select(position);
+        }
+    }
+
+    /// The column chosen for sorting and resizing with keys.
+    pub fn selected_column(&self) -> usize {
+        self.selected_column
+    }
+
+    /// The index in `rows` of the row activated since the last call, if any.
+    pub fn take_activated(&mut self) -> Option<usize> {
+        self.activated.take()
+    }
+
+    /// Select the row shown at `position`, clamped to the last row.
+    fn select(&mut self, position: usize) {
+        if self.order.is_empty() {
+            return;
+        }
+
+        let position = position.min(self.order.len() - 1);
+        self.selected = Some(position);
+
+        let height = self.body_height();
+        if position < self.offset {
+            self.offset = position;
+        } else if position >= self.offset + height {
+            self.offset = position + 1 - height;
+        }
+    }
+
+    fn apply_sort(&mut self) {
+        let Some((column, sort_order)) = self.sort else {
+            return;
+        };
+
+        let rows = &self.rows;
+        let key = |row: usize| rows[row].get(column).map(Line::plain).unwrap_or_default();
+        self.order.sort_by(|a, b| {
+            let ordering = compare_cells(&key(*a), &key(*b));
+            match sort_order {
+                SortOrder::Ascending => ordering,
+                SortOrder::Descending => ordering.reverse(),
+            }
+        });
+    }
+
+    /// Sort by `column`, or reverse the order if it's sorted by it already.
+    fn toggle_sort(&mut self, column: usize) {
+        let sort_order = match self.sort {
+            Some((sorted, SortOrder::Ascending)) if sorted == column => SortOrder::Descending,
+            _ => SortOrder::Ascending,
+        };
+        self.sort_by(Some((column, sort_order)));
+    }
+
+    /// The column at `x`, counting the gap after a column as part of it.
+    fn column_at(&self, x: usize) -> Option<usize> {
+        let mut right = 0;
+        self.columns.iter().position(|column| {
+            right += column.width + COLUMN_GAP;
+            x < right
+        })
+    }
+
+    /// The number of visible rows below the header, at least one.
+    fn body_height(&self) -> usize {
+        self.height.saturating_sub(1).max(1)
+    }
+
+    fn max_offset(&self) -> usize {
+        self.order.len().saturating_sub(self.body_height())
+    }
+
+    fn render_row(&self, cells: impl Iterator<Item = Line>) -> Line {
+        let mut line = Line::new();
+        for (index, (cell, column)) in cells.zip(&self.columns).enumerate() {
+            if index > 0 {
+                line.push(" ".repeat(COLUMN_GAP));
+            }
+            line.spans
+                .extend(cell.aligned(column.width, column.alignment).into_spans());
+        }
+        line.truncate(self.width)
+    }
+}
+
+/// Numbers are compared by their value, and come before other text.
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl Panel for Table {
+    fn tick(&mut self, key_events: &InputStream) {
+        let page = (self.body_height() - 1).max(1);
+
+        let actions = [
+            keymap::UP,
+            keymap::DOWN,
+            keymap::LEFT,
+            keymap::RIGHT,
+            keymap::PAGE_UP,
+            keymap::PAGE_DOWN,
+            keymap::HOME,
+            keymap::END,
+            keymap::ACTIVATE,
+        ];
+        for action in consume_actions(key_events, &actions) {
+            let selected = self.selected.unwrap_or(0);
+            let last_column = self.columns.len().saturating_sub(1);
+            match action {
+                keymap::UP => self.select(selected.saturating_sub(1)),
+                keymap::DOWN => self.select(selected + 1),
+                keymap::LEFT => self.selected_column = self.selected_column.saturating_sub(1),
+                keymap::RIGHT => self.selected_column = (self.selected_column + 1).min(last_column),
+                keymap::PAGE_UP => self.select(selected.saturating_sub(page)),
+                keymap::PAGE_DOWN => self.select(selected + page),
+                keymap::HOME => self.select(0),
+                keymap::END => self.select(usize::MAX),
+                _ => self.activated = self.selected_row(),
+            }
+        }
+
+        let column = self.selected_column;
+        if key_events.consume_key_code(KeyCode::Char('s')) {
+            self.toggle_sort(column);
+        }
+        while key_events.consume_key_code(KeyCode::Char('<')) {
+            let width = self.columns.get(column).map_or(1, |column| column.width);
+            self.set_column_width(column, width.saturating_sub(1));
+        }
+        while key_events.consume_key_code(KeyCode::Char('>')) {
+            let width = self.columns.get(column).map_or(1, |column| column.width);
+            self.set_column_width(column, width + 1);
+        }
+
+        while let Some(event) = key_events.consume_mouse(|event| {
+            is_left_press(event)
+                || matches!(
+                    event.kind,
+                    MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+                )
+        }) {
+            match event.kind {
+                MouseEventKind::ScrollUp => self.offset = self.offset.saturating_sub(1),
+                MouseEventKind::ScrollDown => {
+                    self.offset = (self.offset + 1).min(self.max_offset())
+                }
+                _ if event.position.y == 0 => {
+                    if let Some(column) = self.column_at(event.position.x.max(0) as usize) {
+                        self.selected_column = column;
+                        self.toggle_sort(column);
+                    }
+                }
+                _ => {
+                    let position = self.offset + event.position.y.max(1) as usize - 1;
+                    if position < self.order.len() {
+                        if self.selected == Some(position) {
+                            self.activated = self.selected_row();
+                        }
+                        self.select(position);
+                    }
+                }
+            }
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let theme = Theme::current();
+
+        let titles = self.columns.iter().enumerate().map(|(index, column)| {
+            let indicator = match self.sort {
+                Some((sorted, SortOrder::Ascending)) if sorted == index => " ▲",
+                Some((sorted, SortOrder::Descending)) if sorted == index => " ▼",
+                _ => "",
+            };
+            // Titles are cut short rather than the indicator.
+            let title_width = column.width.saturating_sub(indicator.visual_len());
+            let mut title = Line::raw(&column.title)
+                .truncate(title_width)
+                .with(indicator);
+            if self.focused && index == self.selected_column {
+                title = title.patch_style(theme.accent);
+            }
+            title
+        });
+        let header = self.render_row(titles).patch_style(Style::new().bold());
+        let mut text = Text::from(header);
+
+        let visible = self.order.iter().enumerate().skip(self.offset);
+        for (position, row) in visible.take(self.body_height()) {
+            let cells = self.rows[*row].iter().cloned();
+            let line = self.render_row(cells);
+            let line = if Some(position) != self.selected {
+                line
+            } else if self.focused {
+                line.aligned(self.width, Alignment::Left)
+                    .patch_style(theme.selection)
+            } else {
+                line.patch_style(Style::new().bold())
+            };
+            text.push_line(line);
+        }
+
+        Render::from_text(text)
+    }
+
+    fn on_resize(&mut self, size: UVec2) {
+        self.width = size.x as usize;
+        self.height = size.y as usize;
+        if let Some(selected) = self.selected {
+            self.select(selected);
+        }
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn table_sort_test() {
+        use super::*;
+        use crate::input::keymap::{Keymap, KeymapState};
+        use crate::input::{InputEvent, KeyCode, KeyModifiers};
+
+        let keys = |codes: &[KeyCode]| {
+            let mut stream = InputStream::from_events(
+                codes
+                    .iter()
+                    .map(|code| InputEvent::new(*code, KeyModifiers::NONE)),
+            );
+            stream.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+            stream
+        };
+        let plain_lines = |table: &mut Table| -> Vec<String> {
+            let text = table.render().to_text();
+            text.lines.iter().map(Line::plain).collect()
+        };
+
+        let mut table = Table::new(vec![
+            Column::new("Name", 6),
+            Column::new("Tris", 4).with_alignment(Alignment::Right),
+        ])
+        .with_rows([["cube", "12"], ["plane", "2"], ["monkey", "968"]]);
+        table.on_resize(uvec2(11, 3));
+        assert_eq!(
+            plain_lines(&mut table),
+            ["Name   Tris", "cube     12", "plane     2"]
+        );
+
+        // Numbers are sorted by value, and the selected row stays selected.
+        table.tick(&keys(&[KeyCode::Right, KeyCode::Char('s')]));
+        assert_eq!(table.sort(), Some((1, SortOrder::Ascending)));
+        assert_eq!(table.selected_row(), Some(0));
+        assert_eq!(
+            plain_lines(&mut table),
+            ["Name   Tr ▲", "plane     2", "cube     12"]
+        );
+
+        table.tick(&keys(&[KeyCode::Char('s')]));
+        assert_eq!(table.sort(), Some((1, SortOrder::Descending)));
+        table.tick(&keys(&[KeyCode::Home, KeyCode::Enter]));
+        assert_eq!(table.take_activated(), Some(2));
+
+        table.tick(&keys(&[KeyCode::Char('<'), KeyCode::Char('<')]));
+        assert_eq!(table.columns()[1].width, 2);
+    }
+}
@@ -0,0 +1,1254 @@
+use super::*;
+use crate::input::string_editor::StringEditor;
+use crate::input::{InputEvent, KeyCode, KeyModifiers};
+use crate::math::*;
+use crate::ui::{InputStream, Line, Panel, Render, Span, Style, Text, Theme};
+use crate::utils::{grapheme_width, str_width, StrUtils};
+use std::any;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The most undo steps kept, older ones are forgotten.
+const MAX_UNDO_STEPS: usize = 100;
+/// Rows scrolled per step of the mouse wheel.
+const SCROLL_ROWS: usize = 3;
+/// Tabs are inserted as spaces, so their width doesn't depend on where they
+/// are.
+const TAB: &str = "    ";
+
+/// A position in the text of a `TextEditor`: a line, and the byte position in
+/// it, which lies on a grapheme boundary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TextPosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl TextPosition {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    LineStart,
+    LineEnd,
+    DocumentStart,
+    DocumentEnd,
+}
+
+/// The kind of the last edit, for grouping consecutive ones into a single
+/// undo step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Insert,
+    Delete,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    lines: Vec<String>,
+    cursor: TextPosition,
+}
+
+/// A part of a line shown on a single row of the panel. Long lines take up
+/// several rows when word wrap is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Row {
+    line: usize,
+    range: Range<usize>,
+    /// Whether the row ends its line.
+    is_last: bool,
+}
+
+/// How a cell of text is highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Highlight {
+    None,
+    Match,
+    Selection,
+    Cursor,
+}
+
+/// A panel for editing multiple lines of text, like scene descriptions.
+///
+/// Editing works like in most editors: arrows move the cursor, selecting
+/// with shift and moving by words with ctrl, and ctrl+home and ctrl+end jump
+/// to the ends of the text. Other shortcuts are:
+///
+/// | Keys | |
+/// |---|---|
+/// | ctrl+a | select everything |
+/// | ctrl+c, ctrl+x | copy or cut the selection, see below |
+/// | ctrl+v | paste what was copied last |
+/// | ctrl+z, ctrl+y or ctrl+shift+z | undo and redo |
+/// | ctrl+f | search, with enter and shift+enter moving between matches |
+/// | f3, shift+f3 | move between the matches of the last search |
+///
+/// Copying also puts the text on the system clipboard with OSC 52, and text
+/// pasted into the terminal is inserted at the cursor. Without a selection,
+/// ctrl+c does nothing, rather than quitting the gui with the edits unsaved.
+///
+/// Tab is left for moving focus, and is inserted as spaces when pasted.
+#[derive(Debug)]
+pub struct TextEditor {
+    lines: Vec<String>,
+    cursor: TextPosition,
+    /// The end of the selection the cursor isn't at.
+    anchor: Option<TextPosition>,
+    /// The column moving up and down tries to keep, in cells from the start
+    /// of the row.
+    goal_column: Option<usize>,
+    /// The first visible row.
+    scroll: usize,
+    /// The first visible column in cells, when lines aren't wrapped.
+    scroll_x: usize,
+    /// Whether the cursor should be scrolled into view on the next render.
+    follow_cursor: bool,
+    size: (usize, usize),
+    line_numbers: bool,
+    word_wrap: bool,
+    focused: bool,
+    undo_stack: Vec<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    last_edit: Option<EditKind>,
+    clipboard: String,
+    /// Text to put on the system clipboard with the next render.
+    pending_clipboard: Option<String>,
+    /// The query of the last search, kept while the search bar is closed for
+    /// f3.
+    search: StringEditor,
+    search_open: bool,
+    changed: bool,
+}
+
+impl Default for TextEditor {
+    fn default() -> Self {
+        Self {
+            lines: vec![String::new()],
+            cursor: TextPosition::default(),
+            anchor: None,
+            goal_column: None,
+            scroll: 0,
+            scroll_x: 0,
+            follow_cursor: true,
+            size: (0, 0),
+            line_numbers: true,
+            word_wrap: true,
+            focused: false,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            last_edit: None,
+            clipboard: String::new(),
+            pending_clipboard: None,
+            search: StringEditor::new(),
+            search_open: false,
+            changed: false,
+        }
+    }
+}
+
+impl TextEditor {
+    /// An empty editor, showing line numbers and wrapping long lines.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.lines = split_lines(text);
+        self
+    }
+
+    pub fn with_line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    pub fn with_word_wrap(mut self, word_wrap: bool) -> Self {
+        self.word_wrap = word_wrap;
+        self
+    }
+
+    /// The text, with lines separated by `\n`.
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Replace the text, moving the cursor to the start. This can be undone.
+    pub fn set_text(&mut self, text: &str) {
+        self.edit(EditKind::Other);
+        self.lines = split_lines(text);
+        self.move_to(TextPosition::default(), false);
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn set_line_numbers(&mut self, line_numbers: bool) {
+        self.line_numbers = line_numbers;
+    }
+
+    pub fn set_word_wrap(&mut self, word_wrap: bool) {
+        self.word_wrap = word_wrap;
+        self.scroll_x = 0;
+        self.follow_cursor = true;
+    }
+
+    pub fn cursor(&self) -> TextPosition {
+        self.cursor
+    }
+
+    /// Move the cursor to `position`, clamped to the text and rounded down to
+    /// a grapheme boundary. Clears the selection.
+    pub fn set_cursor(&mut self, position: TextPosition) {
+        let line = position.line.min(self.lines.len() - 1);
+        let column = floor_grapheme(&self.lines[line], position.column);
+        self.move_to(TextPosition::new(line, column), false);
+    }
+
+    /// The selected range, if anything is selected.
+    pub fn selection(&self) -> Option<Range<TextPosition>> {
+        let anchor = self.anchor?;
+        if anchor == self.cursor {
+            return None;
+        }
+
+        Some(anchor.min(self.cursor)..anchor.max(self.cursor))
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let selection = self.selection()?;
+        Some(self.text_between(selection.start, selection.end))
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(TextPosition::default());
+        let last = self.lines.len() - 1;
+        self.cursor = TextPosition::new(last, self.lines[last].len());
+        self.follow_cursor = true;
+    }
+
+    /// What was copied or cut last.
+    pub fn clipboard(&self) -> &str {
+        &self.clipboard
+    }
+
+    /// Copy the selection, returning `false` if nothing is selected.
+    pub fn copy(&mut self) -> bool {
+        let Some(text) = self.selected_text() else {
+            return false;
+        };
+
+        self.clipboard = text.clone();
+        self.pending_clipboard = Some(text);
+        true
+    }
+
+    /// Copy the selection and delete it, returning `false` if nothing is
+    /// selected.
+    pub fn cut(&mut self) -> bool {
+        if !self.copy() {
+            return false;
+        }
+
+        self.edit(EditKind::Other);
+        self.delete_selection();
+        true
+    }
+
+    /// Insert `text` at the cursor, replacing the selection.
+    pub fn insert(&mut self, text: &str) {
+        let text = sanitize(text);
+        if text.is_empty() {
+            return;
+        }
+
+        // Words are undone one at a time, along with the spaces after them.
+        let before = &self.lines[self.cursor.line][..self.cursor.column];
+        let new_word = (before.is_empty() || before.ends_with(char::is_whitespace))
+            && !text.starts_with(char::is_whitespace);
+        if new_word || text.contains('\n') || self.selection().is_some() {
+            self.last_edit = None;
+        }
+        self.edit(EditKind::Insert);
+        self.delete_selection();
+
+        let TextPosition { line, column } = self.cursor;
+        let tail = self.lines[line].split_off(column);
+
+        let mut parts = text.split('\n');
+        self.lines[line].push_str(parts.next().unwrap_or(""));
+        let mut end_line = line;
+        for part in parts {
+            end_line += 1;
+            self.lines.insert(end_line, part.to_owned());
+        }
+
+        let end_column = self.lines[end_line].len();
+        self.lines[end_line].push_str(&tail);
+        self.move_to(TextPosition::new(end_line, end_column), false);
+    }
+
+    /// Delete the selection, or the grapheme left of the cursor, joining the
+    /// line with the one above at its start.
+    pub fn delete_backward(&mut self) {
+        self.delete_towards(Motion::Left);
+    }
+
+    /// Delete the selection, or the grapheme right of the cursor.
+    pub fn delete_forward(&mut self) {
+        self.delete_towards(Motion::Right);
+    }
+
+    pub fn delete_word_backward(&mut self) {
+        self.delete_towards(Motion::WordLeft);
+    }
+
+    /// Undo the last edit, returning `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.undo_stack.pop() else {
+            return false;
+        };
+
+        let current = self.restore(snapshot);
+        self.redo_stack.push(current);
+        true
+    }
+
+    /// Redo the last undone edit, returning `false` if there is none.
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.redo_stack.pop() else {
+            return false;
+        };
+
+        let current = self.restore(snapshot);
+        self.undo_stack.push(current);
+        true
+    }
+
+    /// Whether the text was edited since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    pub fn search_query(&self) -> &str {
+        self.search.string()
+    }
+
+    pub fn is_search_open(&self) -> bool {
+        self.search_open
+    }
+
+    /// Search for `query`, ignoring ASCII case, and select the first match
+    /// after the cursor. Returns `false` if there is none.
+    pub fn find(&mut self, query: &str) -> bool {
+        self.search.set_string(query);
+        self.find_next(true)
+    }
+
+    /// Select the next match of the last search, or the previous one if not
+    /// `forward`, wrapping around at the ends. Returns `false` if there are
+    /// no matches.
+    pub fn find_next(&mut self, forward: bool) -> bool {
+        let from = match forward {
+            true => self.cursor,
+            false => self
+                .selection()
+                .map_or(self.cursor, |selection| selection.start),
+        };
+        self.select_match(from, forward)
+    }
+
+    /// The ranges matching the search query, widened to the graphemes they
+    /// are part of.
+    pub fn matches(&self) -> Vec<Range<TextPosition>> {
+        let query = self.search.string().to_ascii_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matches: Vec<Range<TextPosition>> = Vec::new();
+        for (line, text) in self.lines.iter().enumerate() {
+            // Lowercasing ASCII keeps byte positions the same.
+            for (column, _) in text.to_ascii_lowercase().match_indices(&query) {
+                let start = floor_grapheme(text, column);
+                let end = ceil_grapheme(text, column + query.len());
+                let range = TextPosition::new(line, start)..TextPosition::new(line, end);
+                if matches.last() != Some(&range) {
+                    matches.push(range);
+                }
+            }
+        }
+        matches
+    }
+
+    /// Select the first match starting at or after `from`, or the last one
+    /// before it if not `forward`.
+    fn select_match(&mut self, from: TextPosition, forward: bool) -> bool {
+        let matches = self.matches();
+        let found = match forward {
+            true => matches
+                .iter()
+                .find(|range| range.start >= from)
+                .or(matches.first()),
+            false => matches
+                .iter()
+                .rev()
+                .find(|range| range.start < from)
+                .or(matches.last()),
+        };
+        let Some(found) = found.cloned() else {
+            return false;
+        };
+
+        self.move_to(found.start, false);
+        self.move_to(found.end, true);
+        true
+    }
+
+    /// Apply the shortcut of a key press, returning whether the key is one.
+    fn handle_key(&mut self, event: &InputEvent) -> bool {
+        if self.search_open {
+            return self.handle_search_key(event);
+        }
+
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = event.modifiers.contains(KeyModifiers::ALT);
+        let shift = event.modifiers.contains(KeyModifiers::SHIFT);
+
+        let motion = match event.code {
+            KeyCode::Left if ctrl => Some(Motion::WordLeft),
+            KeyCode::Right if ctrl => Some(Motion::WordRight),
+            KeyCode::Home if ctrl => Some(Motion::DocumentStart),
+            KeyCode::End if ctrl => Some(Motion::DocumentEnd),
+            KeyCode::Left => Some(Motion::Left),
+            KeyCode::Right => Some(Motion::Right),
+            KeyCode::Up => Some(Motion::Up),
+            KeyCode::Down => Some(Motion::Down),
+            KeyCode::PageUp => Some(Motion::PageUp),
+            KeyCode::PageDown => Some(Motion::PageDown),
+            KeyCode::Home => Some(Motion::LineStart),
+            KeyCode::End => Some(Motion::LineEnd),
+            _ => None,
+        };
+        if let Some(motion) = motion {
+            let vertical = matches!(
+                motion,
+                Motion::Up | Motion::Down | Motion::PageUp | Motion::PageDown
+            );
+            let goal_column = self.goal_column;
+            let target = self.target(motion);
+            self.move_to(target, shift);
+            if vertical {
+                self.goal_column = goal_column.or(Some(self.row_column(&self.rows())));
+            }
+            return true;
+        }
+
+        match event.code {
+            KeyCode::Char('a') if ctrl => self.select_all(),
+            KeyCode::Char('c') if ctrl => {
+                self.copy();
+            }
+            KeyCode::Char('x') if ctrl => return self.cut(),
+            KeyCode::Char('v') if ctrl => self.insert(&self.clipboard.clone()),
+            KeyCode::Char('z' | 'Z') if ctrl && shift => {
+                self.redo();
+            }
+            KeyCode::Char('z') if ctrl => {
+                self.undo();
+            }
+            KeyCode::Char('y') if ctrl => {
+                self.redo();
+            }
+            KeyCode::Char('f') if ctrl => self.search_open = true,
+            KeyCode::Char(_) if ctrl || alt => return false,
+            KeyCode::Char(char) => self.insert(char.encode_utf8(&mut [0; 4])),
+            KeyCode::Enter => self.insert("\n"),
+            KeyCode::Backspace if ctrl || alt => self.delete_word_backward(),
+            KeyCode::Backspace => self.delete_backward(),
+            KeyCode::Delete => self.delete_forward(),
+            KeyCode::F(3) => {
+                self.find_next(!shift);
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn handle_search_key(&mut self, event: &InputEvent) -> bool {
+        let shift = event.modifiers.contains(KeyModifiers::SHIFT);
+
+        match event.code {
+            KeyCode::Esc => self.search_open = false,
+            KeyCode::Enter | KeyCode::F(3) => {
+                self.find_next(!shift);
+            }
+            _ => {
+                let query = self.search.string().to_owned();
+                if !self.search.handle_key(event) {
+                    return false;
+                }
+
+                // Searching as you type starts over from the current match.
+                if self.search.string() != query {
+                    let from = self.selection().map_or(self.cursor, |range| range.start);
+                    if !self.select_match(from, true) {
+                        self.anchor = None;
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Move the cursor to `position`, selecting the text in between if
+    /// `select` is set.
+    fn move_to(&mut self, position: TextPosition, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+
+        self.cursor = position;
+        self.goal_column = None;
+        self.last_edit = None;
+        self.follow_cursor = true;
+    }
+
+    /// The position `motion` moves the cursor to.
+    fn target(&self, motion: Motion) -> TextPosition {
+        let TextPosition { line, column } = self.cursor;
+        let text = &self.lines[line];
+        let last_line = self.lines.len() - 1;
+
+        match motion {
+            Motion::Left | Motion::WordLeft if column == 0 => match line {
+                0 => self.cursor,
+                _ => TextPosition::new(line - 1, self.lines[line - 1].len()),
+            },
+            Motion::Right | Motion::WordRight if column == text.len() => match line < last_line {
+                true => TextPosition::new(line + 1, 0),
+                false => self.cursor,
+            },
+            Motion::Left => {
+                let column = text[..column]
+                    .grapheme_indices(true)
+                    .next_back()
+                    .map_or(0, |(position, _)| position);
+                TextPosition::new(line, column)
+            }
+            Motion::Right => {
+                let grapheme = text[column..].graphemes(true).next().unwrap_or("");
+                TextPosition::new(line, column + grapheme.len())
+            }
+            Motion::WordLeft => TextPosition::new(line, text.next_word_boundary_left(column)),
+            Motion::WordRight => TextPosition::new(line, text.next_word_boundary_right(column)),
+            Motion::LineStart => TextPosition::new(line, 0),
+            Motion::LineEnd => TextPosition::new(line, text.len()),
+            Motion::DocumentStart => TextPosition::default(),
+            Motion::DocumentEnd => TextPosition::new(last_line, self.lines[last_line].len()),
+            Motion::Up | Motion::Down | Motion::PageUp | Motion::PageDown => {
+                let rows = self.rows();
+                let row = self.row_index(&rows, self.cursor);
+                let page = self.text_height().saturating_sub(1).max(1);
+                let target_row = match motion {
+                    Motion::Up => row.saturating_sub(1),
+                    Motion::Down => row + 1,
+                    Motion::PageUp => row.saturating_sub(page),
+                    _ => row + page,
+                };
+                let target_row = target_row.min(rows.len() - 1);
+                if target_row == row {
+                    return match motion {
+                        Motion::Up | Motion::PageUp => TextPosition::new(line, 0),
+                        _ => TextPosition::new(line, text.len()),
+                    };
+                }
+
+                let goal_column = self.goal_column.unwrap_or(self.row_column(&rows));
+                self.position_in_row(&rows[target_row], goal_column)
+            }
+        }
+    }
+
+    /// Delete the selection, or the text between the cursor and where
+    /// `motion` would move it.
+    fn delete_towards(&mut self, motion: Motion) {
+        let range = match self.selection() {
+            Some(selection) => selection,
+            None => {
+                let target = self.target(motion);
+                self.cursor.min(target)..self.cursor.max(target)
+            }
+        };
+        if range.is_empty() {
+            return;
+        }
+
+        let kind = match motion {
+            Motion::Left | Motion::Right if self.selection().is_none() => EditKind::Delete,
+            _ => EditKind::Other,
+        };
+        self.edit(kind);
+        self.delete_between(range.start, range.end);
+        self.anchor = None;
+        self.cursor = range.start;
+        self.goal_column = None;
+        self.follow_cursor = true;
+        // `move_to` isn't used, so deletes keep being grouped.
+        self.last_edit = Some(kind);
+    }
+
+    fn delete_selection(&mut self) {
+        if let Some(selection) = self.selection() {
+            self.delete_between(selection.start, selection.end);
+            self.cursor = selection.start;
+        }
+        self.anchor = None;
+    }
+
+    fn delete_between(&mut self, start: TextPosition, end: TextPosition) {
+        if start.line == end.line {
+            self.lines[start.line].replace_range(start.column..end.column, "");
+            return;
+        }
+
+        let tail = self.lines[end.line][end.column..].to_owned();
+        self.lines[start.line].truncate(start.column);
+        self.lines[start.line].push_str(&tail);
+        self.lines.drain(start.line + 1..=end.line);
+    }
+
+    fn text_between(&self, start: TextPosition, end: TextPosition) -> String {
+        if start.line == end.line {
+            return self.lines[start.line][start.column..end.column].to_owned();
+        }
+
+        let mut text = self.lines[start.line][start.column..].to_owned();
+        for line in &self.lines[start.line + 1..end.line] {
+            text.push('\n');
+            text.push_str(line);
+        }
+        text.push('\n');
+        text.push_str(&self.lines[end.line][..end.column]);
+        text
+    }
+
+    /// Record the state before an edit of `kind`, unless it continues the
+    /// last edit.
+    fn edit(&mut self, kind: EditKind) {
+        self.redo_stack.clear();
+        self.changed = true;
+
+        if kind == EditKind::Other || self.last_edit != Some(kind) {
+            self.undo_stack.push(Snapshot {
+                lines: self.lines.clone(),
+                cursor: self.cursor,
+            });
+            if self.undo_stack.len() > MAX_UNDO_STEPS {
+                self.undo_stack.remove(0);
+            }
+        }
+
+        self.last_edit = Some(kind);
+    }
+
+    /// Replace the state with `snapshot`, returning the current state.
+    fn restore(&mut self, snapshot: Snapshot) -> Snapshot {
+        let current = Snapshot {
+            lines: std::mem::replace(&mut self.lines, snapshot.lines),
+            cursor: self.cursor,
+        };
+        self.move_to(snapshot.cursor, false);
+        self.changed = true;
+        current
+    }
+
+    fn gutter_width(&self) -> usize {
+        match self.line_numbers {
+            true => self.lines.len().to_string().len() + 1,
+            false => 0,
+        }
+    }
+
+    /// The number of columns available for text, unlimited before the
+    /// editor has been given a size.
+    fn text_width(&self) -> usize {
+        match self.size.0 {
+            0 => usize::MAX,
+            width => width.saturating_sub(self.gutter_width()).max(1),
+        }
+    }
+
+    /// The number of rows available for text, leaving room for the search
+    /// bar.
+    fn text_height(&self) -> usize {
+        let height = self.size.1.saturating_sub(self.search_open as usize);
+        height.max(1)
+    }
+
+    /// Every row of the text, wrapping lines if word wrap is on.
+    fn rows(&self) -> Vec<Row> {
+        let width = match self.word_wrap {
+            true => self.text_width(),
+            false => usize::MAX,
+        };
+
+        let mut rows = Vec::new();
+        for (line, text) in self.lines.iter().enumerate() {
+            let ranges = wrap(text, width);
+            let count = ranges.len();
+            rows.extend(ranges.into_iter().enumerate().map(|(index, range)| Row {
+                line,
+                range,
+                is_last: index + 1 == count,
+            }));
+        }
+        rows
+    }
+
+    /// The index of the row `position` is shown in. A position at the end of
+    /// a wrapped row is shown at the start of the next one.
+    fn row_index(&self, rows: &[Row], position: TextPosition) -> usize {
+        rows.iter()
+            .position(|row| {
+                row.line == position.line && (position.column < row.range.end || row.is_last)
+            })
+            .unwrap_or(0)
+    }
+
+    /// The column of the cursor in its row, in cells.
+    fn row_column(&self, rows: &[Row]) -> usize {
+        let row = &rows[self.row_index(rows, self.cursor)];
+        str_width(&self.lines[row.line][row.range.start..self.cursor.column])
+    }
+
+    /// The position of the grapheme covering the cell `column` of `row`,
+    /// staying inside the row.
+    fn position_in_row(&self, row: &Row, column: usize) -> TextPosition {
+        let text = &self.lines[row.line][row.range.clone()];
+        let mut end = 0;
+        let grapheme = text.grapheme_indices(true).find(|(_, grapheme)| {
+            end += grapheme_width(grapheme);
+            end > column
+        });
+
+        let offset = match (grapheme, row.is_last) {
+            (Some((offset, _)), _) => offset,
+            (None, true) => text.len(),
+            // The end of a wrapped row is the start of the next one.
+            (None, false) => text
+                .grapheme_indices(true)
+                .next_back()
+                .map_or(0, |(offset, _)| offset),
+        };
+        TextPosition::new(row.line, row.range.start + offset)
+    }
+
+    /// The position shown at the cell `position` of the panel.
+    fn position_at(&self, position: IVec2) -> TextPosition {
+        let rows = self.rows();
+        let row = (self.scroll as i64 + position.y.max(0) as i64) as usize;
+        let Some(row) = rows.get(row) else {
+            let last = self.lines.len() - 1;
+            return TextPosition::new(last, self.lines[last].len());
+        };
+
+        let column = (position.x.max(0) as usize).saturating_sub(self.gutter_width());
+        self.position_in_row(row, column + self.scroll_x)
+    }
+
+    fn scroll_to_cursor(&mut self, rows: &[Row]) {
+        let row = self.row_index(rows, self.cursor);
+        let height = self.text_height();
+        self.scroll = self.scroll.min(row).max((row + 1).saturating_sub(height));
+
+        let width = self.text_width();
+        if self.word_wrap || width == usize::MAX {
+            self.scroll_x = 0;
+        } else {
+            // The cursor takes up a cell past the end of the line.
+            let column = self.row_column(rows);
+            let cursor_width = self.lines[self.cursor.line][self.cursor.column..]
+                .graphemes(true)
+                .next()
+                .map_or(1, grapheme_width);
+            self.scroll_x = self
+                .scroll_x
+                .min(column)
+                .max((column + cursor_width).saturating_sub(width));
+        }
+    }
+
+    fn highlight_at(
+        &self,
+        position: TextPosition,
+        selection: &Option<Range<TextPosition>>,
+        matches: &[Range<TextPosition>],
+    ) -> Highlight {
+        if self.focused && !self.search_open && position == self.cursor {
+            Highlight::Cursor
+        } else if selection
+            .as_ref()
+            .is_some_and(|range| range.contains(&position))
+        {
+            Highlight::Selection
+        } else if matches.iter().any(|range| range.contains(&position)) {
+            Highlight::Match
+        } else {
+            Highlight::None
+        }
+    }
+
+    fn render_search_bar(&self) -> Line {
+        let mut line = Line::styled("Find: ", Style::new().bold());
+
+        let query = self.search.string();
+        let cursor = self.search.cursor();
+        line.push(&query[..cursor]);
+        let grapheme = query[cursor..].graphemes(true).next().unwrap_or(" ");
+        line.push(highlight_span(grapheme, self.focused));
+        line.push(&query[(cursor + grapheme.len()).min(query.len())..]);
+
+        let matches = self.matches();
+        if !query.is_empty() {
+            let status = match matches
+                .iter()
+                .position(|range| Some(range) == self.selection().as_ref())
+            {
+                _ if matches.is_empty() => " no matches".to_owned(),
+                Some(index) => format!(" {}/{}", index + 1, matches.len()),
+                None => format!(" {} matches", matches.len()),
+            };
+            line.push(Span::styled(status, Theme::current().muted));
+        }
+
+        line
+    }
+}
+
+impl Panel for TextEditor {
+    fn tick(&mut self, key_events: &InputStream) {
+        let events: Vec<InputEvent> = key_events.iter().copied().collect();
+        for event in events {
+            if self.handle_key(&event) {
+                key_events.consume(&event);
+            }
+        }
+
+        while let Some(text) = key_events.consume_paste() {
+            match self.search_open {
+                true => self.search.insert(&text),
+                false => self.insert(&text),
+            }
+        }
+
+        while let Some(event) = key_events.consume_mouse(|event| {
+            matches!(
+                event.kind,
+                MouseEventKind::Down(MouseButton::Left)
+                    | MouseEventKind::Drag(MouseButton::Left)
+                    | MouseEventKind::ScrollUp
+                    | MouseEventKind::ScrollDown
+            )
+        }) {
+            match event.kind {
+                MouseEventKind::ScrollUp => self.scroll = self.scroll.saturating_sub(SCROLL_ROWS),
+                MouseEventKind::ScrollDown => {
+                    let last_row = self.rows().len().saturating_sub(1);
+                    self.scroll = (self.scroll + SCROLL_ROWS).min(last_row);
+                }
+                MouseEventKind::Drag(_) => {
+                    let position = self.position_at(event.position);
+                    self.move_to(position, true);
+                }
+                _ => {
+                    let position = self.position_at(event.position);
+                    self.move_to(position, false);
+                }
+            }
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let rows = self.rows();
+        if std::mem::take(&mut self.follow_cursor) {
+            self.scroll_to_cursor(&rows);
+        }
+
+        let mut text = Text::new();
+        let theme = Theme::current();
+        let gutter_width = self.gutter_width();
+        let text_width = self.text_width();
+        let selection = self.selection();
+        let matches = self.matches();
+
+        for row in rows.iter().skip(self.scroll).take(self.text_height()) {
+            let mut line = Line::new();
+            if self.line_numbers {
+                let number = match row.range.start {
+                    0 => format!("{:>width$} ", row.line + 1, width = gutter_width - 1),
+                    _ => " ".repeat(gutter_width),
+                };
+                line.push(Span::styled(number, theme.muted));
+            }
+
+            // Runs of graphemes with the same highlight are styled together.
+            let mut run = String::new();
+            let mut run_highlight = Highlight::None;
+            let push_run = |line: &mut Line, run: &mut String, highlight| {
+                let style = match highlight {
+                    Highlight::None => Style::new(),
+                    Highlight::Match => theme.highlight,
+                    Highlight::Selection | Highlight::Cursor => theme.selection,
+                };
+                line.push(Span::styled(std::mem::take(run), style));
+            };
+
+            let row_text = &self.lines[row.line][row.range.clone()];
+            let mut graphemes: Vec<(usize, &str)> = row_text.grapheme_indices(true).collect();
+            // The cursor takes up a cell past the end of the line.
+            if row.is_last {
+                graphemes.push((row_text.len(), " "));
+            }
+
+            let mut column = 0;
+            for (offset, grapheme) in graphemes {
+                let start = column;
+                column += grapheme_width(grapheme);
+                if column <= self.scroll_x {
+                    continue;
+                }
+                if column > self.scroll_x.saturating_add(text_width) {
+                    break;
+                }
+                // Only the right half of a wide grapheme is scrolled into view.
+                let grapheme = match start < self.scroll_x {
+                    true => " ",
+                    false => grapheme,
+                };
+
+                let position = TextPosition::new(row.line, row.range.start + offset);
+                let highlight = self.highlight_at(position, &selection, &matches);
+                if highlight != run_highlight {
+                    push_run(&mut line, &mut run, run_highlight);
+                    run_highlight = highlight;
+                }
+                run.push_str(grapheme);
+            }
+            push_run(&mut line, &mut run, run_highlight);
+            text.push_line(line);
+        }
+
+        if self.search_open {
+            for _ in rows.len().saturating_sub(self.scroll)..self.text_height() {
+                text.push_line("");
+            }
+            text.push_line(self.render_search_bar());
+        }
+
+        let render = Render::from_text(text);
+        match self.pending_clipboard.take() {
+            Some(clipboard) => render.with_sequence(screen::get_set_clipboard(&clipboard)),
+            None => render,
+        }
+    }
+
+    fn on_resize(&mut self, size: UVec2) {
+        self.size = (size.x as usize, size.y as usize);
+        self.follow_cursor = true;
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+fn split_lines(text: &str) -> Vec<String> {
+    sanitize(text).split('\n').map(str::to_owned).collect()
+}
+
+/// Normalize line endings and tabs, and leave out other control characters.
+fn sanitize(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\t', TAB)
+        .chars()
+        .filter(|char| *char == '\n' || !char.is_control())
+        .collect()
+}
+
+/// The byte ranges of `text` fitting in `width` columns each, breaking after
+/// whitespace where possible.
+fn wrap(text: &str, width: usize) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut length = 0;
+    // The position after the last whitespace in the current row.
+    let mut break_at = None;
+
+    for (index, grapheme) in text.grapheme_indices(true) {
+        let grapheme_width = grapheme_width(grapheme);
+        // An empty row takes any grapheme, so ones wider than `width` get a
+        // row of their own.
+        if length > 0 && length + grapheme_width > width {
+            let end = match break_at {
+                Some(position) if position > start => position,
+                _ => index,
+            };
+            ranges.push(start..end);
+            length = str_width(&text[end..index]);
+            start = end;
+            break_at = None;
+
+            // A wide grapheme can still overflow what's left of the word.
+            if length > 0 && length + grapheme_width > width {
+                ranges.push(start..index);
+                length = 0;
+                start = index;
+            }
+        }
+
+        length += grapheme_width;
+        if grapheme.chars().all(char::is_whitespace) {
+            break_at = Some(index + grapheme.len());
+        }
+    }
+
+    ranges.push(start..text.len());
+    ranges
+}
+
+/// `position` rounded down to a grapheme boundary of `text`.
+fn floor_grapheme(text: &str, position: usize) -> usize {
+    if position >= text.len() {
+        return text.len();
+    }
+
+    text.grapheme_indices(true)
+        .map(|(index, _)| index)
+        .take_while(|index| *index <= position)
+        .last()
+        .unwrap_or(0)
+}
+
+/// `position` rounded up to a grapheme boundary of `text`.
+fn ceil_grapheme(text: &str, position: usize) -> usize {
+    let floor = floor_grapheme(text, position);
+    match floor == position {
+        true => position,
+        false => floor + text[floor..].graphemes(true).next().map_or(0, str::len),
+    }
+}
+
+mod tests {
+    #[allow(dead_code)]
+    fn keys(
+        codes: &[(crate::input::KeyCode, crate::input::KeyModifiers)],
+    ) -> crate::ui::InputStream {
+        use crate::input::InputEvent;
+
+        crate::ui::InputStream::from_events(
+            codes
+                .iter()
+                .map(|(code, modifiers)| InputEvent::new(*code, *modifiers)),
+        )
+    }
+
+    #[test]
+    fn multi_line_editing_test() {
+        use super::*;
+
+        let mut editor = TextEditor::new().with_text("first\nsecond");
+        editor.set_cursor(TextPosition::new(0, 5));
+        editor.insert("\r\nmiddle");
+        assert_eq!(editor.text(), "first\nmiddle\nsecond");
+        assert_eq!(editor.cursor(), TextPosition::new(1, 6));
+
+        // Deleting at the start of a line joins it with the previous one.
+        editor.set_cursor(TextPosition::new(2, 0));
+        editor.delete_backward();
+        assert_eq!(editor.lines(), ["first", "middlesecond"]);
+
+        editor.set_cursor(TextPosition::new(0, 2));
+        editor.move_to(TextPosition::new(1, 3), true);
+        assert_eq!(editor.selected_text().as_deref(), Some("rst\nmid"));
+        assert!(editor.cut());
+        assert_eq!(editor.text(), "fidlesecond");
+        assert_eq!(editor.clipboard(), "rst\nmid");
+
+        assert!(editor.undo());
+        assert!(editor.undo());
+        assert!(editor.undo());
+        assert_eq!(editor.text(), "first\nsecond");
+        assert!(editor.redo());
+        assert_eq!(editor.text(), "first\nmiddle\nsecond");
+    }
+
+    #[test]
+    fn word_wrap_test() {
+        use super::*;
+        use ansi_parser::AnsiParser;
+
+        assert_eq!(wrap("hello big world", 7), vec![0..6, 6..10, 10..15]);
+        assert_eq!(wrap("abcdefgh", 3), vec![0..3, 3..6, 6..8]);
+        assert_eq!(wrap("", 3), vec![0..0]);
+        // Wide characters take up two columns.
+        assert_eq!(wrap("中文 ab", 3), vec![0..3, 3..7, 7..9]);
+        assert_eq!(wrap(" ab中", 3), vec![0..1, 1..3, 3..6]);
+
+        let mut editor = TextEditor::new()
+            .with_text("hello big world\nend")
+            .with_line_numbers(true);
+        editor.on_resize(uvec2(9, 10));
+
+        let render = editor.render();
+        let lines: Vec<String> = render.lines().map(|line| line.visual_truncate(9)).collect();
+        let text: Vec<String> = lines
+            .iter()
+            .map(|line| {
+                line.ansi_parse()
+                    .filter_map(|block| match block {
+                        ansi_parser::Output::TextBlock(text) => Some(text),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(text, ["1 hello ", "  big ", "  world ", "2 end "]);
+
+        // Moving down goes through the rows of a wrapped line.
+        editor.set_cursor(TextPosition::new(0, 1));
+        editor.tick(&keys(&[(KeyCode::Down, KeyModifiers::NONE)]));
+        assert_eq!(editor.cursor(), TextPosition::new(0, 7));
+        editor.tick(&keys(&[
+            (KeyCode::Down, KeyModifiers::NONE),
+            (KeyCode::Down, KeyModifiers::NONE),
+        ]));
+        assert_eq!(editor.cursor(), TextPosition::new(1, 1));
+    }
+
+    #[test]
+    fn wide_text_test() {
+        use super::*;
+
+        let mut editor = TextEditor::new()
+            .with_text(
+                "中文字
+abcdef",
+            )
+            .with_line_numbers(false)
+            .with_word_wrap(false);
+        editor.on_resize(uvec2(4, 2));
+        editor.set_cursor(TextPosition::new(0, "中文字".len()));
+
+        // Scrolled so the cursor past the end is in the last column, with
+        // only the right half of "文" left of "字".
+        let text = editor.render().to_text();
+        assert_eq!(text.lines[0].plain(), " 字 ");
+        assert_eq!(text.lines[1].plain(), "def ");
+
+        // Moving up and down keeps the column in cells.
+        editor.set_cursor(TextPosition::new(0, "中".len()));
+        editor.tick(&keys(&[(KeyCode::Down, KeyModifiers::NONE)]));
+        assert_eq!(editor.cursor(), TextPosition::new(1, 2));
+        editor.tick(&keys(&[(KeyCode::Right, KeyModifiers::NONE)]));
+        editor.tick(&keys(&[(KeyCode::Up, KeyModifiers::NONE)]));
+        assert_eq!(editor.cursor(), TextPosition::new(0, "中".len()));
+    }
+
+    #[test]
+    fn search_test() {
+        use super::*;
+
+        let mut editor = TextEditor::new().with_text("Cube cube\nsphere CUBE");
+        editor.on_resize(uvec2(20, 5));
+        editor.tick(&keys(&[
+            (KeyCode::Char('f'), KeyModifiers::CONTROL),
+            (KeyCode::Char('c'), KeyModifiers::NONE),
+            (KeyCode::Char('u'), KeyModifiers::NONE),
+            (KeyCode::Char('b'), KeyModifiers::NONE),
+        ]));
+        assert!(editor.is_search_open());
+        assert_eq!(editor.matches().len(), 3);
+        assert_eq!(
+            editor.selection(),
+            Some(TextPosition::new(0, 0)..TextPosition::new(0, 3))
+        );
+
+        editor.tick(&keys(&[
+            (KeyCode::Enter, KeyModifiers::NONE),
+            (KeyCode::Enter, KeyModifiers::NONE),
+        ]));
+        assert_eq!(editor.cursor(), TextPosition::new(1, 10));
+        editor.tick(&keys(&[(KeyCode::Enter, KeyModifiers::NONE)]));
+        assert_eq!(editor.cursor(), TextPosition::new(0, 3));
+        editor.tick(&keys(&[(KeyCode::Enter, KeyModifiers::SHIFT)]));
+        assert_eq!(editor.cursor(), TextPosition::new(1, 10));
+
+        // Closing the search leaves the match selected, and typing replaces it.
+        editor.tick(&keys(&[
+            (KeyCode::Esc, KeyModifiers::NONE),
+            (KeyCode::Char('x'), KeyModifiers::NONE),
+        ]));
+        assert_eq!(editor.text(), "Cube cube\nsphere xE");
+        assert!(!editor.find("missing"));
+
+        // Matches inside a grapheme cover all of it.
+        editor.set_text("cafe\u{301} e");
+        editor.find("e");
+        assert_eq!(
+            editor.matches(),
+            [
+                TextPosition::new(0, 3)..TextPosition::new(0, 6),
+                TextPosition::new(0, 7)..TextPosition::new(0, 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn clipboard_test() {
+        use super::*;
+
+        let mut editor = TextEditor::new().with_text("copy me");
+        editor.select_all();
+        let stream = keys(&[(KeyCode::Char('c'), KeyModifiers::CONTROL)]);
+        editor.tick(&stream);
+        assert_eq!(stream.iter().count(), 0);
+
+        let render = editor.render();
+        assert_eq!(render.sequences(), ["\x1b]52;c;Y29weSBtZQ==\x07"]);
+
+        // Without a selection, ctrl+c still doesn't reach the gui, where it
+        // would quit.
+        editor.set_cursor(TextPosition::new(0, 0));
+        let stream = keys(&[(KeyCode::Char('c'), KeyModifiers::CONTROL)]);
+        editor.tick(&stream);
+        assert_eq!(stream.iter().count(), 0);
+        assert!(editor.render().sequences().is_empty());
+
+        editor.tick(&InputStream::from_pastes(["pasted\ttext\n".to_owned()]));
+        assert_eq!(editor.lines(), ["pasted    text", "copy me"]);
+    }
+}
@@ -0,0 +1,267 @@
+use super::*;
+use crate::input::string_editor::StringEditor;
+use crate::input::{InputEvent, KeyCode};
+use crate::math::*;
+use crate::ui::{InputStream, Line, Panel, Render, Span, Theme};
+use crate::utils::grapheme_width;
+use std::any;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A single line text field, edited like the prompts of `Input::read_string`
+/// with the shortcuts of `StringEditor::handle_key`.
+///
+/// Enter submits the text, which can be picked up with `take_submitted`, and
+/// adds it to the history browsed with up and down.
+#[derive(Debug, Default)]
+pub struct TextInput {
+    editor: StringEditor,
+    placeholder: String,
+    /// The first column shown, which follows the cursor if the text is wider
+    /// than the panel.
+    scroll: usize,
+    width: usize,
+    focused: bool,
+    submitted: Option<String>,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Text shown dimmed while the input is empty.
+    pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.placeholder = placeholder.into();
+        self
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.editor = StringEditor::from(text);
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        self.editor.string()
+    }
+
+    /// Replace the text, moving the cursor to its end.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.editor.set_string(text);
+    }
+
+    pub fn editor(&self) -> &StringEditor {
+        &self.editor
+    }
+
+    pub fn editor_mut(&mut self) -> &mut StringEditor {
+        &mut self.editor
+    }
+
+    /// The text submitted with enter since the last call, if any.
+    pub fn take_submitted(&mut self) -> Option<String> {
+        self.submitted.take()
+    }
+}
+
+impl Panel for TextInput {
+    fn tick(&mut self, key_events: &InputStream) {
+        let events: Vec<InputEvent> = key_events.iter().copied().collect();
+        for event in events {
+            let handled = match event.code {
+                KeyCode::Enter => {
+                    let text = self.editor.string().to_owned();
+                    self.editor.push_history(&text);
+                    self.submitted = Some(text);
+                    true
+                }
+                _ => self.editor.handle_key(&event),
+            };
+            if handled {
+                key_events.consume(&event);
+            }
+        }
+
+        while let Some(text) = key_events.consume_paste() {
+            self.editor.insert(&text);
+        }
+
+        // Clicking moves the cursor before the grapheme under it.
+        if let Some(event) = key_events.consume_mouse(is_left_press) {
+            let column = self.scroll + event.position.x.max(0) as usize;
+            let text = self.editor.string();
+            let mut end = 0;
+            let position = text
+                .grapheme_indices(true)
+                .find(|(_, grapheme)| {
+                    end += grapheme_width(grapheme);
+                    end > column
+                })
+                .map_or(text.len(), |(position, _)| position);
+            self.editor.set_cursor(position);
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let mut line = Line::new();
+
+        if self.editor.is_empty() && !self.placeholder.is_empty() {
+            if self.focused {
+                line.push(highlight_span(" ", true));
+            }
+            line.push(Span::styled(&self.placeholder, Theme::current().muted));
+            return Render::from_text(line);
+        }
+
+        // The graphemes with their byte position and the columns they cover.
+        // The cursor takes up a cell past the end of the text.
+        let text = self.editor.string();
+        let mut column = 0;
+        let cells: Vec<(usize, &str, Range<usize>)> = text
+            .grapheme_indices(true)
+            .chain([(text.len(), " ")])
+            .map(|(position, grapheme)| {
+                let start = column;
+                column += grapheme_width(grapheme);
+                (position, grapheme, start..column)
+            })
+            .collect();
+
+        let cursor = self.editor.cursor();
+        let (_, _, cursor_columns) = cells
+            .iter()
+            .find(|(position, ..)| *position == cursor)
+            .expect("the cursor is on a grapheme boundary");
+        if self.width > 0 {
+            self.scroll = self
+                .scroll
+                .min(cursor_columns.start)
+                .max(cursor_columns.end.saturating_sub(self.width));
+        } else {
+            self.scroll = 0;
+        }
+
+        // The selection is shown while unfocused as well.
+        let selection = self.editor.selection();
+        for (position, grapheme, columns) in &cells {
+            if columns.end <= self.scroll {
+                continue;
+            }
+            if self.width > 0 && columns.end > self.scroll + self.width {
+                break;
+            }
+            // Only the right half of a wide grapheme is scrolled into view.
+            let grapheme = match columns.start < self.scroll {
+                true => " ",
+                false => grapheme,
+            };
+            let selected = selection
+                .as_ref()
+                .is_some_and(|range| range.contains(position));
+            line.push(highlight_span(
+                grapheme,
+                selected || (self.focused && *position == cursor),
+            ));
+        }
+
+        Render::from_text(line)
+    }
+
+    fn on_resize(&mut self, size: UVec2) {
+        self.width = size.x as usize;
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn text_input_editing_test() {
+        use super::*;
+        use crate::input::KeyModifiers;
+
+        let mut input = TextInput::new().with_text("héllo wörld");
+        let key = |code| InputEvent::new(code, KeyModifiers::NONE);
+        let stream = InputStream::from_events([
+            InputEvent::new(KeyCode::Left, KeyModifiers::CONTROL),
+            key(KeyCode::Backspace),
+            key(KeyCode::Char('-')),
+            key(KeyCode::Home),
+            key(KeyCode::Delete),
+            key(KeyCode::Char('H')),
+            key(KeyCode::End),
+            InputEvent::new(KeyCode::Backspace, KeyModifiers::ALT),
+            key(KeyCode::Char('!')),
+            key(KeyCode::Enter),
+            key(KeyCode::Tab),
+            InputEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+        ]);
+        input.tick(&stream);
+
+        assert_eq!(input.text(), "Héllo-!");
+        assert_eq!(input.editor().cursor(), input.text().len());
+        assert_eq!(input.take_submitted().as_deref(), Some("Héllo-!"));
+        assert_eq!(input.take_submitted(), None);
+
+        // Focus and shortcuts are left to the gui.
+        assert!(stream.contains_key_code(KeyCode::Tab));
+        assert!(stream.contains_key_code(KeyCode::Char('c')));
+        assert_eq!(stream.iter().count(), 2);
+    }
+
+    #[test]
+    fn text_input_scroll_test() {
+        use super::*;
+        use crate::input::KeyModifiers;
+        use crate::utils::StrUtils;
+
+        let mut input = TextInput::new().with_text("abcdefgh");
+        input.on_resize(uvec2(4, 1));
+        input.on_focus_change(true);
+
+        // The cursor at the end stays visible.
+        let render = input.render();
+        assert_eq!(render.lines().next().unwrap().visual_len(), 4);
+        assert!(render.lines().next().unwrap().starts_with("fgh"));
+
+        input.tick(&InputStream::from_events([InputEvent::new(
+            KeyCode::Home,
+            KeyModifiers::NONE,
+        )]));
+        let render = input.render();
+        assert!(render.lines().next().unwrap().ends_with("bcd"));
+    }
+
+    #[test]
+    fn text_input_wide_text_test() {
+        use super::*;
+        use crate::input::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+        let mut input = TextInput::new().with_text("中文字");
+        input.on_resize(uvec2(4, 1));
+        input.on_focus_change(true);
+
+        // The cursor past the end is in the last column, with only the right
+        // half of "文" left of "字".
+        let text = input.render().to_text();
+        assert_eq!(text.lines[0].plain(), " 字 ");
+        assert_eq!(text.width(), 4);
+
+        // Clicking the right half of "字" puts the cursor before it.
+        input.tick(&InputStream::from_mouse_events([MouseEvent::new(
+            MouseEventKind::Down(MouseButton::Left),
+            ivec2(2, 0),
+            KeyModifiers::NONE,
+        )]));
+        assert_eq!(input.editor().cursor(), "中文".len());
+    }
+}
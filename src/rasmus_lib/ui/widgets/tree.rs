@@ -0,0 +1,415 @@
+use super::*;
+use crate::input::keymap;
+use crate::math::*;
+use crate::ui::{Alignment, InputStream, Line, Panel, Render, Style, Text, Theme};
+use std::any;
+use std::collections::HashSet;
+
+/// A node of a `Tree`, with a label and nodes nested in it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TreeNode {
+    pub label: Line,
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    pub fn new(label: impl Into<Line>) -> Self {
+        Self {
+            label: label.into(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with_children(mut self, children: Vec<TreeNode>) -> Self {
+        self.children = children;
+        self
+    }
+
+    pub fn with_child(mut self, child: TreeNode) -> Self {
+        self.children.push(child);
+        self
+    }
+}
+
+/// A node shown in the tree.
+#[derive(Debug, Clone)]
+struct Row {
+    path: Vec<usize>,
+    has_children: bool,
+}
+
+/// Nodes that can be expanded to show the nodes nested in them.
+///
+/// Nodes are addressed by their path, the index of the node in every level
+/// of the tree leading to it. The selection is moved with the `ui.*` actions
+/// like in a `List`. `ui.right` expands the selected node, or moves into it
+/// if it's expanded already, and `ui.left` collapses it or moves to its
+/// parent. `ui.activate` and clicking the selected node toggle it, which can
+/// be picked up with `take_activated`.
+///
+/// Which nodes are expanded is kept when the nodes are replaced, so a tree
+/// can be rebuilt from the data it shows on every tick.
+#[derive(Debug, Default)]
+pub struct Tree {
+    roots: Vec<TreeNode>,
+    expanded: HashSet<Vec<usize>>,
+    /// The nodes that are shown, in order.
+    rows: Vec<Row>,
+    /// The position of the selected node in `rows`.
+    selected: Option<usize>,
+    /// The position in `rows` of the first visible node.
+    offset: usize,
+    width: usize,
+    height: usize,
+    focused: bool,
+    activated: Option<Vec<usize>>,
+}
+
+impl Tree {
+    pub fn new(roots: Vec<TreeNode>) -> Self {
+        let mut tree = Self::default();
+        tree.set_roots(roots);
+        tree
+    }
+
+    pub fn roots(&self) -> &[TreeNode] {
+        &self.roots
+    }
+
+    /// Replace the nodes, keeping the same paths selected and expanded where
+    /// they still exist.
+    pub fn set_roots(&mut self, roots: Vec<TreeNode>) {
+        let selected = self.selected().map(<[usize]>::to_vec);
+        self.roots = roots;
+        self.update_rows();
+
+        self.selected = None;
+        match selected {
+            Some(path) if self.node(&path).is_some() => self.select(&path),
+            _ if !self.rows.is_empty() => self.select_position(0),
+            _ => {}
+        }
+        self.offset = self.offset.min(self.max_offset());
+    }
+
+    /// The node at `path`, if there is one.
+    pub fn node(&self, path: &[usize]) -> Option<&TreeNode> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.roots.get(*first)?;
+        for index in rest {
+            node = node.children.get(*index)?;
+        }
+        Some(node)
+    }
+
+    /// The path of the selected node.
+    pub fn selected(&self) -> Option<&[usize]> {
+        Some(&self.rows[self.selected?].path)
+    }
+
+    pub fn selected_node(&self) -> Option<&TreeNode> {
+        self.node(self.selected()?)
+    }
+
+    /// Select the node at `path`, expanding its parents and scrolling it into
+    /// view.
+    pub fn select(&mut self, path: &[usize]) {
+        if self.node(path).is_none() {
+            return;
+        }
+        for depth in 1..path.len() {
+            self.expanded.insert(path[..depth].to_vec());
+        }
+        self.update_rows();
+
+        if let Some(position) = self.rows.iter().position(|row| row.path == path) {
+            self.select_position(position);
+        }
+    }
+
+    pub fn is_expanded(&self, path: &[usize]) -> bool {
+        self.expanded.contains(path)
+    }
+
+    pub fn set_expanded(&mut self, path: &[usize], expanded: bool) {
+        let selected = self.selected().map(<[usize]>::to_vec);
+        match expanded {
+            true => self.expanded.insert(path.to_vec()),
+            false => self.expanded.remove(path),
+        };
+        self.update_rows();
+
+        // Collapsing a parent of the selected node selects the parent.
+        if let Some(mut selected) = selected {
+            while !self.rows.iter().any(|row| row.path == selected) {
+                selected.pop();
+            }
+            self.select(&selected);
+        }
+    }
+
+    /// Expand every node with children.
+    pub fn expand_all(&mut self) {
+        let mut stack: Vec<(Vec<usize>, &TreeNode)> = self
+            .roots
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (vec![index], node))
+            .collect();
+        while let Some((path, node)) = stack.pop() {
+            for (index, child) in node.children.iter().enumerate() {
+                let mut child_path = path.clone();
+                child_path.push(index);
+                stack.push((child_path, child));
+            }
+            if !node.children.is_empty() {
+                self.expanded.insert(path);
+            }
+        }
+        self.update_rows();
+    }
+
+    /// The path of the node activated since the last call, if any.
+    pub fn take_activated(&mut self) -> Option<Vec<usize>> {
+        self.activated.take()
+    }
+
+    fn update_rows(&mut self) {
+        fn visit(
+            nodes: &[TreeNode],
+            path: &mut Vec<usize>,
+            expanded: &HashSet<Vec<usize>>,
+            rows: &mut Vec<Row>,
+        ) {
+            for (index, node) in nodes.iter().enumerate() {
+                path.push(index);
+                rows.push(Row {
+                    path: path.clone(),
+                    has_children: !node.children.is_empty(),
+                });
+                if expanded.contains(path.as_slice()) {
+                    visit(&node.children, path, expanded, rows);
+                }
+                path.pop();
+            }
+        }
+
+        self.rows.clear();
+        visit(&self.roots, &mut Vec::new(), &self.expanded, &mut self.rows);
+        if let Some(selected) = self.selected {
+            self.selected = (!self.rows.is_empty()).then(|| selected.min(self.rows.len() - 1));
+        }
+    }
+
+    fn select_position(&mut self, position: usize) {
+        if self.rows.is_empty() {
+            return;
+        }
+
+        let position = position.min(self.rows.len() - 1);
+        self.selected = Some(position);
+
+        let height = self.height.max(1);
+        if position < self.offset {
+            self.offset = position;
+        } else if position >= self.offset + height {
+            self.offset = position + 1 - height;
+        }
+    }
+
+    /// Expand or collapse the selected node, marking it as activated.
+    fn activate(&mut self) {
+        let Some(selected) = self.selected else {
+            return;
+        };
+        let row = self.rows[selected].clone();
+        if row.has_children {
+            self.set_expanded(&row.path, !self.is_expanded(&row.path));
+        }
+        self.activated = Some(row.path);
+    }
+
+    fn max_offset(&self) -> usize {
+        self.rows.len().saturating_sub(self.height.max(1))
+    }
+}
+
+impl Panel for Tree {
+    fn tick(&mut self, key_events: &InputStream) {
+        let page = (self.height.max(1) - 1).max(1);
+
+        let actions = [
+            keymap::UP,
+            keymap::DOWN,
+            keymap::LEFT,
+            keymap::RIGHT,
+            keymap::PAGE_UP,
+            keymap::PAGE_DOWN,
+            keymap::HOME,
+            keymap::END,
+            keymap::ACTIVATE,
+        ];
+        for action in consume_actions(key_events, &actions) {
+            let selected = self.selected.unwrap_or(0);
+            let row = self.rows.get(selected).cloned();
+            match action {
+                keymap::UP => self.select_position(selected.saturating_sub(1)),
+                keymap::DOWN => self.select_position(selected + 1),
+                keymap::PAGE_UP => self.select_position(selected.saturating_sub(page)),
+                keymap::PAGE_DOWN => self.select_position(selected + page),
+                keymap::HOME => self.select_position(0),
+                keymap::END => self.select_position(usize::MAX),
+                keymap::RIGHT => match row {
+                    Some(row) if row.has_children && !self.is_expanded(&row.path) => {
+                        self.set_expanded(&row.path, true)
+                    }
+                    Some(row) if row.has_children => self.select_position(selected + 1),
+                    _ => {}
+                },
+                keymap::LEFT => match row {
+                    Some(row) if self.is_expanded(&row.path) => self.set_expanded(&row.path, false),
+                    Some(row) if row.path.len() > 1 => self.select(&row.path[..row.path.len() - 1]),
+                    _ => {}
+                },
+                _ => self.activate(),
+            }
+        }
+
+        while let Some(event) = key_events.consume_mouse(|event| {
+            is_left_press(event)
+                || matches!(
+                    event.kind,
+                    MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+                )
+        }) {
+            match event.kind {
+                MouseEventKind::ScrollUp => self.offset = self.offset.saturating_sub(1),
+                MouseEventKind::ScrollDown => {
+                    self.offset = (self.offset + 1).min(self.max_offset())
+                }
+                _ => {
+                    let position = self.offset + event.position.y.max(0) as usize;
+                    if position < self.rows.len() {
+                        let was_selected = self.selected == Some(position);
+                        self.select_position(position);
+                        if was_selected {
+                            self.activate();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn render(&mut self) -> Render {
+        let theme = Theme::current();
+        let mut text = Text::new();
+
+        let visible = self.rows.iter().enumerate().skip(self.offset);
+        for (position, row) in visible.take(self.height.max(1)) {
+            let Some(node) = self.node(&row.path) else {
+                continue;
+            };
+            let marker = match (row.has_children, self.is_expanded(&row.path)) {
+                (false, _) => "  ",
+                (true, false) => "▸ ",
+                (true, true) => "▾ ",
+            };
+            let indent = "  ".repeat(row.path.len() - 1);
+
+            let mut line = Line::raw(indent + marker);
+            line.spans.extend(node.label.clone().into_spans());
+            let line = if Some(position) != self.selected {
+                line.truncate(self.width)
+            } else if self.focused {
+                line.aligned(self.width, Alignment::Left)
+                    .patch_style(theme.selection)
+            } else {
+                line.truncate(self.width).patch_style(Style::new().bold())
+            };
+            text.push_line(line);
+        }
+
+        Render::from_text(text)
+    }
+
+    fn on_resize(&mut self, size: UVec2) {
+        self.width = size.x as usize;
+        self.height = size.y as usize;
+        if let Some(selected) = self.selected {
+            self.select_position(selected);
+        }
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn tree_navigation_test() {
+        use super::*;
+        use crate::input::keymap::{Keymap, KeymapState};
+        use crate::input::{InputEvent, KeyCode, KeyModifiers};
+
+        let keys = |codes: &[KeyCode]| {
+            let mut stream = InputStream::from_events(
+                codes
+                    .iter()
+                    .map(|code| InputEvent::new(*code, KeyModifiers::NONE)),
+            );
+            stream.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+            stream
+        };
+        let roots = || {
+            vec![
+                TreeNode::new("Camera"),
+                TreeNode::new("Objects")
+                    .with_child(TreeNode::new("Cube"))
+                    .with_child(TreeNode::new("Suzanne")),
+            ]
+        };
+
+        let mut tree = Tree::new(roots());
+        tree.on_resize(uvec2(20, 5));
+        assert_eq!(tree.render().height(), 2);
+
+        tree.tick(&keys(&[
+            KeyCode::Down,
+            KeyCode::Right,
+            KeyCode::Right,
+            KeyCode::Down,
+        ]));
+        assert_eq!(tree.selected(), Some(&[1, 1][..]));
+        let lines: Vec<String> = tree
+            .render()
+            .to_text()
+            .lines
+            .iter()
+            .map(|line| line.plain().trim_end().to_owned())
+            .collect();
+        assert_eq!(lines, ["  Camera", "▾ Objects", "    Cube", "    Suzanne"]);
+
+        // Rebuilding the tree keeps it expanded.
+        tree.set_roots(roots());
+        assert_eq!(tree.selected(), Some(&[1, 1][..]));
+        assert!(tree.is_expanded(&[1]));
+
+        // Left moves to the parent, then collapses it.
+        tree.tick(&keys(&[KeyCode::Left, KeyCode::Left]));
+        assert_eq!(tree.selected(), Some(&[1][..]));
+        assert!(!tree.is_expanded(&[1]));
+
+        tree.tick(&keys(&[KeyCode::Enter]));
+        assert!(tree.is_expanded(&[1]));
+        assert_eq!(tree.take_activated(), Some(vec![1]));
+    }
+}
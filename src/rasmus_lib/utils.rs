@@ -16,14 +16,32 @@ pub trait StrUtils {
 
     /// Returns the displayed length of `self` in a terminal supporting ansi escape sequences.
     ///
-    /// This counts the visual characters while ignoring ansi escape sequences.
+    /// This counts the terminal columns taken up by the visual characters,
+    /// see `grapheme_width`, while ignoring ansi escape sequences.
     fn visual_len(&self) -> usize;
 
+    /// Cut `self` down to a displayed length of at most `width`, counted like
+    /// `visual_len`. Ansi escape sequences are kept, so formatting is still
+    /// applied and reset correctly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use terminal_renderer::utils::StrUtils;
+    ///
+    /// assert_eq!("\x1b[1mhello\x1b[0m".visual_truncate(3), "\x1b[1mhel\x1b[0m");
+    /// assert_eq!("short".visual_truncate(10), "short");
+    /// assert_eq!("中文".visual_truncate(3), "中");
+    /// ```
+    fn visual_truncate(&self, width: usize) -> String;
+
     /// Returns an iterator over the words of a string slice.
     ///
     /// Words are slices seperated by unicode whitespace.
-    fn words(&self) -> Words;
+    fn words(&self) -> Words<'_>;
 
+    /// The byte position of the closest word boundary left of the byte
+    /// `start_position`, which has to lie on a char boundary.
     fn next_word_boundary_left(&self, start_position: usize) -> usize;
     fn next_word_boundary_right(&self, start_position: usize) -> usize;
 
@@ -46,14 +64,38 @@ impl StrUtils for str {
                 Output::Escape(_) => None,
                 Output::TextBlock(string) => Some(string),
             })
-            .map(|string| {
-                string.graphemes(true).count()
-                    - string.chars().filter(|char| char.is_control()).count()
-            })
+            .flat_map(|string| string.graphemes(true))
+            .map(grapheme_width)
             .sum()
     }
 
-    fn words(&self) -> Words {
+    fn visual_truncate(&self, width: usize) -> String {
+        let mut out = String::with_capacity(self.len());
+        let mut length = 0;
+        // Set once a grapheme didn't fit, so nothing after it is shown.
+        let mut is_full = false;
+
+        for block in self.ansi_parse() {
+            match block {
+                Output::Escape(escape) => out.push_str(&escape.to_string()),
+                Output::TextBlock(string) => {
+                    for grapheme in string.graphemes(true) {
+                        let grapheme_width = grapheme_width(grapheme);
+                        if grapheme_width > 0 && (is_full || length + grapheme_width > width) {
+                            is_full = true;
+                            continue;
+                        }
+                        length += grapheme_width;
+                        out.push_str(grapheme);
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+    fn words(&self) -> Words<'_> {
         Words::new(self)
     }
 
@@ -68,25 +110,21 @@ impl StrUtils for str {
 
         let mut searching_for_word = None;
 
-        let mut position = start_position - 1;
-        for char in search_str.chars().rev() {
-            if char.is_alphanumeric() || char == '_' {
+        for (position, grapheme) in search_str.grapheme_indices(true).rev() {
+            let after = position + grapheme.len();
+            if is_word_grapheme(grapheme) {
                 match searching_for_word {
                     None => searching_for_word = Some(false),
-                    Some(true) => return position + 1,
+                    Some(true) => return after,
                     _ => {}
                 }
             } else {
                 match searching_for_word {
                     None => searching_for_word = Some(true),
-                    Some(false) => return position + 1,
+                    Some(false) => return after,
                     _ => {}
                 }
             }
-
-            if position != 0 {
-                position -= 1;
-            }
         }
 
         0
@@ -100,8 +138,8 @@ impl StrUtils for str {
 
         let mut searching_for_word = None;
 
-        for (position, char) in search_str.chars().enumerate() {
-            if char.is_alphanumeric() || char == '_' {
+        for (position, grapheme) in search_str.grapheme_indices(true) {
+            if is_word_grapheme(grapheme) {
                 match searching_for_word {
                     None => searching_for_word = Some(false),
                     Some(true) => return start_position + position,
@@ -127,7 +165,7 @@ impl StrUtils for str {
         let part_length = self.grapheme_len();
         let mut out: String = self.repeat(length / part_length);
 
-        if length % part_length != 0 {
+        if !length.is_multiple_of(part_length) {
             out.push_str(
                 &self
                     .graphemes(true)
@@ -140,6 +178,68 @@ impl StrUtils for str {
     }
 }
 
+/// Whether `grapheme` is part of a word when looking for word boundaries,
+/// judged by its first char so combining marks stay with their letter.
+fn is_word_grapheme(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .next()
+        .is_some_and(|char| char.is_alphanumeric() || char == '_')
+}
+
+/// The number of terminal columns `grapheme` takes up: two for wide east
+/// asian characters and emoji, none for control characters and one for
+/// everything else.
+///
+/// ```
+/// use terminal_renderer::utils::grapheme_width;
+///
+/// assert_eq!(grapheme_width("a"), 1);
+/// assert_eq!(grapheme_width("中"), 2);
+/// assert_eq!(grapheme_width("\n"), 0);
+/// ```
+pub fn grapheme_width(grapheme: &str) -> usize {
+    let Some(first) = grapheme.chars().next() else {
+        return 0;
+    };
+    if first.is_control() {
+        return 0;
+    }
+    // The variation selector asking for the emoji presentation.
+    if grapheme.contains('\u{fe0f}') {
+        return 2;
+    }
+
+    let is_wide = matches!(
+        first as u32,
+        0x1100..=0x115f
+            | 0x2e80..=0x303e
+            | 0x3041..=0x33ff
+            | 0x3400..=0x4dbf
+            | 0x4e00..=0x9fff
+            | 0xa000..=0xa4cf
+            | 0xac00..=0xd7a3
+            | 0xf900..=0xfaff
+            | 0xfe30..=0xfe4f
+            | 0xff00..=0xff60
+            | 0xffe0..=0xffe6
+            | 0x1f300..=0x1f64f
+            | 0x1f680..=0x1f6ff
+            | 0x1f900..=0x1f9ff
+            | 0x20000..=0x2fffd
+            | 0x30000..=0x3fffd
+    );
+    match is_wide {
+        true => 2,
+        false => 1,
+    }
+}
+
+/// The number of terminal columns `string` takes up, see `grapheme_width`.
+pub fn str_width(string: &str) -> usize {
+    string.graphemes(true).map(grapheme_width).sum()
+}
+
 pub struct Words<'a>(SplitWhitespace<'a>);
 
 impl<'a> Words<'a> {
@@ -415,6 +515,25 @@ pub struct Ref<T> {
     phantom: PhantomData<T>,
 }
 
+// Implemented manually, since deriving would require `T` to implement them.
+impl<T> Clone for Ref<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for Ref<T> {}
+impl<T> PartialEq for Ref<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl<T> Eq for Ref<T> {}
+impl<T> std::hash::Hash for Ref<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
 pub struct RefVec<T> {
     vec: Vec<(u32, Option<T>)>,
     next_id: u32,
@@ -526,14 +645,38 @@ impl<T> RefVec<T> {
         self.vec.remove(index).1
     }
 
-    pub fn iter(&self) -> AnyIter<&T> {
+    pub fn iter(&self) -> AnyIter<'_, &T> {
         let iter = self.vec.iter().filter_map(|(_, item)| item.as_ref());
         AnyIter::new(iter)
     }
-    pub fn iter_mut(&mut self) -> AnyIter<&mut T> {
+    pub fn iter_mut(&mut self) -> AnyIter<'_, &mut T> {
         let iter = self.vec.iter_mut().filter_map(|(_, item)| item.as_mut());
         AnyIter::new(iter)
     }
+
+    /// Like `iter`, but also yields a reference to every item.
+    pub fn iter_refs(&self) -> AnyIter<'_, (Ref<T>, &T)> {
+        let iter = self.vec.iter().filter_map(|(id, item)| {
+            let reference = Ref {
+                id: *id,
+                phantom: PhantomData,
+            };
+            Some((reference, item.as_ref()?))
+        });
+        AnyIter::new(iter)
+    }
+
+    /// Like `iter_mut`, but also yields a reference to every item.
+    pub fn iter_mut_refs(&mut self) -> AnyIter<'_, (Ref<T>, &mut T)> {
+        let iter = self.vec.iter_mut().filter_map(|(id, item)| {
+            let reference = Ref {
+                id: *id,
+                phantom: PhantomData,
+            };
+            Some((reference, item.as_mut()?))
+        });
+        AnyIter::new(iter)
+    }
 }
 
 impl<T> Default for RefVec<T> {
@@ -73,6 +73,23 @@ impl RenderBuffer {
         Some(true)
     }
 
+    /// Returns the color buffer as 8-bit rgb triplets, with rows ordered from
+    /// top to bottom.
+    ///
+    /// Row `0` of the buffer is the bottom of the image, so rows are reversed.
+    pub fn to_rgb_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.get_width() * self.get_height() * 3);
+
+        for row in self.color.iter().rev() {
+            for pixel in row {
+                let color = pixel.normalize().to_byte_rgb();
+                bytes.extend_from_slice(&[color.r, color.g, color.b]);
+            }
+        }
+
+        bytes
+    }
+
     pub fn clear(&mut self) {
         for rows in self.color.iter_mut() {
             for pixel in rows.iter_mut() {
@@ -0,0 +1,373 @@
+use super::*;
+use crate::capabilities::CellSize;
+use crate::input::{MouseButton, MouseEventKind};
+use crate::ui::InputStream;
+use std::f32::consts;
+use std::time::Instant;
+
+/// Pitch is kept just short of straight up or down, where yaw stops being
+/// well defined.
+const MAX_PITCH: f32 = consts::FRAC_PI_2 - 0.01;
+/// Longest time moved for in a single update while holding keys, so the
+/// camera doesn't jump after a hiccup.
+const MAX_UPDATE_SECS: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Rotate around `target`, always looking at it.
+    Orbit,
+    /// Move freely, looking around from the camera's position.
+    Fly,
+}
+
+/// Moves a camera with the keyboard and mouse.
+///
+/// Controlled by the `camera.*` actions of the keymap. By default arrow keys
+/// (and mouse drags) rotate the camera, `+`/`-` (and the scroll wheel) zoom,
+/// and `m` switches between orbiting and flying. While flying, WASD moves
+/// along the view direction and `q`/`e` move down and up.
+///
+/// When the terminal reports key releases, the camera moves smoothly while
+/// keys are held, using `rotate_speed` and `move_speed`. Otherwise every
+/// press (and repeat) moves it by `rotate_step` or `move_step`.
+///
+/// Attach it to a `ScenePanel` by setting its `camera_controller`.
+#[derive(Debug, Clone)]
+pub struct CameraController {
+    pub mode: CameraMode,
+    /// The point orbited around.
+    pub target: Vec3,
+    /// Distance from the target while orbiting.
+    pub distance: f32,
+    /// Rotation around the vertical axis, in radians.
+    pub yaw: f32,
+    /// Rotation up or down, in radians. Positive values look down from above.
+    pub pitch: f32,
+    /// The camera position while flying.
+    pub position: Vec3,
+    /// Radians rotated per key press.
+    pub rotate_step: f32,
+    /// Radians rotated per cell dragged sideways. Dragging up or down rotates
+    /// as much per pixel, so more per cell.
+    pub drag_sensitivity: f32,
+    /// Units moved per key press while flying.
+    pub move_step: f32,
+    /// Radians rotated per second while holding a key.
+    pub rotate_speed: f32,
+    /// Units moved per second while holding a key while flying.
+    pub move_speed: f32,
+    /// Factor the view is scaled by per zoom step.
+    pub zoom_step: f32,
+    last_drag: Option<IVec2>,
+    last_update: Option<Instant>,
+}
+
+impl CameraController {
+    /// Orbit around `target` from `distance` away.
+    pub fn orbit(target: Vec3, distance: f32) -> Self {
+        Self {
+            mode: CameraMode::Orbit,
+            target,
+            distance,
+            yaw: 0.,
+            pitch: 0.,
+            position: target + vec3(0., 0., distance),
+            rotate_step: 10.0_f32.to_radians(),
+            drag_sensitivity: 3.0_f32.to_radians(),
+            move_step: 0.25,
+            rotate_speed: 90.0_f32.to_radians(),
+            move_speed: 2.,
+            zoom_step: 1.2,
+            last_drag: None,
+            last_update: None,
+        }
+    }
+
+    /// Fly around, starting at `position` looking along the negative z axis.
+    pub fn fly(position: Vec3) -> Self {
+        Self {
+            mode: CameraMode::Fly,
+            ..Self::orbit(position - vec3(0., 0., 1.), 1.)
+        }
+    }
+
+    /// The rotation of the camera, looking along its negative z axis.
+    pub fn rotation(&self) -> Quaternion {
+        let yaw = Quaternion::from_axis_angle(Vec3::Y_AXIS, self.yaw);
+        let pitch = Quaternion::from_axis_angle(Vec3::X_AXIS, -self.pitch);
+
+        yaw.rotate_by(pitch)
+    }
+
+    /// The position the camera is placed at.
+    pub fn camera_position(&self) -> Vec3 {
+        match self.mode {
+            CameraMode::Orbit => {
+                self.target + self.rotation().rotate_point(vec3(0., 0., self.distance))
+            }
+            CameraMode::Fly => self.position,
+        }
+    }
+
+    /// Switch between orbiting and flying, without moving the camera.
+    pub fn toggle_mode(&mut self) {
+        match self.mode {
+            CameraMode::Orbit => {
+                self.position = self.camera_position();
+                self.mode = CameraMode::Fly;
+            }
+            CameraMode::Fly => {
+                // Orbit around the point in front of the camera.
+                let forward = self.rotation().rotate_point(vec3(0., 0., -self.distance));
+                self.target = self.position + forward;
+                self.mode = CameraMode::Orbit;
+            }
+        }
+    }
+
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = (self.yaw + yaw) % consts::TAU;
+        self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Move relative to the view direction while flying: `x` to the right,
+    /// `y` up and `z` forward.
+    pub fn move_by(&mut self, offset: Vec3) {
+        let rotation = self.rotation();
+        let movement =
+            rotation.rotate_point(vec3(offset.x, 0., -offset.z)) + Vec3::Y_AXIS * offset.y;
+
+        match self.mode {
+            CameraMode::Orbit => self.target += movement,
+            CameraMode::Fly => self.position += movement,
+        }
+    }
+
+    /// Zoom in by `steps` zoom steps, or out if negative.
+    pub fn zoom(&mut self, camera: &mut OrthographicCamera, steps: i32) {
+        let factor = self.zoom_step.powi(-steps);
+
+        camera.width *= factor;
+        camera.height *= factor;
+    }
+
+    /// Update the controller with the input, consuming the actions and mouse
+    /// events it uses, and move `camera` accordingly. Returns whether
+    /// anything changed.
+    ///
+    /// `cell_size` is the pixel size of the cells the mouse is dragged over.
+    pub fn handle_input(
+        &mut self,
+        input: &InputStream,
+        camera: &mut OrthographicCamera,
+        cell_size: CellSize,
+    ) -> bool {
+        let mut changed = false;
+
+        let now = Instant::now();
+        let elapsed = self
+            .last_update
+            .map_or(MAX_UPDATE_SECS, |last| (now - last).as_secs_f32())
+            .min(MAX_UPDATE_SECS);
+        self.last_update = Some(now);
+
+        let smooth = input.reports_releases();
+        // How much an action moves the camera, in presses or in seconds held.
+        let amount = |name| {
+            let mut presses = 0;
+            while input.consume_action(name) {
+                presses += 1;
+            }
+
+            match smooth {
+                true if input.is_action_held(name) => elapsed,
+                true => 0.,
+                false => presses as f32,
+            }
+        };
+        let (rotate_step, move_step) = match smooth {
+            true => (self.rotate_speed, self.move_speed),
+            false => (self.rotate_step, self.move_step),
+        };
+
+        if input.consume_action("camera.toggle_mode") {
+            self.toggle_mode();
+            changed = true;
+        }
+
+        // Up moves the camera up around the target while orbiting, but looks
+        // up while flying.
+        let up_pitch = match self.mode {
+            CameraMode::Orbit => rotate_step,
+            CameraMode::Fly => -rotate_step,
+        };
+
+        let mut yaw = (amount("camera.rotate_right") - amount("camera.rotate_left")) * rotate_step;
+        let mut pitch = (amount("camera.rotate_up") - amount("camera.rotate_down")) * up_pitch;
+
+        let mut movement = vec3(0., 0., 0.);
+        let bindings = [
+            ("camera.forward", vec3(0., 0., 1.)),
+            ("camera.back", vec3(0., 0., -1.)),
+            ("camera.left", vec3(-1., 0., 0.)),
+            ("camera.right", vec3(1., 0., 0.)),
+            ("camera.rise", vec3(0., 1., 0.)),
+            ("camera.sink", vec3(0., -1., 0.)),
+        ];
+        if self.mode == CameraMode::Fly {
+            for (name, direction) in bindings {
+                movement += direction * (amount(name) * move_step);
+            }
+        } else {
+            // Moving orbits like rotating while orbiting.
+            yaw += (amount("camera.right") - amount("camera.left")) * rotate_step;
+            pitch += (amount("camera.forward") - amount("camera.back")) * up_pitch;
+        }
+
+        while let Some(event) = input.consume_mouse(|event| {
+            matches!(
+                event.kind,
+                MouseEventKind::Down(MouseButton::Left)
+                    | MouseEventKind::Drag(MouseButton::Left)
+                    | MouseEventKind::Up(MouseButton::Left)
+            )
+        }) {
+            match event.kind {
+                MouseEventKind::Down(_) => self.last_drag = Some(event.position),
+                MouseEventKind::Drag(_) => {
+                    if let Some(last) = self.last_drag {
+                        let delta = event.position - last;
+                        yaw -= delta.x as f32 * self.drag_sensitivity;
+                        pitch += delta.y as f32 * self.drag_sensitivity / cell_size.aspect_ratio();
+                    }
+                    self.last_drag = Some(event.position);
+                }
+                _ => self.last_drag = None,
+            }
+        }
+
+        let mut zoom = -input.consume_scroll();
+        while input.consume_action("camera.zoom_in") {
+            zoom += 1;
+        }
+        while input.consume_action("camera.zoom_out") {
+            zoom -= 1;
+        }
+
+        if yaw != 0. || pitch != 0. {
+            self.rotate(yaw, pitch);
+            changed = true;
+        }
+        if movement != vec3(0., 0., 0.) {
+            self.move_by(movement);
+            changed = true;
+        }
+        if zoom != 0 {
+            self.zoom(camera, zoom);
+            changed = true;
+        }
+
+        self.apply(camera);
+        changed
+    }
+
+    /// Place `camera` according to the controller.
+    pub fn apply(&self, camera: &mut OrthographicCamera) {
+        camera.position = self.camera_position();
+        camera.rotation = self.rotation();
+    }
+}
+
+mod tests {
+    #[test]
+    fn orbit_keeps_target_centered_test() {
+        use super::*;
+        use approx::*;
+
+        let mut camera = OrthographicCamera::new(Transform::identity(), 8., 8., 100., 0.01);
+        let mut controller = CameraController::orbit(vec3(1., 2., 3.), 5.);
+        controller.rotate(40.0_f32.to_radians(), 30.0_f32.to_radians());
+        controller.apply(&mut camera);
+
+        let projected = camera.project_point(controller.target);
+        assert_abs_diff_eq!(projected.x, 0., epsilon = 1e-4);
+        assert_abs_diff_eq!(projected.y, 0., epsilon = 1e-4);
+
+        // Looking down from above, without rolling.
+        assert!(camera.position.y > controller.target.y);
+        assert_abs_diff_eq!(camera.rotation.right().y, 0., epsilon = 1e-4);
+        assert_abs_diff_eq!(
+            (camera.position - controller.target).magnitude(),
+            5.,
+            epsilon = 1e-4
+        );
+    }
+
+    #[test]
+    fn fly_moves_forward_test() {
+        use super::*;
+        use crate::input::keymap::{Keymap, KeymapState};
+        use crate::input::{InputEvent, KeyCode, KeyModifiers};
+        use approx::*;
+
+        let mut camera = OrthographicCamera::new(Transform::identity(), 8., 8., 100., 0.01);
+        let mut controller = CameraController::fly(vec3(0., 0., 2.));
+
+        let mut input =
+            InputStream::from_events([InputEvent::new(KeyCode::Char('w'), KeyModifiers::NONE)]);
+        input.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+        assert!(controller.handle_input(&input, &mut camera, CellSize::FALLBACK));
+        assert!(!input.contains_key_code(KeyCode::Char('w')));
+
+        assert_abs_diff_eq!(camera.position, vec3(0., 0., 1.75), epsilon = 1e-4);
+    }
+
+    #[test]
+    fn held_keys_move_smoothly_test() {
+        use super::*;
+        use crate::input::keymap::{Keymap, KeymapState};
+        use crate::input::{InputEvent, KeyCode, KeyEventKind, KeyModifiers};
+        use approx::*;
+
+        let mut camera = OrthographicCamera::new(Transform::identity(), 8., 8., 100., 0.01);
+        let mut controller = CameraController::fly(vec3(0., 0., 2.));
+
+        // `w` was pressed in an earlier tick and is still held.
+        let repeat =
+            InputEvent::new_with_kind(KeyCode::Char('w'), KeyModifiers::NONE, KeyEventKind::Repeat);
+        let mut input = InputStream::from_events_with_releases([repeat], [KeyCode::Char('w')]);
+        input.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+
+        controller.last_update = Some(Instant::now() - std::time::Duration::from_millis(50));
+        assert!(controller.handle_input(&input, &mut camera, CellSize::FALLBACK));
+
+        // Moved for 50ms, rather than a whole step.
+        assert_abs_diff_eq!(
+            camera.position.z,
+            2. - 0.05 * controller.move_speed,
+            epsilon = 0.02
+        );
+    }
+
+    #[test]
+    fn drag_rotates_per_pixel_test() {
+        use super::*;
+        use crate::input::{KeyModifiers, MouseEvent};
+        use approx::*;
+
+        let mut camera = OrthographicCamera::new(Transform::identity(), 8., 8., 100., 0.01);
+        let mut controller = CameraController::orbit(vec3(0., 0., 0.), 5.);
+
+        let event = |kind, x, y| MouseEvent::new(kind, ivec2(x, y), KeyModifiers::NONE);
+        let input = InputStream::from_mouse_events([
+            event(MouseEventKind::Down(MouseButton::Left), 0, 0),
+            event(MouseEventKind::Drag(MouseButton::Left), 1, 1),
+        ]);
+        // Cells three times as tall as they are wide.
+        let cell_size = CellSize::new(8, 24).unwrap();
+        assert!(controller.handle_input(&input, &mut camera, cell_size));
+
+        assert_abs_diff_eq!(controller.yaw, -controller.drag_sensitivity);
+        assert_abs_diff_eq!(controller.pitch, 3. * controller.drag_sensitivity);
+    }
+}
@@ -107,7 +107,7 @@ impl From<Hsl> for Rgb {
 
 impl From<Vec3> for Rgb {
     fn from(vec: Vec3) -> Self {
-        rgb(vec.x as f32, vec.y as f32, vec.z as f32).normalize()
+        rgb(vec.x, vec.y, vec.z).normalize()
     }
 }
 
@@ -145,7 +145,7 @@ impl From<Rgb> for ByteRgb {
 
 impl From<Hsl> for ByteRgb {
     fn from(hsl: Hsl) -> Self {
-        hsl.into()
+        hsl.to_rgb().into()
     }
 }
 
@@ -248,7 +248,7 @@ impl From<Rgb> for Hsl {
 
 impl From<Vec3> for Hsl {
     fn from(vec: Vec3) -> Self {
-        hsl(vec.x as f32, vec.y as f32, vec.z as f32).normalize()
+        hsl(vec.x, vec.y, vec.z).normalize()
     }
 }
 
@@ -0,0 +1,315 @@
+//! Exporting of render buffers to image files, for rendering without a
+//! terminal and for comparing renders in tests.
+
+use super::*;
+use crate::encoding::png;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Binary portable pixmap (`.ppm`) for colors, or graymap (`.pgm`) for
+    /// depth.
+    Netpbm,
+    Png,
+}
+
+impl ImageFormat {
+    /// Guess the format from the extension of `path`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "ppm" | "pgm" | "pnm" => Some(Self::Netpbm),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+}
+
+impl RenderBuffer {
+    /// Returns the depth buffer as 8-bit grayscale values, with rows ordered
+    /// from top to bottom.
+    ///
+    /// Depths are normalized over the range of drawn pixels, with the
+    /// nearest pixels being white. Pixels without anything drawn are black.
+    pub fn to_depth_bytes(&self) -> Vec<u8> {
+        let drawn = || {
+            self.depth
+                .iter()
+                .flatten()
+                .copied()
+                .filter(|depth| *depth != f32::MAX && depth.is_finite())
+        };
+
+        let min = drawn().fold(f32::INFINITY, f32::min);
+        let max = drawn().fold(f32::NEG_INFINITY, f32::max);
+        let range = (max - min).max(f32::EPSILON);
+
+        self.depth
+            .iter()
+            .rev()
+            .flatten()
+            .map(|depth| {
+                if *depth == f32::MAX || !depth.is_finite() {
+                    return 0;
+                }
+
+                // Leave some room so the farthest pixels don't blend in with
+                // the background.
+                let normalized = 1. - (depth - min) / range;
+                (32. + normalized * 223.).round() as u8
+            })
+            .collect()
+    }
+
+    /// Write the color buffer as a binary ppm image (P6).
+    pub fn write_ppm(&self, writer: &mut impl Write) -> io::Result<()> {
+        write!(
+            writer,
+            "P6\n{} {}\n255\n",
+            self.get_width(),
+            self.get_height()
+        )?;
+        writer.write_all(&self.to_rgb_bytes())
+    }
+
+    /// Write the normalized depth buffer as a binary pgm image (P5), see
+    /// `to_depth_bytes`.
+    pub fn write_depth_pgm(&self, writer: &mut impl Write) -> io::Result<()> {
+        write!(
+            writer,
+            "P5\n{} {}\n255\n",
+            self.get_width(),
+            self.get_height()
+        )?;
+        writer.write_all(&self.to_depth_bytes())
+    }
+
+    /// Write the color buffer as a png image.
+    pub fn write_png(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&png::encode(
+            self.get_width() as u32,
+            self.get_height() as u32,
+            png::ColorType::Rgb,
+            &self.to_rgb_bytes(),
+        ))
+    }
+
+    /// Write the normalized depth buffer as a grayscale png image, see
+    /// `to_depth_bytes`.
+    pub fn write_depth_png(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&png::encode(
+            self.get_width() as u32,
+            self.get_height() as u32,
+            png::ColorType::Grayscale,
+            &self.to_depth_bytes(),
+        ))
+    }
+
+    /// Save the color buffer to `path`, using the format matching its
+    /// extension.
+    pub fn save_color(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+
+        match format_for(path)? {
+            ImageFormat::Netpbm => self.write_ppm(&mut file)?,
+            ImageFormat::Png => self.write_png(&mut file)?,
+        }
+
+        file.flush()
+    }
+
+    /// Save the normalized depth buffer to `path`, using the format matching
+    /// its extension.
+    pub fn save_depth(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+
+        match format_for(path)? {
+            ImageFormat::Netpbm => self.write_depth_pgm(&mut file)?,
+            ImageFormat::Png => self.write_depth_png(&mut file)?,
+        }
+
+        file.flush()
+    }
+}
+
+fn format_for(path: &Path) -> io::Result<ImageFormat> {
+    ImageFormat::from_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported image extension: {}", path.display()),
+        )
+    })
+}
+
+/// A decoded binary ppm image, used to compare renders with reference images.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PpmImage {
+    pub width: usize,
+    pub height: usize,
+    /// Rgb triplets, from the top row to the bottom.
+    pub data: Vec<u8>,
+}
+
+impl PpmImage {
+    /// Parse a binary ppm (P6) image with a max value of 255.
+    pub fn parse(bytes: &[u8]) -> io::Result<Self> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+
+        let mut fields = Vec::new();
+        let mut position = 0;
+
+        // The header consists of four whitespace separated fields, where `#`
+        // starts a comment.
+        while fields.len() < 4 {
+            while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            if position < bytes.len() && bytes[position] == b'#' {
+                while position < bytes.len() && bytes[position] != b'\n' {
+                    position += 1;
+                }
+                continue;
+            }
+
+            let start = position;
+            while position < bytes.len() && !bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            if start == position {
+                return Err(invalid("truncated ppm header"));
+            }
+            fields.push(&bytes[start..position]);
+        }
+        // A single whitespace character separates the header from the data.
+        position += 1;
+
+        if fields[0] != b"P6" {
+            return Err(invalid("not a binary ppm image"));
+        }
+        let parse_number = |field: &[u8]| {
+            std::str::from_utf8(field)
+                .ok()
+                .and_then(|string| string.parse::<usize>().ok())
+                .ok_or_else(|| invalid("invalid number in ppm header"))
+        };
+        let width = parse_number(fields[1])?;
+        let height = parse_number(fields[2])?;
+        if parse_number(fields[3])? != 255 {
+            return Err(invalid(
+                "only ppm images with a max value of 255 are supported",
+            ));
+        }
+
+        let data = bytes
+            .get(position..position + width * height * 3)
+            .ok_or_else(|| invalid("truncated ppm data"))?
+            .to_vec();
+
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    pub fn from_buffer(buffer: &RenderBuffer) -> Self {
+        Self {
+            width: buffer.get_width(),
+            height: buffer.get_height(),
+            data: buffer.to_rgb_bytes(),
+        }
+    }
+
+    /// Returns the number of color channels differing by more than
+    /// `tolerance`, or `None` if the image sizes differ.
+    pub fn count_differences(&self, other: &Self, tolerance: u8) -> Option<usize> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+
+        Some(
+            self.data
+                .iter()
+                .zip(other.data.iter())
+                .filter(|(a, b)| a.abs_diff(**b) > tolerance)
+                .count(),
+        )
+    }
+}
+
+mod tests {
+    /// Renders the test cube and compares it with the reference image in
+    /// `assets/golden`. Run with `UPDATE_GOLDEN=1` to regenerate it after
+    /// intentional changes to the rasterizer.
+    #[test]
+    fn golden_cube_render_test() {
+        use crate::math::*;
+        use crate::render_3d::*;
+        use crate::test_data;
+        use std::num::NonZeroUsize;
+
+        const GOLDEN_PATH: &str =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/assets/golden/cube_48x48.ppm");
+
+        let mut scene = Scene::new();
+        scene.camera = OrthographicCamera::new(Transform::identity(), 4., 4., 100., 0.01);
+        scene.camera.position += vec3(0., 0., 2.);
+
+        let mut transform = Transform::identity();
+        transform.rotate_mut(Quaternion::from_euler_angles(
+            25.0_f32.to_radians(),
+            45.0_f32.to_radians(),
+            0.,
+        ));
+        scene.add_object(SceneObject::Object(Object::new(
+            ObjMeshLoader::load(test_data::CUBE_OBJ_FILE).unwrap(),
+            transform,
+            hsl(0.5, 1., 0.5).into(),
+        )));
+
+        let mut renderer = Renderer::with_size(
+            NonZeroUsize::new(48).unwrap(),
+            NonZeroUsize::new(48).unwrap(),
+        );
+        renderer.render_scene(&scene);
+
+        let image = PpmImage::from_buffer(renderer.buffer());
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            renderer.buffer().save_color(GOLDEN_PATH).unwrap();
+        }
+
+        let golden = PpmImage::parse(&std::fs::read(GOLDEN_PATH).unwrap()).unwrap();
+
+        let differences = image.count_differences(&golden, 2).expect("size changed");
+        assert!(
+            differences <= 8,
+            "render differs from golden image in {} channels",
+            differences
+        );
+    }
+
+    #[test]
+    fn ppm_round_trip_test() {
+        use crate::render_3d::*;
+        use std::num::NonZeroUsize;
+
+        let mut buffer =
+            RenderBuffer::new(NonZeroUsize::new(3).unwrap(), NonZeroUsize::new(2).unwrap());
+        buffer.set_pixel_color(crate::math::uvec2(1, 0), rgb(1., 0., 0.), 1.);
+
+        let mut bytes = Vec::new();
+        buffer.write_ppm(&mut bytes).unwrap();
+        let image = PpmImage::parse(&bytes).unwrap();
+
+        assert_eq!(image, PpmImage::from_buffer(&buffer));
+        // Row 0 is the bottom row.
+        assert_eq!(&image.data[9..15], &[0, 0, 0, 255, 0, 0]);
+    }
+}
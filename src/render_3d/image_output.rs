@@ -0,0 +1,162 @@
+//! Output of render buffers through terminal graphics protocols, letting a
+//! scene be displayed at the terminal's native pixel resolution.
+
+use super::*;
+use crate::capabilities::TerminalCapabilities;
+use crate::encoding::{base64, png};
+
+/// Maximum size of a single kitty graphics escape payload.
+///
+/// Source: https://sw.kovidgoyal.net/kitty/graphics-protocol/#remote-client
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    /// The kitty graphics protocol.
+    ///
+    /// Source: https://sw.kovidgoyal.net/kitty/graphics-protocol/
+    Kitty,
+    /// iTerm2's inline image protocol (OSC 1337).
+    ///
+    /// Source: https://iterm2.com/documentation-images.html
+    Iterm2,
+}
+
+/// Describes how a render buffer should be displayed in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneOutput {
+    /// Every pixel is drawn as two colored character cells.
+    Cells,
+    /// The buffer is sent as an image, scaled to cover `columns` by `rows`
+    /// character cells.
+    Image {
+        protocol: ImageProtocol,
+        columns: u16,
+        rows: u16,
+        /// Id used to replace the previously sent image in place. Only used
+        /// by the kitty protocol.
+        image_id: u32,
+    },
+}
+
+impl SceneOutput {
+    pub fn image(protocol: ImageProtocol, columns: u16, rows: u16) -> Self {
+        Self::Image {
+            protocol,
+            columns,
+            rows,
+            image_id: 1,
+        }
+    }
+
+    /// Pick the best output supported by the terminal, using an image
+    /// protocol covering `columns` by `rows` cells if one is available.
+    pub fn from_capabilities(capabilities: &TerminalCapabilities, columns: u16, rows: u16) -> Self {
+        if capabilities.kitty_graphics {
+            Self::image(ImageProtocol::Kitty, columns, rows)
+        } else if capabilities.iterm2_images {
+            Self::image(ImageProtocol::Iterm2, columns, rows)
+        } else {
+            Self::Cells
+        }
+    }
+}
+
+/// Get the escape sequence displaying `buffer` using `protocol` at the
+/// current cursor position, covering `columns` by `rows` cells.
+///
+/// The cursor is not moved by the kitty protocol. Other protocols may move
+/// it, so save and restore the position if that matters.
+pub fn get_image_sequence(
+    buffer: &RenderBuffer,
+    protocol: ImageProtocol,
+    columns: u16,
+    rows: u16,
+    image_id: u32,
+) -> String {
+    match protocol {
+        ImageProtocol::Kitty => get_kitty_image(buffer, image_id, columns, rows),
+        ImageProtocol::Iterm2 => get_iterm2_image(buffer, columns, rows),
+    }
+}
+
+/// Get the kitty graphics escape sequences transmitting and displaying
+/// `buffer` as raw rgb data.
+///
+/// Sending an image with an `image_id` that is already in use replaces the
+/// previous image and its placement, so animations don't flicker.
+pub fn get_kitty_image(buffer: &RenderBuffer, image_id: u32, columns: u16, rows: u16) -> String {
+    let (width, height) = (buffer.get_width(), buffer.get_height());
+    let payload = base64::encode(&buffer.to_rgb_bytes());
+
+    let mut out = String::with_capacity(payload.len() + payload.len() / KITTY_CHUNK_SIZE * 16 + 96);
+
+    // `payload` only contains ascii, so splitting on byte boundaries is fine.
+    let mut chunks = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).peekable();
+    let mut is_first = true;
+    while let Some(chunk) = chunks.next() {
+        let more = chunks.peek().is_some() as u8;
+
+        out.push_str("\x1b_G");
+        if is_first {
+            out.push_str(&format!(
+                "a=T,f=24,s={},v={},i={},p=1,c={},r={},C=1,q=2,",
+                width, height, image_id, columns, rows
+            ));
+            is_first = false;
+        }
+        out.push_str(&format!("m={};", more));
+        out.push_str(std::str::from_utf8(chunk).expect("base64 is always valid utf-8"));
+        out.push_str("\x1b\\");
+    }
+
+    out
+}
+
+/// Get the escape sequence removing the image with `image_id` (and all its
+/// placements) when using the kitty graphics protocol.
+pub fn get_kitty_delete_image(image_id: u32) -> String {
+    format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", image_id)
+}
+
+/// Get the iTerm2 inline image escape sequence displaying `buffer` as a png
+/// stretched over `columns` by `rows` cells.
+pub fn get_iterm2_image(buffer: &RenderBuffer, columns: u16, rows: u16) -> String {
+    let png = png::encode(
+        buffer.get_width() as u32,
+        buffer.get_height() as u32,
+        png::ColorType::Rgb,
+        &buffer.to_rgb_bytes(),
+    );
+
+    let mut out = format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=0:",
+        png.len(),
+        columns,
+        rows
+    );
+    base64::encode_into(&png, &mut out);
+    out.push('\x07');
+
+    out
+}
+
+mod tests {
+    #[test]
+    fn kitty_chunking_test() {
+        use crate::render_3d::*;
+        use std::num::NonZeroUsize;
+
+        // 64 * 32 * 3 bytes => 8192 base64 characters => 2 chunks
+        let buffer = RenderBuffer::new(
+            NonZeroUsize::new(64).unwrap(),
+            NonZeroUsize::new(32).unwrap(),
+        );
+        let sequence = get_kitty_image(&buffer, 7, 10, 5);
+
+        assert_eq!(sequence.matches("\x1b_G").count(), 2);
+        assert!(sequence.starts_with("\x1b_Ga=T,f=24,s=64,v=32,i=7,p=1,c=10,r=5,C=1,q=2,m=1;"));
+        assert!(sequence.contains("\x1b\\\x1b_Gm=0;"));
+        assert!(sequence.ends_with("\x1b\\"));
+    }
+}
@@ -0,0 +1,683 @@
+use crate::input::{KeyCode, MouseButton, MouseEventKind};
+use crate::ui::{Alignment, Column, Line, Panel, Table, Tree, TreeNode};
+use std::any;
+
+use super::*;
+
+/// The values of a camera shown by a `SceneInspector`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraSnapshot {
+    pub position: Vec3,
+    /// Euler angles in degrees.
+    pub rotation: Vec3,
+    pub width: f32,
+    pub height: f32,
+    pub near_plane: f32,
+    pub far_plane: f32,
+}
+
+/// The values of an object shown by a `SceneInspector`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObjectSnapshot {
+    pub position: Vec3,
+    /// Euler angles in degrees.
+    pub rotation: Vec3,
+    pub scale: Vec3,
+    pub color: Rgb,
+    pub vertices: usize,
+    pub triangles: usize,
+    pub edges: usize,
+    /// The smallest and largest coordinates of the vertices, before they're
+    /// transformed. `None` for meshes without vertices.
+    pub bounds: Option<(Vec3, Vec3)>,
+}
+
+/// A copy of the values of a `Scene` a `SceneInspector` shows.
+///
+/// The inspector can't borrow the scene of a `ScenePanel`, as both are
+/// panels of the gui, so a ticker takes a snapshot of the scene every tick
+/// and hands it to the inspector instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneSnapshot {
+    pub camera: CameraSnapshot,
+    pub light_direction: Vec3,
+    pub objects: Vec<ObjectSnapshot>,
+}
+
+impl SceneSnapshot {
+    pub fn new(scene: &Scene) -> Self {
+        let camera = &scene.camera;
+        let objects = scene
+            .iter()
+            .map(|object| {
+                let SceneObject::Object(object) = object;
+                let transform = &object.transform;
+                let mesh = &object.mesh;
+
+                let bounds = mesh.verts_iter().fold(None, |bounds, vert| match bounds {
+                    None => Some((*vert, *vert)),
+                    Some((min, max)) => Some((
+                        vec3(min.x.min(vert.x), min.y.min(vert.y), min.z.min(vert.z)),
+                        vec3(max.x.max(vert.x), max.y.max(vert.y), max.z.max(vert.z)),
+                    )),
+                });
+
+                ObjectSnapshot {
+                    position: transform.position,
+                    rotation: degrees(transform.rotation),
+                    scale: transform.scale,
+                    color: object.color,
+                    vertices: mesh.verts_iter().len(),
+                    triangles: mesh.triangles_iter().count(),
+                    edges: mesh.edges_iter().count(),
+                    bounds,
+                }
+            })
+            .collect();
+
+        Self {
+            camera: CameraSnapshot {
+                position: camera.position,
+                rotation: degrees(camera.rotation),
+                width: camera.width,
+                height: camera.height,
+                near_plane: camera.near_plane,
+                far_plane: camera.far_plane,
+            },
+            light_direction: scene.light_direction,
+            objects,
+        }
+    }
+}
+
+/// The euler angles of `rotation`, in degrees.
+fn degrees(rotation: Quaternion) -> Vec3 {
+    let angles = rotation.to_euler_angles();
+    vec3(
+        angles.x.to_degrees(),
+        angles.y.to_degrees(),
+        angles.z.to_degrees(),
+    )
+}
+
+/// What is selected in the tree of a `SceneInspector`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InspectorSelection {
+    Scene,
+    Camera,
+    Light,
+    Objects,
+    Object(usize),
+}
+
+impl InspectorSelection {
+    /// The selection for a path in the tree.
+    fn from_path(path: &[usize]) -> Option<Self> {
+        let selection = match path {
+            [0] => Self::Scene,
+            [0, 0] => Self::Camera,
+            [0, 1] => Self::Light,
+            [0, 2] => Self::Objects,
+            [0, 2, index] => Self::Object(*index),
+            _ => return None,
+        };
+        Some(selection)
+    }
+}
+
+/// What a `SceneEdit` changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditTarget {
+    Camera,
+    /// The object at this position in `Scene::iter`.
+    Object(usize),
+}
+
+/// A value of the camera or an object that can be changed in a
+/// `SceneInspector`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditProperty {
+    Position,
+    /// Euler angles in degrees, as shown by `SceneSnapshot`. A step changes
+    /// the angle of its component alone, with the y angle kept short of ±90°
+    /// where the x and z angles would turn around the same axis.
+    Rotation,
+    Scale,
+    /// The hue, saturation and lightness of an object's color.
+    Color,
+    /// The width and height of the camera.
+    Size,
+    /// The near and far plane of the camera.
+    Clip,
+}
+
+impl EditProperty {
+    /// How much `component` changes per step.
+    pub fn step(self, component: usize) -> f32 {
+        match (self, component) {
+            (EditProperty::Position | EditProperty::Scale, _) => 0.1,
+            (EditProperty::Rotation, _) => 5.,
+            (EditProperty::Color, 0) => 0.02,
+            (EditProperty::Color, _) => 0.05,
+            (EditProperty::Size, _) => 0.5,
+            (EditProperty::Clip, 0) => 0.01,
+            (EditProperty::Clip, _) => 1.,
+        }
+    }
+
+    /// The number of values the property has.
+    fn components(self) -> usize {
+        match self {
+            EditProperty::Size | EditProperty::Clip => 2,
+            _ => 3,
+        }
+    }
+}
+
+/// A change made in a `SceneInspector`, to be applied to the scene it shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneEdit {
+    pub target: EditTarget,
+    pub property: EditProperty,
+    /// Which value of the property changes, like 1 for the y coordinate of a
+    /// position.
+    pub component: usize,
+    pub delta: f32,
+}
+
+impl SceneEdit {
+    /// Change the value in `scene`. Edits of objects that aren't in the
+    /// scene, or of properties the target doesn't have, do nothing.
+    pub fn apply(&self, scene: &mut Scene) {
+        let offset = axis(self.component) * self.delta;
+
+        match self.target {
+            EditTarget::Camera => {
+                let camera = &mut scene.camera;
+                match self.property {
+                    EditProperty::Position => camera.position += offset,
+                    EditProperty::Rotation => {
+                        camera.rotation = self.rotated(camera.rotation);
+                    }
+                    EditProperty::Size => {
+                        let size = match self.component {
+                            0 => &mut camera.width,
+                            _ => &mut camera.height,
+                        };
+                        *size = (*size + self.delta).max(MIN_CAMERA_SIZE);
+                    }
+                    EditProperty::Clip => match self.component {
+                        0 => {
+                            camera.near_plane = (camera.near_plane + self.delta)
+                                .clamp(MIN_NEAR_PLANE, camera.far_plane)
+                        }
+                        _ => {
+                            camera.far_plane =
+                                (camera.far_plane + self.delta).max(camera.near_plane)
+                        }
+                    },
+                    EditProperty::Scale | EditProperty::Color => {}
+                }
+            }
+            EditTarget::Object(index) => {
+                let Some(SceneObject::Object(object)) = scene.iter_mut().nth(index) else {
+                    return;
+                };
+                let transform = &mut object.transform;
+                match self.property {
+                    EditProperty::Position => transform.position += offset,
+                    EditProperty::Rotation => {
+                        transform.rotation = self.rotated(transform.rotation);
+                    }
+                    EditProperty::Scale => transform.scale += offset,
+                    EditProperty::Color => {
+                        let mut color = object.color.to_hsl();
+                        match self.component {
+                            0 => color.h += self.delta,
+                            1 => color.s += self.delta,
+                            _ => color.l += self.delta,
+                        }
+                        object.color = color.normalize().to_rgb();
+                    }
+                    EditProperty::Size | EditProperty::Clip => {}
+                }
+            }
+        }
+    }
+
+    /// `rotation` with the delta added to the euler angle of the component.
+    fn rotated(&self, rotation: Quaternion) -> Quaternion {
+        let mut angles = degrees(rotation) + axis(self.component) * self.delta;
+        angles.y = angles.y.clamp(-MAX_PITCH, MAX_PITCH);
+
+        Quaternion::from_euler_angles(
+            angles.x.to_radians(),
+            angles.y.to_radians(),
+            angles.z.to_radians(),
+        )
+    }
+}
+
+/// The largest y angle, in degrees, rotation edits go to. At 90° the x and z
+/// angles would turn around the same axis, and edits of them would be lost.
+const MAX_PITCH: f32 = 89.;
+
+/// The smallest the camera can be made by edits, as it can't show anything
+/// without a size.
+const MIN_CAMERA_SIZE: f32 = 0.1;
+const MIN_NEAR_PLANE: f32 = 0.001;
+
+fn axis(component: usize) -> Vec3 {
+    match component {
+        0 => Vec3::X_AXIS,
+        1 => Vec3::Y_AXIS,
+        _ => Vec3::Z_AXIS,
+    }
+}
+
+/// Browses the objects of a scene in a tree, with the values of the selected
+/// one in a table below it.
+///
+/// The scene is shown as it was in the last snapshot passed to `update`.
+/// Keys go to the tree or the table, `t` switches between them, as does
+/// clicking one of them.
+///
+/// The transform and color of objects and the camera's values can be
+/// changed in the table: `+` and `-` change the value in the selected row
+/// and column by a step, `]` and `[` by ten steps. The property column
+/// changes the first value. The changes are collected as `SceneEdit`s, which
+/// are picked up with `take_edits` and applied to the scene.
+#[derive(Debug)]
+pub struct SceneInspector {
+    tree: Tree,
+    table: Table,
+    /// What the rows of the table can change, by their index.
+    properties: Vec<Option<EditProperty>>,
+    edits: Vec<SceneEdit>,
+    snapshot: Option<SceneSnapshot>,
+    /// Whether keys go to the table, rather than the tree.
+    table_focused: bool,
+    focused: bool,
+    tree_height: u32,
+}
+
+impl SceneInspector {
+    pub fn new() -> Self {
+        let number = |title| Column::new(title, 9).with_alignment(Alignment::Right);
+        let table = Table::new(vec![
+            Column::new("Property", 10),
+            number("X"),
+            number("Y"),
+            number("Z"),
+        ]);
+
+        let mut tree = Tree::new(Vec::new());
+        tree.set_expanded(&[0], true);
+        tree.set_expanded(&[0, 2], true);
+
+        Self {
+            tree,
+            table,
+            properties: Vec::new(),
+            edits: Vec::new(),
+            snapshot: None,
+            table_focused: false,
+            focused: false,
+            tree_height: 1,
+        }
+    }
+
+    /// Show the values in `snapshot`.
+    pub fn update(&mut self, snapshot: SceneSnapshot) {
+        if self.snapshot.as_ref() == Some(&snapshot) {
+            return;
+        }
+
+        let objects = snapshot
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(index, object)| {
+                TreeNode::new(format!("Object {} ({} tris)", index, object.triangles))
+            })
+            .collect();
+        self.tree
+            .set_roots(vec![TreeNode::new("Scene").with_children(vec![
+                TreeNode::new("Camera"),
+                TreeNode::new("Light"),
+                TreeNode::new("Objects").with_children(objects),
+            ])]);
+
+        self.snapshot = Some(snapshot);
+        self.update_table();
+    }
+
+    /// What the table shows the values of.
+    pub fn selection(&self) -> Option<InspectorSelection> {
+        InspectorSelection::from_path(self.tree.selected()?)
+    }
+
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    /// The changes made since the last call.
+    pub fn take_edits(&mut self) -> Vec<SceneEdit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Change the selected value by `steps` steps.
+    fn edit(&mut self, steps: f32) {
+        let target = match self.selection() {
+            Some(InspectorSelection::Camera) => EditTarget::Camera,
+            Some(InspectorSelection::Object(index)) => EditTarget::Object(index),
+            _ => return,
+        };
+        let property = self
+            .table
+            .selected_row()
+            .and_then(|row| self.properties.get(row).copied().flatten());
+        let Some(property) = property else {
+            return;
+        };
+
+        let component = self.table.selected_column().saturating_sub(1);
+        if component < property.components() {
+            self.edits.push(SceneEdit {
+                target,
+                property,
+                component,
+                delta: steps * property.step(component),
+            });
+        }
+    }
+
+    fn update_table(&mut self) {
+        let Some(snapshot) = &self.snapshot else {
+            return;
+        };
+
+        let rows: Vec<(Option<EditProperty>, Vec<Line>)> = match self.selection() {
+            Some(InspectorSelection::Scene) => {
+                let objects = &snapshot.objects;
+                vec![
+                    (None, scalar_row("Objects", objects.len())),
+                    (
+                        None,
+                        scalar_row("Vertices", objects.iter().map(|o| o.vertices).sum()),
+                    ),
+                    (
+                        None,
+                        scalar_row("Triangles", objects.iter().map(|o| o.triangles).sum()),
+                    ),
+                ]
+            }
+            Some(InspectorSelection::Camera) => {
+                let camera = &snapshot.camera;
+                vec![
+                    (
+                        Some(EditProperty::Position),
+                        vec3_row("Position", camera.position),
+                    ),
+                    (
+                        Some(EditProperty::Rotation),
+                        vec3_row("Rotation", camera.rotation),
+                    ),
+                    (
+                        Some(EditProperty::Size),
+                        row("Size", [camera.width, camera.height]),
+                    ),
+                    (
+                        Some(EditProperty::Clip),
+                        row("Clip", [camera.near_plane, camera.far_plane]),
+                    ),
+                ]
+            }
+            Some(InspectorSelection::Light) => {
+                vec![(None, vec3_row("Direction", snapshot.light_direction))]
+            }
+            Some(InspectorSelection::Objects) => snapshot
+                .objects
+                .iter()
+                .enumerate()
+                .map(|(index, object)| {
+                    (
+                        None,
+                        vec3_row(&format!("Object {}", index), object.position),
+                    )
+                })
+                .collect(),
+            Some(InspectorSelection::Object(index)) => match snapshot.objects.get(index) {
+                Some(object) => {
+                    let color = object.color.to_hsl();
+                    let mut rows = vec![
+                        (
+                            Some(EditProperty::Position),
+                            vec3_row("Position", object.position),
+                        ),
+                        (
+                            Some(EditProperty::Rotation),
+                            vec3_row("Rotation", object.rotation),
+                        ),
+                        (Some(EditProperty::Scale), vec3_row("Scale", object.scale)),
+                        (
+                            Some(EditProperty::Color),
+                            row("Color HSL", [color.h, color.s, color.l]),
+                        ),
+                        (None, scalar_row("Vertices", object.vertices)),
+                        (None, scalar_row("Triangles", object.triangles)),
+                        (None, scalar_row("Edges", object.edges)),
+                    ];
+                    if let Some((min, max)) = object.bounds {
+                        rows.push((None, vec3_row("Min", min)));
+                        rows.push((None, vec3_row("Max", max)));
+                    }
+                    rows
+                }
+                None => Vec::new(),
+            },
+            None => Vec::new(),
+        };
+
+        let (properties, rows): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
+        self.properties = properties;
+        self.table.set_rows(rows);
+    }
+
+    fn set_table_focused(&mut self, table_focused: bool) {
+        self.table_focused = table_focused;
+        self.tree.on_focus_change(self.focused && !table_focused);
+        self.table.on_focus_change(self.focused && table_focused);
+    }
+}
+
+impl Default for SceneInspector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn row<const N: usize>(property: &str, values: [f32; N]) -> Vec<Line> {
+    let mut row = vec![Line::raw(property)];
+    row.extend(values.map(|value| Line::raw(format!("{:.3}", value))));
+    row
+}
+
+fn vec3_row(property: &str, value: Vec3) -> Vec<Line> {
+    row(property, [value.x, value.y, value.z])
+}
+
+fn scalar_row(property: &str, value: usize) -> Vec<Line> {
+    vec![Line::raw(property), Line::raw(value.to_string())]
+}
+
+impl Panel for SceneInspector {
+    fn tick(&mut self, key_events: &ui::InputStream) {
+        if key_events.consume_key_code(KeyCode::Char('t')) {
+            self.set_table_focused(!self.table_focused);
+        }
+
+        // The table starts below the tree.
+        let tree_height = self.tree_height as i32;
+        let pressed_in = |table: bool| {
+            key_events.mouse_events().any(|event| {
+                event.kind == MouseEventKind::Down(MouseButton::Left)
+                    && (event.position.y >= tree_height) == table
+            })
+        };
+        if pressed_in(!self.table_focused) {
+            self.set_table_focused(!self.table_focused);
+        }
+
+        let tree_events = key_events.derive(!self.table_focused, |event| {
+            (event.position.y < tree_height).then_some(*event)
+        });
+        let table_events = key_events.derive(self.table_focused, |event| {
+            (event.position.y >= tree_height).then(|| event.relative_to(ivec2(0, tree_height)))
+        });
+
+        let selection = self.selection();
+        self.tree.tick(&tree_events);
+        self.table.tick(&table_events);
+        if self.selection() != selection {
+            self.update_table();
+        }
+
+        if self.table_focused {
+            for (key, steps) in [('+', 1.), ('-', -1.), (']', 10.), ('[', -10.)] {
+                while key_events.consume_key_code(KeyCode::Char(key)) {
+                    self.edit(steps);
+                }
+            }
+        }
+    }
+
+    fn render(&mut self) -> ui::Render {
+        let mut text = self.tree.render().to_text();
+        text.lines.resize(self.tree_height as usize, Line::new());
+        text.extend(self.table.render().to_text());
+
+        ui::Render::from_text(text)
+    }
+
+    /// The tree takes the top half, the table the rest.
+    fn on_resize(&mut self, size: UVec2) {
+        self.tree_height = (size.y / 2).max(1);
+        self.tree.on_resize(uvec2(size.x, self.tree_height));
+        self.table
+            .on_resize(uvec2(size.x, size.y.saturating_sub(self.tree_height)));
+    }
+
+    fn on_focus_change(&mut self, focused: bool) {
+        self.focused = focused;
+        self.set_table_focused(self.table_focused);
+    }
+
+    fn as_any(&self) -> &dyn any::Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn any::Any {
+        self
+    }
+}
+
+mod tests {
+    #[test]
+    fn inspector_edit_test() {
+        use super::*;
+        use crate::input::keymap::{Keymap, KeymapState};
+        use crate::input::{InputEvent, KeyModifiers};
+        use crate::test_data;
+        use approx::*;
+
+        let keys = |codes: &[KeyCode]| {
+            let mut stream = ui::InputStream::from_events(
+                codes
+                    .iter()
+                    .map(|code| InputEvent::new(*code, KeyModifiers::NONE)),
+            );
+            stream.resolve_actions(&Keymap::default(), &mut KeymapState::new());
+            stream
+        };
+
+        let mut scene = Scene::new();
+        scene.add_object(SceneObject::Object(Object::new(
+            ObjMeshLoader::load(test_data::CUBE_OBJ_FILE).unwrap(),
+            Transform::identity(),
+            hsl(0.5, 1., 0.5).into(),
+        )));
+
+        let mut inspector = SceneInspector::new();
+        inspector.on_resize(uvec2(40, 20));
+        inspector.on_focus_change(true);
+        inspector.update(SceneSnapshot::new(&scene));
+        assert_eq!(inspector.selection(), Some(InspectorSelection::Scene));
+
+        // Select the object, then the y rotation in the table.
+        inspector.tick(&keys(&[KeyCode::End]));
+        assert_eq!(inspector.selection(), Some(InspectorSelection::Object(0)));
+        inspector.tick(&keys(&[
+            KeyCode::Char('t'),
+            KeyCode::Down,
+            KeyCode::Right,
+            KeyCode::Right,
+        ]));
+        inspector.tick(&keys(&[KeyCode::Char(']'), KeyCode::Char('-')]));
+
+        let edits = inspector.take_edits();
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].property, EditProperty::Rotation);
+        assert_eq!(edits[0].component, 1);
+        for edit in edits {
+            edit.apply(&mut scene);
+        }
+        let snapshot = SceneSnapshot::new(&scene);
+        assert_abs_diff_eq!(snapshot.objects[0].rotation.y, 45., epsilon = 0.01);
+
+        // The hue wraps around.
+        inspector.update(snapshot);
+        inspector.tick(&keys(&[KeyCode::Down, KeyCode::Down, KeyCode::Left]));
+        for _ in 0..30 {
+            inspector.tick(&keys(&[KeyCode::Char('+')]));
+        }
+        for edit in inspector.take_edits() {
+            edit.apply(&mut scene);
+        }
+        let SceneObject::Object(object) = scene.iter().next().unwrap();
+        assert_abs_diff_eq!(object.color.to_hsl().h, 0.1, epsilon = 0.01);
+    }
+
+    #[test]
+    fn rotation_edit_test() {
+        use super::*;
+        use approx::*;
+
+        let mut scene = Scene::new();
+        let edit = |component: usize, steps: i32| SceneEdit {
+            target: EditTarget::Camera,
+            property: EditProperty::Rotation,
+            component,
+            delta: EditProperty::Rotation.step(component) * steps as f32,
+        };
+        let angles = |scene: &Scene| SceneSnapshot::new(scene).camera.rotation;
+
+        // Each step changes only the angle shown for its component.
+        edit(0, 6).apply(&mut scene);
+        edit(2, -2).apply(&mut scene);
+        edit(1, 3).apply(&mut scene);
+        assert_abs_diff_eq!(angles(&scene), vec3(30., 15., -10.), epsilon = 0.01);
+
+        // The y angle stops short of the gimbal lock at 90°, where the x and
+        // z angles are kept.
+        for _ in 0..36 {
+            edit(1, 1).apply(&mut scene);
+        }
+        assert_abs_diff_eq!(angles(&scene), vec3(30., MAX_PITCH, -10.), epsilon = 0.01);
+        edit(1, -1).apply(&mut scene);
+        assert_abs_diff_eq!(angles(&scene), vec3(30., 84., -10.), epsilon = 0.01);
+    }
+}
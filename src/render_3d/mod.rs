@@ -11,24 +11,34 @@ use std::fmt::Display;
 
 mod buffer;
 mod camera;
+mod camera_controller;
 mod color;
 mod drawers;
+pub mod export;
+mod image_output;
+mod inspector;
 pub mod mesh_loader;
 mod panel;
 mod quaternion;
+mod recording;
 mod renderer;
 mod scene;
 pub mod shader;
-mod transform;
 pub mod shader_fn;
+mod transform;
 
 pub use buffer::*;
 pub use camera::*;
+pub use camera_controller::*;
 pub use color::*;
 pub use drawers::*;
+pub use export::*;
+pub use image_output::*;
+pub use inspector::*;
 pub use mesh_loader::*;
 pub use panel::*;
 pub use quaternion::*;
+pub use recording::*;
 pub use renderer::*;
 pub use scene::*;
 pub use transform::*;
@@ -182,10 +192,7 @@ impl Mesh {
 
         let has_normals = normals.is_some();
 
-        let normals = match normals {
-            Some(normals) => normals,
-            None => Vec::new(),
-        };
+        let normals = normals.unwrap_or_default();
 
         let vertex_colors = match vertex_colors {
             Some(colors) => colors,
@@ -233,15 +240,15 @@ impl Mesh {
         self.vertices.get_with_mut(&vert)
     }
 
-    pub fn verts_iter(&self) -> std::slice::Iter<Vec3> {
+    pub fn verts_iter(&self) -> std::slice::Iter<'_, Vec3> {
         self.vertices.iter()
     }
 
-    pub fn verts_iter_mut(&mut self) -> std::slice::IterMut<Vec3> {
+    pub fn verts_iter_mut(&mut self) -> std::slice::IterMut<'_, Vec3> {
         self.vertices.iter_mut()
     }
 
-    pub fn edges_iter(&self) -> AnyIter<Edge> {
+    pub fn edges_iter(&self) -> AnyIter<'_, Edge> {
         AnyIter::new(
             self.edge_cache
                 .iter()
@@ -249,7 +256,7 @@ impl Mesh {
         )
     }
 
-    pub fn triangles_iter(&self) -> AnyIter<Triangle> {
+    pub fn triangles_iter(&self) -> AnyIter<'_, Triangle> {
         AnyIter::new(
             self.triangles
                 .iter()
@@ -261,11 +268,11 @@ impl Mesh {
         )
     }
 
-    pub fn normals_iter(&self) -> std::slice::Iter<(Vec3, Vec3, Vec3)> {
+    pub fn normals_iter(&self) -> std::slice::Iter<'_, (Vec3, Vec3, Vec3)> {
         self.normals.iter()
     }
 
-    pub fn vertex_colors_iter(&self) -> std::slice::Iter<(Rgb, Rgb, Rgb)> {
+    pub fn vertex_colors_iter(&self) -> std::slice::Iter<'_, (Rgb, Rgb, Rgb)> {
         self.vertex_colors.iter()
     }
 }
@@ -1,4 +1,6 @@
-use crate::input;
+use crate::capabilities::CellSize;
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
 
 use super::*;
 
@@ -7,20 +9,76 @@ pub struct ScenePanel {
     pub scene: Scene,
     pub renderer: Renderer,
     pub debug_object: Option<ObjectId>,
+    /// How the rendered buffer is displayed. The renderer size is measured in
+    /// pixels when using an image protocol.
+    pub output: SceneOutput,
+    /// Every rendered frame is added to the recorder while it is set.
+    pub recorder: Option<GifRecorder>,
+    /// Pixel size of the terminal cells, used to keep rendered pixels close
+    /// to square.
+    pub cell_size: CellSize,
+    /// Moves the scene's camera using the input the panel receives.
+    pub camera_controller: Option<CameraController>,
     timer: DeltaTimer,
     time: f32,
     paused: bool,
+    render_timings: RenderTimings,
 }
 
 impl ScenePanel {
     pub fn new(scene: Scene) -> Self {
+        Self::with_renderer(scene, Renderer::new())
+    }
+
+    /// Create a panel using `renderer`, for example one created with
+    /// `Renderer::with_size` when not running in a terminal.
+    pub fn with_renderer(scene: Scene, renderer: Renderer) -> Self {
         Self {
             scene,
-            renderer: Renderer::new(),
+            renderer,
             debug_object: None,
+            output: SceneOutput::Cells,
+            recorder: None,
+            cell_size: CellSize::FALLBACK,
+            camera_controller: None,
             timer: DeltaTimer::new(),
             time: 0.,
             paused: false,
+            render_timings: RenderTimings::new(),
+        }
+    }
+
+    /// How many columns every rendered pixel is stretched over, so it's about
+    /// as wide as it is tall.
+    pub fn columns_per_pixel(&self) -> usize {
+        let ratio = self.cell_size.height as f32 / self.cell_size.width as f32;
+        (ratio.round() as usize).max(1)
+    }
+
+    /// Width divided by height of a rendered pixel, as shown on screen.
+    /// Pixels sent as an image are square.
+    pub fn pixel_aspect_ratio(&self) -> f32 {
+        match self.output {
+            SceneOutput::Cells => self.columns_per_pixel() as f32 * self.cell_size.aspect_ratio(),
+            SceneOutput::Image { .. } => 1.,
+        }
+    }
+
+    /// Resize the renderer to fill `columns` by `rows` cells.
+    pub fn fit_to_cells(&mut self, columns: u16, rows: u16) {
+        let width = columns as usize / self.columns_per_pixel();
+
+        self.renderer.set_size(
+            NonZeroUsize::new(width.max(1)).unwrap(),
+            NonZeroUsize::new((rows as usize).max(1)).unwrap(),
+        );
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+
+        if !self.paused {
+            self.timer.restart();
         }
     }
 
@@ -29,44 +87,144 @@ impl ScenePanel {
     }
 }
 
+/// Render durations, logged as an average about once a second rather than
+/// every frame.
+#[derive(Debug)]
+struct RenderTimings {
+    frames: u32,
+    total: Duration,
+    last_report: Instant,
+}
+
+impl RenderTimings {
+    const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+    fn new() -> Self {
+        Self {
+            frames: 0,
+            total: Duration::ZERO,
+            last_report: Instant::now(),
+        }
+    }
+
+    fn record(&mut self, duration: Duration, (width, height): (usize, usize)) {
+        self.frames += 1;
+        self.total += duration;
+
+        if self.last_report.elapsed() < Self::REPORT_INTERVAL {
+            return;
+        }
+
+        let average = self.total.as_secs_f64() * 1000. / self.frames as f64;
+        log::debug!(
+            target: "render",
+            "{}x{}: {:.2} ms per frame over {} frames",
+            width,
+            height,
+            average,
+            self.frames
+        );
+        *self = Self::new();
+    }
+}
+
 impl ui::Panel for ScenePanel {
     fn tick(&mut self, key_events: &ui::InputStream) {
-        if key_events.contains_key_code(input::KeyCode::Char(' ')) {
-            self.paused = !self.paused;
+        if let Some(controller) = &mut self.camera_controller {
+            controller.handle_input(key_events, &mut self.scene.camera, self.cell_size);
+        }
 
-            if !self.paused {
-                self.timer.restart();
-            }
+        if key_events.consume_action("scene.pause") {
+            self.toggle_pause();
         }
 
         if self.paused {
             return;
         }
 
-        let SceneObject::Object(object) = self
-            .scene
-            .get_object_mut(self.debug_object.unwrap())
-            .unwrap();
+        let Some(SceneObject::Object(object)) = self
+            .debug_object
+            .and_then(|debug_object| self.scene.get_object_mut(debug_object))
+        else {
+            return;
+        };
 
         self.time += self.timer.delta_s();
 
         object.transform.rotation =
             Quaternion::from_euler_angles(self.time * 2., self.time / 2., self.time);
     }
+    fn commands(&self) -> Vec<ui::CommandInfo> {
+        let pause_title = match self.paused {
+            true => "Resume animation",
+            false => "Pause animation",
+        };
+        let mut commands = vec![ui::CommandInfo::new("scene.pause", pause_title)];
+
+        if self.camera_controller.is_some() {
+            commands.push(ui::CommandInfo::new(
+                "camera.toggle_mode",
+                "Switch between orbit and fly camera",
+            ));
+        }
+        commands
+    }
+
+    fn run_command(&mut self, name: &str) {
+        match name {
+            "scene.pause" => self.toggle_pause(),
+            "camera.toggle_mode" => {
+                if let Some(controller) = &mut self.camera_controller {
+                    controller.toggle_mode();
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn render(&mut self) -> ui::Render {
         let (width, height) = self.renderer.get_size();
 
         self.scene
             .camera
-            .set_aspect_ratio(width as f32 / height as f32);
+            .set_aspect_ratio(width as f32 * self.pixel_aspect_ratio() / height as f32);
 
+        let before = Instant::now();
         self.renderer.clear();
         self.renderer.render_scene(&self.scene);
+        self.render_timings
+            .record(before.elapsed(), (width, height));
         // self.renderer.render_test();
         let pixel_buffer = self.renderer.buffer();
 
+        if let Some(recorder) = &mut self.recorder {
+            recorder.push_frame(pixel_buffer);
+        }
+
         let mut text_buffer = ui::RenderBuffer::new();
 
+        if let SceneOutput::Image {
+            protocol,
+            columns,
+            rows,
+            image_id,
+        } = self.output
+        {
+            text_buffer.push(&get_image_sequence(
+                pixel_buffer,
+                protocol,
+                columns,
+                rows,
+                image_id,
+            ));
+            // Reserve the cells covered by the image.
+            for _ in 1..rows {
+                text_buffer.push("\r\n");
+            }
+
+            return ui::Render::new(text_buffer);
+        }
+
         for row in pixel_buffer.color.iter().rev() {
             let run_length_colors = row.run_length_encoding();
             for (color, length) in run_length_colors {
@@ -78,7 +236,7 @@ impl ui::Panel for ScenePanel {
                     color.r, color.g, color.b,
                 ))));
 
-                text_buffer.push(&" ".repeat(length * 2));
+                text_buffer.push(&" ".repeat(length * self.columns_per_pixel()));
             }
             text_buffer.push(&format::get_format(Format::Reset));
             text_buffer.push("\r\n");
@@ -86,6 +244,28 @@ impl ui::Panel for ScenePanel {
 
         ui::Render::new(text_buffer)
     }
+    /// Resizes the renderer to fill the panel. With an image output, the image
+    /// is resized to cover the panel as well.
+    fn on_resize(&mut self, size: UVec2) {
+        let (columns, rows) = (size.x.max(1) as u16, size.y.max(1) as u16);
+
+        match &mut self.output {
+            SceneOutput::Cells => self.fit_to_cells(columns, rows),
+            SceneOutput::Image {
+                columns: image_columns,
+                rows: image_rows,
+                ..
+            } => {
+                *image_columns = columns;
+                *image_rows = rows;
+
+                self.renderer.set_size(
+                    NonZeroUsize::new(columns as usize * self.cell_size.width as usize).unwrap(),
+                    NonZeroUsize::new(rows as usize * self.cell_size.height as usize).unwrap(),
+                );
+            }
+        }
+    }
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -93,3 +273,49 @@ impl ui::Panel for ScenePanel {
         self
     }
 }
+
+mod tests {
+    #[test]
+    fn pixel_aspect_ratio_test() {
+        use super::*;
+
+        let size = NonZeroUsize::new(10).unwrap();
+        let mut panel = ScenePanel::with_renderer(Scene::new(), Renderer::with_size(size, size));
+        panel.cell_size = CellSize {
+            width: 8,
+            height: 18,
+        };
+        assert_eq!(panel.pixel_aspect_ratio(), 2. * 8. / 18.);
+
+        panel.output = SceneOutput::image(ImageProtocol::Kitty, 10, 5);
+        assert_eq!(panel.pixel_aspect_ratio(), 1.);
+    }
+
+    #[test]
+    fn unfocused_scene_keys_test() {
+        use super::*;
+        use crate::input::ScriptedInputSource;
+
+        // The text input is focused first, so it gets the keys bound to the
+        // camera and to pausing.
+        let source = ScriptedInputSource::parse("text w m").unwrap();
+        let mut gui = ui::Gui::headless(uvec2(40, 10), source);
+        let input = gui.add_panel(uvec2(1, 1), ui::TextInput::new());
+        let size = NonZeroUsize::new(10).unwrap();
+        let mut panel = ScenePanel::with_renderer(Scene::new(), Renderer::with_size(size, size));
+        panel.camera_controller = Some(CameraController::orbit(vec3(0., 0., 0.), 2.));
+        let scene = gui.add_panel(uvec2(1, 3), panel);
+        gui.tick();
+
+        assert_eq!(
+            gui.get_panel::<ui::TextInput>(&input).unwrap().text(),
+            "w m"
+        );
+        let panel = gui.get_panel::<ScenePanel>(&scene).unwrap();
+        assert!(!panel.paused);
+        assert_eq!(
+            panel.camera_controller.as_ref().unwrap().mode,
+            CameraMode::Orbit
+        );
+    }
+}
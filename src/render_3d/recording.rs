@@ -0,0 +1,105 @@
+//! Recording of rendered frames into animated gif files.
+
+use super::*;
+use crate::encoding::gif::GifEncoder;
+use crate::utils::DeltaTimer;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Delay used for the last frame of a recording, as there is no following
+/// frame to measure it against.
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(50);
+
+/// Collects render buffers into an animated gif.
+///
+/// Frames with a different size than the first frame are skipped, as gif
+/// animations have a fixed size.
+#[derive(Debug)]
+pub struct GifRecorder {
+    encoder: Option<GifEncoder>,
+    /// The last frame, which is written once its delay is known.
+    pending: Option<(Vec<u8>, Option<Duration>)>,
+    timer: DeltaTimer,
+}
+
+impl GifRecorder {
+    pub fn new() -> Self {
+        Self {
+            encoder: None,
+            pending: None,
+            timer: DeltaTimer::new(),
+        }
+    }
+
+    /// Add a frame, which is shown until the next frame is pushed.
+    pub fn push_frame(&mut self, buffer: &RenderBuffer) {
+        self.push(buffer, None);
+    }
+
+    /// Add a frame which is shown for `delay`, for example when rendering
+    /// with a fixed time step.
+    pub fn push_frame_with_delay(&mut self, buffer: &RenderBuffer, delay: Duration) {
+        self.push(buffer, Some(delay));
+    }
+
+    /// The amount of recorded frames.
+    pub fn frame_count(&self) -> usize {
+        self.encoder
+            .as_ref()
+            .map_or(0, |encoder| encoder.frame_count())
+            + self.pending.is_some() as usize
+    }
+
+    fn push(&mut self, buffer: &RenderBuffer, delay: Option<Duration>) {
+        let elapsed = self.timer.delta_time();
+
+        let (width, height) = (buffer.get_width() as u16, buffer.get_height() as u16);
+        let encoder = self
+            .encoder
+            .get_or_insert_with(|| GifEncoder::new(width, height));
+
+        if (encoder.width(), encoder.height()) != (width, height) {
+            return;
+        }
+
+        if let Some((frame, pending_delay)) = self.pending.take() {
+            encoder.add_frame(&frame, to_centis(pending_delay.unwrap_or(elapsed)));
+        }
+
+        self.pending = Some((buffer.to_rgb_bytes(), delay));
+    }
+
+    /// Finish the recording, returning the contents of the gif file, or
+    /// `None` if no frames were recorded.
+    pub fn finish(mut self) -> Option<Vec<u8>> {
+        let mut encoder = self.encoder.take()?;
+
+        if let Some((frame, delay)) = self.pending.take() {
+            encoder.add_frame(&frame, to_centis(delay.unwrap_or(DEFAULT_FRAME_DELAY)));
+        }
+
+        Some(encoder.finish())
+    }
+
+    /// Finish the recording and write it to `path`.
+    pub fn save(self, path: impl AsRef<Path>) -> io::Result<()> {
+        let gif = self
+            .finish()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no frames recorded"))?;
+
+        fs::write(path, gif)
+    }
+}
+
+impl Default for GifRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn to_centis(duration: Duration) -> u16 {
+    // Most viewers ignore delays shorter than two hundredths of a second.
+    (duration.as_millis() / 10).clamp(2, u16::MAX as u128) as u16
+}
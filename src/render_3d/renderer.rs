@@ -20,15 +20,28 @@ pub struct SceneInfo {
 }
 
 impl Renderer {
+    /// Create a renderer filling the terminal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the terminal size couldn't be read, for example when not
+    /// running in a terminal. Use `with_size` in that case.
     pub fn new() -> Self {
         let (mut width, height) = crossterm::terminal::size().expect("couldn't get terminal size");
         width /= 2;
 
-        let buffer = RenderBuffer::new(
+        Self::with_size(
             NonZeroUsize::new(width as usize).unwrap(),
             NonZeroUsize::new(height as usize).unwrap(),
-        );
-        Renderer { buffer }
+        )
+    }
+
+    /// Create a renderer with a buffer of `width` by `height` pixels, without
+    /// needing a terminal.
+    pub fn with_size(width: NonZeroUsize, height: NonZeroUsize) -> Self {
+        Renderer {
+            buffer: RenderBuffer::new(width, height),
+        }
     }
 
     fn normalized_to_buffer_space(&self, point: Vec2) -> UVec2 {
@@ -53,19 +66,26 @@ impl Renderer {
         &self.buffer
     }
 
+    /// Resize the buffer to fill the terminal, like `new`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the terminal size couldn't be read.
     pub fn update_size(&mut self) {
-        let (height, width) = crossterm::terminal::size().expect("Couldn't get terminal size");
+        let (width, height) = crossterm::terminal::size().expect("Couldn't get terminal size");
 
-        let buffer = RenderBuffer::new(
-            NonZeroUsize::new(width as usize).unwrap(),
-            NonZeroUsize::new(height as usize).unwrap(),
+        self.set_size(
+            NonZeroUsize::new((width as usize / 2).max(1)).unwrap(),
+            NonZeroUsize::new((height as usize).max(1)).unwrap(),
         );
-
-        self.buffer = buffer;
     }
 
+    /// Reallocate the buffer with a new size. Does nothing if the size is
+    /// unchanged.
     pub fn set_size(&mut self, width: NonZeroUsize, height: NonZeroUsize) {
-        self.buffer = RenderBuffer::new(width, height)
+        if self.get_size() != (width.get(), height.get()) {
+            self.buffer = RenderBuffer::new(width, height)
+        }
     }
 
     /// Returns the number of pixels as `(width, height)`.
@@ -219,21 +239,21 @@ impl Renderer {
                     RenderVertex {
                         pos: projected_a.xy(),
                         albedo: object.color,
-                        depth: -projected_a.z as f32,
+                        depth: -projected_a.z,
                         normal: tri.normals.0,
                         vertex_color: tri.vertex_colors.0,
                     },
                     RenderVertex {
                         pos: projected_b.xy(),
                         albedo: object.color,
-                        depth: -projected_b.z as f32,
+                        depth: -projected_b.z,
                         normal: tri.normals.1,
                         vertex_color: tri.vertex_colors.1,
                     },
                     RenderVertex {
                         pos: projected_c.xy(),
                         albedo: object.color,
-                        depth: -projected_c.z as f32,
+                        depth: -projected_c.z,
                         normal: tri.normals.2,
                         vertex_color: tri.vertex_colors.2,
                     },
@@ -58,11 +58,17 @@ impl Scene {
         Some(&mut item.1)
     }
 
-    pub fn iter(&self) -> AnyIter<&SceneObject> {
+    pub fn iter(&self) -> AnyIter<'_, &SceneObject> {
         let iter = self.objects.iter().map(|(_, object)| object);
 
         AnyIter::new(iter)
     }
+
+    pub fn iter_mut(&mut self) -> AnyIter<'_, &mut SceneObject> {
+        let iter = self.objects.iter_mut().map(|(_, object)| object);
+
+        AnyIter::new(iter)
+    }
 }
 
 impl Default for Scene {